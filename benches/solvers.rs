@@ -0,0 +1,91 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, Bencher, BenchmarkId, Criterion,
+    Throughput,
+};
+use egui_test::model::{
+    compare::compare_models,
+    differential::*,
+    model::{Coefficient, InitialCondition, Model},
+    system::{SystemModel, SystemOptions},
+};
+
+const LENGTH: f64 = 200.;
+const NODE_COUNTS: [u32; 4] = [50, 100, 150, 300];
+
+fn expr(s: &str) -> exmex::FlatEx<f64> {
+    exmex::parse::<f64>(s).unwrap()
+}
+
+/// A step well inside the explicit stability limit, so the nodes stay finite however long it runs
+fn stable_step(node_count: u32) -> f64 {
+    let h = LENGTH / (node_count - 1) as f64;
+    0.4 * h * h
+}
+
+fn differential(node_count: u32) -> DifferentialModel {
+    DifferentialModel::new(
+        InitialCondition::Expr(Box::new(expr("100*sin(PI*x/200)"))),
+        expr("0*t"),
+        expr("0*t"),
+        Coefficient::Expr(Box::new(expr("1+0*x"))),
+        LENGTH,
+        DifferentialOptions::new(node_count, stable_step(node_count)),
+    )
+    .unwrap()
+}
+
+fn system(node_count: u32) -> SystemModel {
+    SystemModel::new(
+        InitialCondition::Expr(Box::new(expr("100*sin(PI*x/200)"))),
+        expr("0*t"),
+        expr("0*t"),
+        expr("1+0*x"),
+        LENGTH,
+        SystemOptions::new(node_count, 1.),
+    )
+    .unwrap()
+}
+
+/// Times one step from a copy of `model` after its first step, which is where the system
+/// model factors its matrix. Stepping the same model on and on would decay the nodes into
+/// denormals, which are much slower to compute with
+fn step_from<M: Model + Clone>(b: &mut Bencher, model: &M) {
+    let mut model = model.clone();
+    model.run_step().unwrap();
+    b.iter_batched_ref(
+        || model.clone(),
+        |m| m.run_step().unwrap(),
+        BatchSize::SmallInput,
+    );
+}
+
+fn run_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run_step");
+    // One element per iteration, so criterion reports steps per second
+    group.throughput(Throughput::Elements(1));
+    for node_count in NODE_COUNTS {
+        let model = differential(node_count);
+        group.bench_with_input(
+            BenchmarkId::new("differential", node_count),
+            &node_count,
+            |b, _| step_from(b, &model),
+        );
+        let model = system(node_count);
+        group.bench_with_input(
+            BenchmarkId::new("system", node_count),
+            &node_count,
+            |b, _| step_from(b, &model),
+        );
+    }
+    group.finish();
+}
+
+fn compare(c: &mut Criterion) {
+    let (model_1, model_2) = (differential(300), system(300));
+    c.bench_function("compare_models/300", |b| {
+        b.iter(|| compare_models(black_box(&model_1), black_box(&model_2)))
+    });
+}
+
+criterion_group!(benches, run_step, compare);
+criterion_main!(benches);
@@ -1,43 +1,108 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::app::model_manager::ModelManager;
-use crate::ticker::Ticker;
+use crate::ticker::{TickStats, Ticker};
 use crate::{call, window::window::Window};
 
-use super::model_manager::ModelInfo;
+use super::model_manager::{ConvergenceStudy, ErrorMap, ModelInfo};
+#[cfg(feature = "server")]
+use super::server::Server;
+use super::session::write_history;
 use super::ui::*;
+use super::view::{picked_color, StripLayout};
+use crate::model::model::{interpolate_positions, Units};
 use crate::renderer::{
-    error::Error, renderer::BatchRenderer, shader::Shader, vertex::VertexLayout,
+    error::Error, renderer::BatchRenderer, shader::Shader, texture::Texture, vertex::VertexLayout,
 };
 use nalgebra::Matrix4;
 
-const VERT_SRC: &'static str = r#"
+const VERT_SRC: &str = r#"
 #version 400 core
 layout(location = 0) in vec4 vertInPosition;
-layout(location = 1) in vec4 vertInColor; 
+layout(location = 1) in float vertInTemperature;
+layout(location = 2) in vec3 vertInTint;
 uniform mat4 uMVP;
 
 out VertexData 
 {
     vec4 position;
-    vec4 color;
+    float temperature;
+    vec3 tint;
 } vertOut;
 
 void main()
 {
     gl_Position = uMVP * vertInPosition;
 
-    vertOut.color = vertInColor;
+    vertOut.temperature = vertInTemperature;
     vertOut.position = vertInPosition;
+    vertOut.tint = vertInTint;
 }
 "#;
 
-const FRAG_SRC: &'static str = r#"#version 400 core
+const FRAG_SRC: &str = r#"#version 400 core
 
 in VertexData
 {
     vec4 position;
+    float temperature;
+    vec3 tint;
+} fragIn;
+uniform float uMinTemperature;
+uniform float uMaxTemperature;
+// 0 linear, 1 log, 2 signed log
+uniform int uColorScale;
+uniform float uLogEpsilon;
+// Seconds since the app started, for animated effects
+uniform float uTime;
+// Brightness swing of a once-a-second pulse, 0 turns it off
+uniform float uPulse;
+out vec4 color;
+
+void main()
+{
+    float t;
+    if (uColorScale == 1) {
+        float lo = log(max(uMinTemperature, uLogEpsilon));
+        float hi = log(max(uMaxTemperature, uLogEpsilon));
+        t = (log(max(fragIn.temperature, uLogEpsilon)) - lo) / (hi - lo);
+    } else if (uColorScale == 2) {
+        float lo = sign(uMinTemperature) * log(1.0 + abs(uMinTemperature));
+        float hi = sign(uMaxTemperature) * log(1.0 + abs(uMaxTemperature));
+        float u = fragIn.temperature;
+        t = (sign(u) * log(1.0 + abs(u)) - lo) / (hi - lo);
+    } else {
+        t = (fragIn.temperature - uMinTemperature) / (uMaxTemperature - uMinTemperature);
+    }
+    float pulse = 1.0 + uPulse * sin(6.2831853 * uTime);
+    color = vec4(clamp(t, 0.0, 1.0) * fragIn.tint * pulse, 1.0);
+}"#;
+
+const LINE_VERT_SRC: &str = r#"
+#version 400 core
+layout(location = 0) in vec4 vertInPosition;
+layout(location = 1) in vec4 vertInColor;
+uniform mat4 uMVP;
+
+out VertexData
+{
+    vec4 color;
+} vertOut;
+
+void main()
+{
+    gl_Position = uMVP * vertInPosition;
+    vertOut.color = vertInColor;
+}
+"#;
+
+const LINE_FRAG_SRC: &str = r#"#version 400 core
+
+in VertexData
+{
     vec4 color;
 } fragIn;
 out vec4 color;
@@ -47,16 +112,98 @@ void main()
     color = fragIn.color;
 }"#;
 
-fn get_node_color(node: f64) -> (f32, f32, f32, f32) {
-    (node as f32 / 100., 0., 0., 1.)
+const MAP_VERT_SRC: &str = r#"
+#version 400 core
+layout(location = 0) in vec4 vertInPosition;
+layout(location = 1) in vec2 vertInUv;
+layout(location = 2) in float vertInScale;
+layout(location = 3) in vec3 vertInTint;
+uniform mat4 uMVP;
+
+out VertexData
+{
+    vec2 uv;
+    float scale;
+    vec3 tint;
+} vertOut;
+
+void main()
+{
+    gl_Position = uMVP * vertInPosition;
+    vertOut.uv = vertInUv;
+    vertOut.scale = vertInScale;
+    vertOut.tint = vertInTint;
+}
+"#;
+
+const MAP_FRAG_SRC: &str = r#"#version 400 core
+
+in VertexData
+{
+    vec2 uv;
+    float scale;
+    vec3 tint;
+} fragIn;
+uniform sampler2D uTexture;
+out vec4 color;
+
+void main()
+{
+    float t = texture(uTexture, fragIn.uv).r * fragIn.scale;
+    color = vec4(clamp(t, 0.0, 1.0) * fragIn.tint, 1.0);
+}"#;
+
+const VIEW_WIDTH: f32 = 640.;
+const VIEW_HEIGHT: f32 = 480.;
+/// Error maps are drawn from the top of the view down, this tall and this far apart
+const FIRST_MAP_TOP: f32 = -200.;
+const MAP_HEIGHT: f32 = 120.;
+const MAP_SPACING: f32 = 160.;
+/// A heatmap node takes 12 floats, so this keeps a push well under the `u16::MAX` batch size
+const MAX_NODES_PER_PUSH: usize = 4096;
+
+fn nodes_to_lines(
+    nodes: &[f64],
+    positions: &[f64],
+    length: f64,
+    height: f32,
+    color_range: (f32, f32),
+    color: (f32, f32, f32, f32),
+) -> (Vec<f32>, Vec<u16>) {
+    let mut inds = vec![];
+    let mut verts = vec![];
+
+    let node_count = nodes.len();
+    let (min, max) = color_range;
+    let (r, g, b, a) = color;
+
+    let left = -length as f32 / 2.;
+    let bottom = height / 2.;
+
+    for (node, position) in nodes.iter().zip(positions) {
+        verts.push(left + *position as f32);
+        verts.push(bottom - (*node as f32 - min) / (max - min) * height);
+        verts.push(r);
+        verts.push(g);
+        verts.push(b);
+        verts.push(a);
+    }
+
+    for i in 0..(node_count as u16 - 1) {
+        inds.push(i);
+        inds.push(i + 1);
+    }
+
+    (verts, inds)
 }
 
 fn nodes_to_verts(
     nodes: &[f64],
+    positions: &[f64],
     length: f64,
     height: f32,
     offset: (f32, f32),
-    index_offset: u16,
+    tint: (f32, f32, f32),
 ) -> (Vec<f32>, Vec<u16>) {
     let mut inds = vec![];
     let mut verts = vec![];
@@ -66,30 +213,21 @@ fn nodes_to_verts(
     let (x, y) = offset;
 
     let left = -length as f32 / 2. + x;
-    let step = length as f32 / (node_count as f32 - 1.);
     let top = -height / 2. + y;
     let bottom = height / 2. + y;
 
-    let mut i = 0;
-    for node in nodes {
-        let (r, g, b, a) = get_node_color(*node);
-        verts.push(left + i as f32 * step);
-        verts.push(top);
-        verts.push(r);
-        verts.push(g);
-        verts.push(b);
-        verts.push(a);
-
-        verts.push(left + i as f32 * step);
-        verts.push(bottom);
-        verts.push(r);
-        verts.push(g);
-        verts.push(b);
-        verts.push(a);
-        i += 1;
+    for (node, position) in nodes.iter().zip(positions) {
+        for y in [top, bottom] {
+            verts.push(left + *position as f32);
+            verts.push(y);
+            verts.push(*node as f32);
+            verts.push(tint.0);
+            verts.push(tint.1);
+            verts.push(tint.2);
+        }
     }
 
-    for i in index_offset..(index_offset + node_count as u16 - 1) {
+    for i in 0..(node_count as u16 - 1) {
         inds.push(2 * i);
         inds.push(2 * i + 1);
         inds.push(2 * i + 2);
@@ -101,10 +239,319 @@ fn nodes_to_verts(
     (verts, inds)
 }
 
+/// Moving average over `2 * radius + 1` nodes, cut short at the edges.
+/// Only used for display, the models never see it
+fn smooth_nodes(nodes: &[f64], radius: usize) -> Vec<f64> {
+    if radius == 0 {
+        return nodes.to_vec();
+    }
+    (0..nodes.len())
+        .map(|i| {
+            let window = &nodes[i.saturating_sub(radius)..(i + radius + 1).min(nodes.len())];
+            window.iter().sum::<f64>() / window.len() as f64
+        })
+        .collect()
+}
+
+/// Splits `node_count` nodes into overlapping `start..=end` ranges of at most
+/// `max_nodes` nodes, so that each one fits into a single render batch
+fn node_chunks(node_count: usize, max_nodes: usize) -> Vec<(usize, usize)> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start + 1 < node_count {
+        let end = (start + max_nodes - 1).min(node_count - 1);
+        chunks.push((start, end));
+        start = end;
+    }
+    chunks
+}
+
+/// A horizontal heatmap strip, either a model or the difference between two
+struct Row<'a> {
+    label: String,
+    nodes: &'a [f64],
+    positions: &'a [f64],
+    length: f64,
+    color: (f32, f32, f32, f32),
+    units: &'a Units,
+    is_difference: bool,
+}
+
+/// Pinned snapshots are drawn in a darker shade of their model's color
+fn pin_color(color: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+    let (r, g, b, a) = color;
+    (r * 0.5, g * 0.5, b * 0.5, a)
+}
+
+fn layout_rows(
+    model_info: &[ModelInfo],
+    show_differences: bool,
+    color: impl Fn(&ModelInfo) -> (f32, f32, f32, f32),
+) -> Vec<Row<'_>> {
+    let mut rows = vec![];
+    for m in model_info {
+        rows.push(Row {
+            label: m.name.clone(),
+            nodes: &m.nodes,
+            positions: &m.positions,
+            length: m.length,
+            color: color(m),
+            units: &m.units,
+            is_difference: false,
+        });
+        if let Some((other, difference)) = m.difference.as_ref().filter(|_| show_differences) {
+            rows.push(Row {
+                label: format!("{} - {}", m.name, other),
+                nodes: difference,
+                positions: &m.positions,
+                length: m.length,
+                color: (1., 1., 1., 1.),
+                units: &m.units,
+                is_difference: true,
+            });
+        }
+        for pin in &m.pins {
+            rows.push(Row {
+                label: format!("{} @ t = {:.2}", m.name, pin.time),
+                nodes: &pin.nodes,
+                positions: &pin.positions,
+                length: m.length,
+                color: pin_color(color(m)),
+                units: &m.units,
+                is_difference: false,
+            });
+        }
+    }
+    rows
+}
+
+/// `row` at `count` evenly spaced points between the first and last position,
+/// so a graded grid doesn't bunch up in the texture
+fn resample_evenly(positions: &[f64], row: &[f64], count: usize) -> Vec<f32> {
+    if positions.len() != row.len() || count < 2 {
+        return row.iter().map(|&d| d as f32).collect();
+    }
+    let (first, last) = (positions[0], positions[positions.len() - 1]);
+    (0..count)
+        .map(|k| {
+            let x = first + (last - first) * k as f64 / (count - 1) as f64;
+            interpolate_positions(positions, row, x.min(last)).unwrap_or(0.) as f32
+        })
+        .collect()
+}
+
+/// Models that have recorded at least one row of their error map
+fn error_maps(model_info: &[ModelInfo]) -> Vec<(&ModelInfo, &ErrorMap)> {
+    model_info
+        .iter()
+        .filter_map(|m| m.error_map.as_ref().map(|map| (m, map)))
+        .filter(|(_, map)| !map.rows.is_empty())
+        .collect()
+}
+
+/// Packs the error maps into one texture, each in a band of rows, oldest row first.
+/// Rows are as wide as the widest map, the narrower ones are padded with zeros.
+/// Returns the width, the height and the values
+fn pack_error_maps(maps: &[(&ModelInfo, &ErrorMap)]) -> (usize, usize, Vec<f32>) {
+    let width = maps
+        .iter()
+        .flat_map(|(_, map)| map.rows.iter().map(|(_, row)| row.len()))
+        .max()
+        .unwrap_or(0);
+    let mut data = vec![];
+    for (m, map) in maps {
+        for (_, row) in &map.rows {
+            let mut row = resample_evenly(&m.positions, row, row.len());
+            row.resize(width, 0.);
+            data.extend(row);
+        }
+    }
+    let height = data.len().checked_div(width).unwrap_or(0);
+    (width, height, data)
+}
+
+/// A quad for every map packed by `pack_error_maps`, each normalized by its own peak
+fn error_map_quads(
+    maps: &[(&ModelInfo, &ErrorMap)],
+    size: (usize, usize),
+    color: impl Fn(&ModelInfo) -> (f32, f32, f32, f32),
+) -> (Vec<f32>, Vec<u16>) {
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    let mut verts = vec![];
+    let mut inds = vec![];
+    let mut band = 0;
+    for (i, (m, map)) in maps.iter().enumerate() {
+        let columns = map.rows.back().map_or(0, |(_, row)| row.len()) as f32;
+        let rows = map.rows.len() as f32;
+        // Through the middle of the outer texels, so the bands don't bleed into each other
+        let (u0, u1) = (0.5 / width, (columns - 0.5) / width);
+        let (v0, v1) = (
+            (band as f32 + 0.5) / height,
+            (band as f32 + rows - 0.5) / height,
+        );
+        band += map.rows.len();
+
+        let peak = map.peak();
+        let scale = if peak > 0. { 1. / peak as f32 } else { 0. };
+        let (r, g, b, _) = color(m);
+        let left = -m.length as f32 / 2.;
+        let top = FIRST_MAP_TOP + i as f32 * MAP_SPACING;
+        let first = (verts.len() / 8) as u16;
+        for (x, y, u, v) in [
+            (left, top, u0, v0),
+            (-left, top, u1, v0),
+            (left, top + MAP_HEIGHT, u0, v1),
+            (-left, top + MAP_HEIGHT, u1, v1),
+        ] {
+            verts.extend([x, y, u, v, scale, r, g, b]);
+        }
+        inds.extend([first, first + 1, first + 2, first + 2, first + 3, first + 1]);
+    }
+    (verts, inds)
+}
+
+/// Names every error map above it with its time range and the peak it is scaled by
+fn draw_error_map_labels(
+    ctx: &egui::CtxRef,
+    maps: &[(&ModelInfo, &ErrorMap)],
+    color: impl Fn(&ModelInfo) -> (f32, f32, f32, f32),
+) {
+    let screen = ctx.input().screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (i, (m, map)) in maps.iter().enumerate() {
+        let (start, end) = map.time_range().unwrap_or_default();
+        let x = -m.length as f32 / 2.;
+        let y = FIRST_MAP_TOP + i as f32 * MAP_SPACING - 4.;
+        let (red, green, blue, _) = color(m);
+        painter.text(
+            egui::pos2(
+                (x / VIEW_WIDTH + 0.5) * screen.width(),
+                (y / VIEW_HEIGHT + 0.5) * screen.height(),
+            ),
+            egui::Align2::LEFT_BOTTOM,
+            format!(
+                "|{} - {}|, t = {:.2} (top) to {:.2} (bottom), peak {:.3e}",
+                m.name,
+                map.other,
+                start,
+                end,
+                map.peak()
+            ),
+            egui::TextStyle::Small,
+            egui::Color32::from_rgb(
+                (red * 255.) as u8,
+                (green * 255.) as u8,
+                (blue * 255.) as u8,
+            ),
+        );
+    }
+}
+
+/// Spreads the magnitude of a difference over the whole color range
+fn difference_to_temperature(difference: &[f64], color_range: (f32, f32)) -> Vec<f64> {
+    let (min, max) = color_range;
+    let peak = difference.iter().fold(0., |acc: f64, d| acc.max(d.abs()));
+    difference
+        .iter()
+        .map(|d| {
+            let t = if peak > 0. { d.abs() / peak } else { 0. };
+            min as f64 + t * (max - min) as f64
+        })
+        .collect()
+}
+
+/// Writes every strip's label left of it, in the strip's color
+fn draw_row_labels(ctx: &egui::CtxRef, rows: &[Row], layout: StripLayout) {
+    let screen = ctx.input().screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (i, r) in rows.iter().enumerate() {
+        let x = -r.length as f32 / 2. - 6.;
+        let y = layout.strip_y(i);
+        let (red, green, blue, _) = r.color;
+        painter.text(
+            egui::pos2(
+                (x / VIEW_WIDTH + 0.5) * screen.width(),
+                (y / VIEW_HEIGHT + 0.5) * screen.height(),
+            ),
+            egui::Align2::RIGHT_CENTER,
+            &r.label,
+            egui::TextStyle::Small,
+            egui::Color32::from_rgb(
+                (red * 255.) as u8,
+                (green * 255.) as u8,
+                (blue * 255.) as u8,
+            ),
+        );
+    }
+}
+
+/// Finds the heatmap strip under `pos` and describes the temperature there
+fn hover_readout(rows: &[Row], layout: StripLayout, pos: (f32, f32)) -> Option<String> {
+    let (x, y) = pos;
+    rows.iter().enumerate().find_map(|(i, r)| {
+        if (y - layout.strip_y(i)).abs() > layout.height / 2. {
+            return None;
+        }
+        let position = x as f64 + r.length / 2.;
+        interpolate_positions(r.positions, r.nodes, position).map(|t| {
+            format!(
+                "{}\nx = {:.2}{}\nT = {:.4}{}",
+                r.label,
+                position,
+                Units::suffix(&r.units.length),
+                t,
+                Units::suffix(&r.units.temperature)
+            )
+        })
+    })
+}
+
+fn screen_to_world(mouse: (i32, i32), window_size: (u32, u32)) -> (f32, f32) {
+    let (x, y) = mouse;
+    let (width, height) = window_size;
+    (
+        (x as f32 / width as f32 - 0.5) * VIEW_WIDTH,
+        (y as f32 / height as f32 - 0.5) * VIEW_HEIGHT,
+    )
+}
+
+/// Frames being saved as numbered PNGs for a time-lapse
+struct Recording {
+    dir: PathBuf,
+    /// Only every `every`th frame is saved
+    every: usize,
+    frame: usize,
+    saved: usize,
+    started: Instant,
+}
+
+impl Recording {
+    fn frame_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("frame-{:05}.png", index))
+    }
+}
+
 pub struct UiReducer {
     model_manager: Rc<ModelManager>,
     model_info: Rc<Vec<ModelInfo>>,
-    tps: usize,
+    tick_stats: TickStats,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+    /// ffmpeg runs still encoding, with the directory of their frames
+    encodings: Vec<(PathBuf, std::process::Child)>,
+    clear_color: [f32; 3],
+    color_range: (f32, f32),
+    color_scale: ColorScale,
+    smoothing: usize,
+    show_differences: bool,
+    strip_layout: StripLayout,
+    render_mode: RenderMode,
+    errors: Vec<String>,
+    convergence_studies: Vec<ConvergenceStudy>,
+    /// MSAA samples granted when the window was created
+    multisample_samples: u8,
+    /// Colors picked in the model list, the others use the palette
+    model_colors: HashMap<String, [f32; 3]>,
 }
 
 impl UiReducer {
@@ -112,33 +559,230 @@ impl UiReducer {
         Self {
             model_manager,
             model_info: Rc::new(Vec::new()),
-            tps: 0,
+            tick_stats: TickStats::default(),
+            screenshot_requested: false,
+            recording: None,
+            encodings: vec![],
+            clear_color: [0.5, 0.5, 0.5],
+            color_range: (0., 100.),
+            color_scale: ColorScale::Linear,
+            smoothing: 0,
+            show_differences: true,
+            strip_layout: StripLayout::default(),
+            render_mode: RenderMode::Heatmap,
+            errors: vec![],
+            convergence_studies: vec![],
+            multisample_samples: 0,
+            model_colors: HashMap::new(),
         }
     }
 
-    pub fn set_model_info(&mut self, model_info: (Vec<ModelInfo>, usize)) {
-        let (model_info, tps) = model_info;
+    pub fn set_model_info(&mut self, model_info: (Vec<ModelInfo>, TickStats)) {
+        let (model_info, tick_stats) = model_info;
         self.model_info = Rc::new(model_info);
-        self.tps = tps;
+        self.tick_stats = tick_stats;
+    }
+
+    pub fn get_model_info(&self) -> Rc<Vec<ModelInfo>> {
+        self.model_info.clone()
+    }
+
+    pub fn push_errors(&mut self, errors: Vec<String>) {
+        self.errors.extend(errors);
+    }
+
+    pub fn push_convergence_studies(&mut self, studies: Vec<ConvergenceStudy>) {
+        self.convergence_studies.extend(studies);
+    }
+
+    pub fn get_render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    pub fn get_color_range(&self) -> (f32, f32) {
+        self.color_range
+    }
+
+    pub fn get_model_color(&self, model: &ModelInfo) -> (f32, f32, f32, f32) {
+        let [r, g, b] = picked_color(&self.model_colors, model);
+        (r, g, b, 1.)
+    }
+
+    pub fn get_color_scale(&self) -> ColorScale {
+        self.color_scale
+    }
+
+    pub fn get_smoothing(&self) -> usize {
+        self.smoothing
+    }
+
+    pub fn get_show_differences(&self) -> bool {
+        self.show_differences
+    }
+
+    pub fn get_strip_layout(&self) -> StripLayout {
+        self.strip_layout
+    }
+
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_requested)
+    }
+
+    pub fn get_clear_color(&self) -> [f32; 3] {
+        self.clear_color
+    }
+
+    /// Where to save the current frame if it is one of the recorded ones
+    pub fn next_recording_frame(&mut self) -> Option<PathBuf> {
+        let recording = self.recording.as_mut()?;
+        recording.frame += 1;
+        if (recording.frame - 1) % recording.every != 0 {
+            return None;
+        }
+        recording.saved += 1;
+        Some(recording.frame_path(recording.saved - 1))
+    }
+
+    fn start_recording(&mut self, dir: PathBuf, every: usize) {
+        match std::fs::create_dir_all(&dir).and_then(|()| remove_frames(&dir)) {
+            Ok(()) => {
+                self.recording = Some(Recording {
+                    dir,
+                    every: every.max(1),
+                    frame: 0,
+                    saved: 0,
+                    started: Instant::now(),
+                })
+            }
+            Err(e) => self
+                .errors
+                .push(format!("Could not record to {}: {}", dir.display(), e)),
+        }
+    }
+
+    /// Stops saving frames and, if asked to, starts ffmpeg on them in the background
+    fn stop_recording(&mut self, encode: bool) {
+        let recording = match self.recording.take() {
+            Some(recording) if encode && recording.saved > 0 => recording,
+            _ => return,
+        };
+        // Played back at the rate the frames were saved at
+        let elapsed = recording.started.elapsed().as_secs_f64();
+        let fps = (recording.saved as f64 / elapsed).round().max(1.);
+        let output = recording.dir.join("recording.mp4");
+        let started = std::process::Command::new("ffmpeg")
+            .arg("-y")
+            .args(["-framerate", &fps.to_string()])
+            .arg("-i")
+            .arg(recording.dir.join("frame-%05d.png"))
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(&output)
+            .spawn();
+        match started {
+            Ok(child) => self.encodings.push((recording.dir, child)),
+            Err(e) => self.errors.push(format!(
+                "Could not run ffmpeg ({}), the frames are left in {}",
+                e,
+                recording.dir.display()
+            )),
+        }
+    }
+
+    /// Reports the ffmpeg runs that have finished without success
+    pub fn check_encodings(&mut self) {
+        let mut errors = vec![];
+        self.encodings
+            .retain_mut(|(dir, child)| match child.try_wait() {
+                Ok(None) => true,
+                Ok(Some(status)) if status.success() => false,
+                Ok(Some(status)) => {
+                    errors.push(format!(
+                        "ffmpeg {}, the frames are left in {}",
+                        status,
+                        dir.display()
+                    ));
+                    false
+                }
+                Err(e) => {
+                    errors.push(format!("Could not wait for ffmpeg: {}", e));
+                    false
+                }
+            });
+        self.errors.extend(errors);
     }
 }
 
+/// Removes the frames of an earlier recording, so they don't end up in the next video
+fn remove_frames(dir: &std::path::Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with("frame-") && name.ends_with(".png") {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
 impl Reducer<UiPost, UiGet> for UiReducer {
     fn reduce(&mut self, op: UiPost) {
         match op {
             UiPost::AddModel(n, m) => {
                 self.model_manager.add_model(&n, m);
             }
+            UiPost::ReseedEdges(n, edges) => {
+                let (l, r) = *edges;
+                self.model_manager.reseed_edges(&n, l, r)
+            }
+            UiPost::CopyStateFrom(dst, src) => self.model_manager.copy_state_from(&dst, &src),
+            UiPost::PinModel(n) => self.model_manager.pin_model(&n),
+            UiPost::UnpinModel(n, i) => self.model_manager.unpin_model(&n, i),
+            UiPost::SetReference(n, on) => self.model_manager.set_reference(&n, on),
             UiPost::RestartModel(s) => {
                 self.model_manager.restart_model(&s);
             }
-            UiPost::RemoveModel(n) => self.model_manager.remove_model(&n),
+            UiPost::JumpToTime(s, t) => self.model_manager.seek_to(&s, t),
+            UiPost::UpdateModel(n, m) => self.model_manager.update_model(&n, m),
+            UiPost::RemoveModel(n) => {
+                self.model_manager.remove_model(&n);
+                self.model_colors.remove(&n);
+            }
+            UiPost::DuplicateModel(n, copy) => self.model_manager.duplicate_model(&n, &copy),
+            UiPost::SetModelColor(n, color) => {
+                self.model_colors.insert(n, color);
+            }
             UiPost::StartComparison(n1, n2) => self.model_manager.start_comparison(&n1, &n2),
             UiPost::StopComparison(n1, n2) => self.model_manager.stop_comparison(&n1, &n2),
-            UiPost::SetMinFrameTime(_) => {}
+            UiPost::SetClearColor(color) => self.clear_color = color,
+            UiPost::StartRecording(dir, every) => self.start_recording(dir, every),
+            UiPost::StopRecording(encode) => self.stop_recording(encode),
             UiPost::SetMinTickTime(d) => {
                 self.model_manager.set_min_tick_time(d);
             }
+            UiPost::SetSpeedMultiplier(m) => self.model_manager.set_speed_multiplier(m),
+            UiPost::SetTimeRate(rate) => self.model_manager.set_time_rate(rate),
+            UiPost::TakeScreenshot => self.screenshot_requested = true,
+            UiPost::SetColorRange(min, max) => self.color_range = (min, max),
+            UiPost::SetColorScale(scale) => self.color_scale = scale,
+            UiPost::SetSmoothing(radius) => self.smoothing = radius,
+            UiPost::SetRenderMode(mode) => {
+                self.render_mode = mode;
+                self.model_manager
+                    .set_error_map(mode == RenderMode::ErrorMap);
+            }
+            UiPost::SetShowDifferences(show) => self.show_differences = show,
+            UiPost::SetStripLayout(layout) => self.strip_layout = layout,
+            UiPost::SetShowSpectrum(show) => self.model_manager.set_spectrum(show),
+            UiPost::SetShowScalars(show) => self.model_manager.set_scalar_history(show),
+            UiPost::RunConvergenceStudy(n, t, runs) => {
+                self.model_manager.run_convergence_study(&n, t, runs)
+            }
+            UiPost::ExportComparison(n1, n2, path) => {
+                self.model_manager.export_comparison(&n1, &n2, path)
+            }
+            UiPost::SetSteadyState(tolerance, pause) => {
+                self.model_manager.set_steady_state(tolerance, pause)
+            }
         }
     }
 
@@ -147,8 +791,24 @@ impl Reducer<UiPost, UiGet> for UiReducer {
             UiGet::ModelInfo(None) => {
                 *op = UiGet::ModelInfo(Some(self.model_info.clone()));
             }
-            UiGet::GetFps(None) => *op = UiGet::GetFps(Some(120)),
-            UiGet::GetTps(None) => *op = UiGet::GetTps(Some(self.tps)),
+            UiGet::RecordedFrames(None) => {
+                *op = UiGet::RecordedFrames(Some(self.recording.as_ref().map(|r| r.saved)))
+            }
+            UiGet::GetTps(None) => *op = UiGet::GetTps(Some(self.tick_stats.tps)),
+            UiGet::GetTickTimes(None) => {
+                *op = UiGet::GetTickTimes(Some((
+                    self.tick_stats.avg_tick_us,
+                    self.tick_stats.max_tick_us,
+                )))
+            }
+            UiGet::GetMultisampleSamples(None) => {
+                *op = UiGet::GetMultisampleSamples(Some(self.multisample_samples))
+            }
+            UiGet::Errors(None) => *op = UiGet::Errors(Some(std::mem::take(&mut self.errors))),
+            UiGet::ModelColors(None) => *op = UiGet::ModelColors(Some(self.model_colors.clone())),
+            UiGet::ConvergenceStudies(None) => {
+                *op = UiGet::ConvergenceStudies(Some(std::mem::take(&mut self.convergence_studies)))
+            }
             _ => (),
         }
     }
@@ -158,6 +818,13 @@ pub struct App {
     window: Window,
     renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort>,
     shader: Shader,
+    line_renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort>,
+    line_shader: Shader,
+    error_map_renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort>,
+    error_map_shader: Shader,
+    error_map_texture: Texture,
+    /// Animations are timed from here, so they run at the same pace at any frame rate
+    started: Instant,
 
     ticker: Ticker,
     model_manager: Rc<ModelManager>,
@@ -165,22 +832,34 @@ pub struct App {
     ui: Controls,
     reducer: UiReducer,
     is_running: bool,
+    #[cfg(feature = "server")]
+    server: Option<Server>,
 }
 
 impl App {
-    pub fn new() -> Result<Self, Error> {
-        let window = call!(Window::new(640, 480, "Hello"))?;
+    pub fn new(width: u32, height: u32, title: &str, samples: u8) -> Result<Self, Error> {
+        let window = call!(Window::new(width, height, title, samples))?;
 
-        let mvp: Matrix4<f32> = Matrix4::new_orthographic(-320., 320., 240., -240., 0., -1.);
+        let mvp: Matrix4<f32> = Matrix4::new_orthographic(
+            -VIEW_WIDTH / 2.,
+            VIEW_WIDTH / 2.,
+            VIEW_HEIGHT / 2.,
+            -VIEW_HEIGHT / 2.,
+            0.,
+            -1.,
+        );
         let mut shader = call!(Shader::new(&[
             (VERT_SRC, gl::VERTEX_SHADER),
             (FRAG_SRC, gl::FRAGMENT_SHADER),
         ]))?;
         call!(shader.set_uniform4x4("uMVP", &mvp))?;
+        call!(shader.set_uniform1f("uLogEpsilon", LOG_SCALE_EPSILON))?;
+        call!(shader.set_uniform1f("uPulse", 0.))?;
 
         let mut layout = VertexLayout::new();
         call!(layout.push_attribute(gl::FLOAT, 2, false, 0))?;
-        call!(layout.push_attribute(gl::FLOAT, 4, false, 1))?;
+        call!(layout.push_attribute(gl::FLOAT, 1, false, 1))?;
+        call!(layout.push_attribute(gl::FLOAT, 3, false, 2))?;
 
         let renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort> =
             call!(BatchRenderer::new(
@@ -193,42 +872,295 @@ impl App {
                 gl::UNSIGNED_SHORT,
             ))?;
 
+        let mut line_shader = call!(Shader::new(&[
+            (LINE_VERT_SRC, gl::VERTEX_SHADER),
+            (LINE_FRAG_SRC, gl::FRAGMENT_SHADER),
+        ]))?;
+        call!(line_shader.set_uniform4x4("uMVP", &mvp))?;
+
+        let mut line_layout = VertexLayout::new();
+        call!(line_layout.push_attribute(gl::FLOAT, 2, false, 0))?;
+        call!(line_layout.push_attribute(gl::FLOAT, 4, false, 1))?;
+
+        let line_renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort> =
+            call!(BatchRenderer::new(
+                line_layout,
+                None,
+                None,
+                u16::MAX as i32,
+                u16::MAX as i32,
+                gl::STATIC_DRAW,
+                gl::UNSIGNED_SHORT,
+            ))?;
+
+        let mut error_map_shader = call!(Shader::new(&[
+            (MAP_VERT_SRC, gl::VERTEX_SHADER),
+            (MAP_FRAG_SRC, gl::FRAGMENT_SHADER),
+        ]))?;
+        call!(error_map_shader.set_uniform4x4("uMVP", &mvp))?;
+        call!(error_map_shader.set_uniform1i("uTexture", 0))?;
+
+        let mut map_layout = VertexLayout::new();
+        call!(map_layout.push_attribute(gl::FLOAT, 2, false, 0))?;
+        call!(map_layout.push_attribute(gl::FLOAT, 2, false, 1))?;
+        call!(map_layout.push_attribute(gl::FLOAT, 1, false, 2))?;
+        call!(map_layout.push_attribute(gl::FLOAT, 3, false, 3))?;
+
+        let error_map_renderer: BatchRenderer<gl::types::GLfloat, gl::types::GLushort> =
+            call!(BatchRenderer::new(
+                map_layout,
+                None,
+                None,
+                u16::MAX as i32,
+                u16::MAX as i32,
+                gl::STATIC_DRAW,
+                gl::UNSIGNED_SHORT,
+            ))?;
+        let error_map_texture = call!(Texture::new())?;
+
         let model_manager = Rc::new(ModelManager::new(Duration::from_micros(100)));
+        let mut reducer = UiReducer::new(model_manager.clone());
+        reducer.multisample_samples = window.get_multisample_samples();
 
         Ok(Self {
             is_running: true,
             shader,
             renderer,
+            line_shader,
+            line_renderer,
+            error_map_shader,
+            error_map_renderer,
+            error_map_texture,
+            started: Instant::now(),
             window,
             ticker: Ticker::new(Duration::from_millis(7)),
             ui: Controls::new(),
-            reducer: UiReducer::new(model_manager.clone()),
+            reducer,
             model_manager,
+            #[cfg(feature = "server")]
+            server: None,
         })
     }
 
+    /// Starts accepting remote commands on `address`, e.g. `127.0.0.1:8080`
+    #[cfg(feature = "server")]
+    pub fn listen(&mut self, address: &str) -> Result<(), Error> {
+        self.server = Some(call!(Server::start(address, self.model_manager.remote()))?);
+        Ok(())
+    }
+
     pub fn run(&mut self) -> Result<(), Error> {
         while call!(self.window.process_events())? && self.is_running {
             self.ticker.start_tick();
+            self.reducer.check_encodings();
 
-            let (model_info, tps) = self.model_manager.get_info();
-            let mut offset = 0;
-            for (i, m) in model_info.iter().enumerate() {
-                let n = &m.nodes;
-                let l = &m.length;
-                let (v, i) = nodes_to_verts(&n[..], *l, 30., (0., -100. + i as f32 * 35.), offset);
-                offset += n.len() as u16;
-                call!(self.renderer.push(&v[..], &i[..]))?;
+            let (model_info, tick_stats) = match self.model_manager.get_info() {
+                Ok(info) => info,
+                Err(e) => {
+                    let reason = self.model_manager.restart();
+                    self.reducer.push_errors(vec![format!(
+                        "{} ({}), restarted without models",
+                        e, reason
+                    )]);
+                    (vec![], TickStats::default())
+                }
+            };
+            let render_mode = self.reducer.get_render_mode();
+            let color_range = self.reducer.get_color_range();
+            let smoothing = self.reducer.get_smoothing();
+            match render_mode {
+                RenderMode::Heatmap => {
+                    let rows = layout_rows(&model_info, self.reducer.get_show_differences(), |m| {
+                        self.reducer.get_model_color(m)
+                    });
+                    let layout = self.reducer.get_strip_layout();
+                    for (i, r) in rows.iter().enumerate() {
+                        let y = layout.strip_y(i);
+                        let nodes = if r.is_difference {
+                            difference_to_temperature(r.nodes, color_range)
+                        } else {
+                            r.nodes.to_vec()
+                        };
+                        // Smoothed as a whole so the chunk seams look like anywhere else
+                        let nodes = smooth_nodes(&nodes, smoothing);
+                        let (red, green, blue, _) = r.color;
+                        for (start, end) in node_chunks(nodes.len(), MAX_NODES_PER_PUSH) {
+                            let (v, i) = nodes_to_verts(
+                                &nodes[start..=end],
+                                &r.positions[start..=end],
+                                r.length,
+                                layout.height,
+                                (0., y),
+                                (red, green, blue),
+                            );
+                            call!(self.renderer.push(&v[..], &i[..]))?;
+                        }
+                    }
+                }
+                RenderMode::LineGraph => {
+                    for m in model_info.iter() {
+                        for pin in &m.pins {
+                            for (start, end) in node_chunks(pin.nodes.len(), MAX_NODES_PER_PUSH) {
+                                let (v, i) = nodes_to_lines(
+                                    &pin.nodes[start..=end],
+                                    &pin.positions[start..=end],
+                                    m.length,
+                                    400.,
+                                    color_range,
+                                    pin_color(self.reducer.get_model_color(m)),
+                                );
+                                call!(self.line_renderer.push(&v[..], &i[..]))?;
+                            }
+                        }
+                        for (start, end) in node_chunks(m.nodes.len(), MAX_NODES_PER_PUSH) {
+                            let (v, i) = nodes_to_lines(
+                                &m.nodes[start..=end],
+                                &m.positions[start..=end],
+                                m.length,
+                                400.,
+                                color_range,
+                                self.reducer.get_model_color(m),
+                            );
+                            call!(self.line_renderer.push(&v[..], &i[..]))?;
+                        }
+                        // |u1 - u2| scaled to the full height, like the heatmap's difference strip
+                        let difference = m
+                            .difference
+                            .as_ref()
+                            .filter(|_| self.reducer.get_show_differences());
+                        if let Some((_, difference)) = difference {
+                            let nodes = difference_to_temperature(difference, color_range);
+                            for (start, end) in node_chunks(nodes.len(), MAX_NODES_PER_PUSH) {
+                                let (v, i) = nodes_to_lines(
+                                    &nodes[start..=end],
+                                    &m.positions[start..=end],
+                                    m.length,
+                                    400.,
+                                    color_range,
+                                    (1., 1., 1., 1.),
+                                );
+                                call!(self.line_renderer.push(&v[..], &i[..]))?;
+                            }
+                        }
+                    }
+                }
+                RenderMode::ErrorMap => {
+                    let maps = error_maps(&model_info);
+                    let (width, height, data) = pack_error_maps(&maps);
+                    if !data.is_empty() {
+                        call!(self.error_map_texture.set_data(width, height, &data))?;
+                        let (v, i) = error_map_quads(&maps, (width, height), |m| {
+                            self.reducer.get_model_color(m)
+                        });
+                        call!(self.error_map_renderer.push(&v[..], &i[..]))?;
+                    }
+                }
+            }
+            let hover_text = match (render_mode, self.window.get_mouse_position()) {
+                (RenderMode::Heatmap, Some(mouse)) => hover_readout(
+                    &layout_rows(&model_info, self.reducer.get_show_differences(), |m| {
+                        self.reducer.get_model_color(m)
+                    }),
+                    self.reducer.get_strip_layout(),
+                    screen_to_world(mouse, self.window.get_size()),
+                ),
+                _ => None,
+            };
+            self.reducer.set_model_info((model_info, tick_stats));
+            self.reducer.push_errors(self.model_manager.take_errors());
+            self.reducer
+                .push_convergence_studies(self.model_manager.take_convergence_studies());
+            for export in self.model_manager.take_comparison_histories() {
+                if let Err(e) = write_history(&export.path, &export.history, &export.units) {
+                    self.reducer.push_errors(vec![format!(
+                        "Could not export {}: {}",
+                        export.path.display(),
+                        e.cause()
+                    )]);
+                }
             }
-            self.reducer.set_model_info((model_info, tps));
 
+            let (min_temperature, max_temperature) = color_range;
+            call!(self
+                .shader
+                .set_uniform1f("uMinTemperature", min_temperature))?;
+            call!(self
+                .shader
+                .set_uniform1f("uMaxTemperature", max_temperature))?;
+            let color_scale = self.reducer.get_color_scale().shader_index();
+            call!(self.shader.set_uniform1i("uColorScale", color_scale))?;
+            call!(self
+                .shader
+                .set_uniform1f("uTime", self.started.elapsed().as_secs_f32()))?;
+
+            self.window.set_clear_color(self.reducer.get_clear_color());
             call!(self.window.start_frame())?;
-            call!(self.renderer.draw(&self.shader, gl::TRIANGLES))?;
-            self.ui
-                .draw(&mut self.window.egui_context, &mut self.reducer);
+            match render_mode {
+                RenderMode::Heatmap => call!(self.renderer.draw(&self.shader, gl::TRIANGLES))?,
+                RenderMode::LineGraph => {
+                    call!(self.line_renderer.draw(&self.line_shader, gl::LINES))?
+                }
+                RenderMode::ErrorMap => {
+                    call!(self.error_map_texture.bind(0))?;
+                    call!(self
+                        .error_map_renderer
+                        .draw(&self.error_map_shader, gl::TRIANGLES))?;
+                    call!(self.error_map_texture.unbind(0))?;
+                }
+            }
+            if self.reducer.take_screenshot_request() {
+                // Milliseconds, so two shots within a second don't overwrite each other
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or(0);
+                let path = format!("screenshot-{}.png", timestamp);
+                if let Err(e) = self.window.save_screenshot(&path) {
+                    eprintln!("Could not save {}: {}", path, e);
+                }
+            }
+            if let Some(path) = self.reducer.next_recording_frame() {
+                if let Err(e) = self.window.save_screenshot(&path.to_string_lossy()) {
+                    self.reducer.push_errors(vec![format!(
+                        "Could not save {}: {}, recording stopped",
+                        path.display(),
+                        e
+                    )]);
+                    self.reducer.reduce(UiPost::StopRecording(false));
+                }
+            }
+            if render_mode == RenderMode::Heatmap {
+                let model_info = self.reducer.get_model_info();
+                let rows = layout_rows(&model_info, self.reducer.get_show_differences(), |m| {
+                    self.reducer.get_model_color(m)
+                });
+                draw_row_labels(
+                    &self.window.egui_context,
+                    &rows,
+                    self.reducer.get_strip_layout(),
+                );
+            }
+            if render_mode == RenderMode::ErrorMap {
+                let model_info = self.reducer.get_model_info();
+                draw_error_map_labels(&self.window.egui_context, &error_maps(&model_info), |m| {
+                    self.reducer.get_model_color(m)
+                });
+            }
+            self.ui.draw(&self.window.egui_context, &mut self.reducer);
+            if let Some(text) = hover_text {
+                let ctx = &self.window.egui_context;
+                if !ctx.is_pointer_over_area() {
+                    egui::show_tooltip_text(ctx, egui::Id::new("hover_readout"), text);
+                }
+            }
 
             call!(self.window.end_frame())?;
+            // Free the batches that held geometry of models that have since shrunk or gone
+            self.renderer.drop_empty_batches(1);
+            self.line_renderer.drop_empty_batches(1);
             call!(self.renderer.clear())?;
+            call!(self.line_renderer.clear())?;
+            call!(self.error_map_renderer.clear())?;
 
             self.ticker.end_tick();
         }
@@ -236,3 +1168,81 @@ impl App {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_chunks_fit_in_a_batch() {
+        let max_floats = u16::MAX as usize;
+        let node_count = 3 * MAX_NODES_PER_PUSH + 17;
+        let nodes = vec![1.; node_count];
+        let positions: Vec<f64> = (0..node_count).map(|i| i as f64).collect();
+
+        let chunks = node_chunks(node_count, MAX_NODES_PER_PUSH);
+        assert_eq!(chunks.first().unwrap().0, 0);
+        assert_eq!(chunks.last().unwrap().1, node_count - 1);
+
+        let mut segments = 0;
+        for (i, &(start, end)) in chunks.iter().enumerate() {
+            if i > 0 {
+                assert_eq!(start, chunks[i - 1].1, "chunks should share a node");
+            }
+            let (v, inds) = nodes_to_verts(
+                &nodes[start..=end],
+                &positions[start..=end],
+                node_count as f64,
+                StripLayout::default().height,
+                (0., 0.),
+                (1., 1., 1.),
+            );
+            assert!(v.len() < max_floats && inds.len() < max_floats);
+            assert!(inds.iter().all(|&i| (i as usize) < v.len() / 6));
+            segments += end - start;
+        }
+        assert_eq!(segments, node_count - 1);
+    }
+
+    #[test]
+    fn recording_saves_every_nth_frame() {
+        let manager = Rc::new(ModelManager::new(Duration::from_millis(1)));
+        let mut reducer = UiReducer::new(manager);
+        assert!(reducer.next_recording_frame().is_none());
+
+        let dir = std::env::temp_dir().join("thermal-recording-cadence");
+        std::fs::create_dir_all(&dir).unwrap();
+        let stale = dir.join("frame-00009.png");
+        std::fs::write(&stale, b"").unwrap();
+        reducer.reduce(UiPost::StartRecording(dir.clone(), 3));
+        assert!(!stale.exists());
+        let saved: Vec<PathBuf> = (0..7)
+            .filter_map(|_| reducer.next_recording_frame())
+            .collect();
+        assert_eq!(
+            saved,
+            (0..3)
+                .map(|i| dir.join(format!("frame-{:05}.png", i)))
+                .collect::<Vec<_>>()
+        );
+
+        reducer.reduce(UiPost::StopRecording(false));
+        assert!(reducer.next_recording_frame().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn smoothing_averages_neighbours() {
+        let sawtooth = [0., 3., 0., 3., 0.];
+        assert_eq!(smooth_nodes(&sawtooth, 0), sawtooth.to_vec());
+        assert_eq!(smooth_nodes(&sawtooth, 1), vec![1.5, 1., 2., 1., 1.5]);
+        assert!(smooth_nodes(&[], 2).is_empty());
+    }
+
+    #[test]
+    fn node_chunks_small_models() {
+        assert!(node_chunks(1, MAX_NODES_PER_PUSH).is_empty());
+        assert_eq!(node_chunks(2, MAX_NODES_PER_PUSH), vec![(0, 1)]);
+        assert_eq!(node_chunks(10, 4), vec![(0, 3), (3, 6), (6, 9)]);
+    }
+}
@@ -1 +0,0 @@
-pub enum Event {}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::model_manager::Simulation;
+use super::session::{build_model, write_history, write_samples, Session};
+use crate::call;
+use crate::renderer::error::Error;
+
+/// Steps to run when neither the command line nor the scenario says how many
+pub const DEFAULT_STEPS: usize = 1000;
+
+/// A session plus what to do with it, a plain session file is a scenario with the defaults
+#[derive(Deserialize, Debug)]
+pub struct Scenario {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Step count used unless one is given on the command line
+    #[serde(default)]
+    pub steps: Option<usize>,
+    /// Directory to write `<model>.csv`, `<model 1>-<model 2>.csv` and `summary.json` to
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct ModelSummary {
+    name: String,
+    diverged: bool,
+    min: f64,
+    max: f64,
+    mean: f64,
+}
+
+#[derive(Serialize)]
+struct ComparisonSummary {
+    model_1: String,
+    model_2: String,
+    l2: f64,
+    peak: f64,
+    /// Per-node RMS difference averaged over the run
+    average_rms: f64,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    steps: usize,
+    seconds: f64,
+    models: Vec<ModelSummary>,
+    comparisons: Vec<ComparisonSummary>,
+}
+
+/// Runs the models of a scenario file for `steps` steps without opening a window,
+/// or as many as the scenario asks for when `steps` is `None`,
+/// prints the wall-clock time and the final L2 difference of every comparison
+/// and writes the results if the scenario has an `output_dir`.
+/// Returns whether every model stayed finite
+pub fn run(config: &Path, steps: Option<usize>) -> Result<bool, Error> {
+    let json = call!(fs::read_to_string(config))?;
+    let scenario: Scenario = call!(serde_json::from_str(&json))?;
+    let session = scenario.session;
+    let steps = match (steps, scenario.steps) {
+        (Some(steps), Some(own)) if steps != own => {
+            eprintln!(
+                "Running {} steps as given by --steps instead of the {} in {}",
+                steps,
+                own,
+                config.display()
+            );
+            steps
+        }
+        (steps, own) => steps.or(own).unwrap_or(DEFAULT_STEPS),
+    };
+    let model_count = session.models.len();
+
+    let mut simulation = Simulation::new();
+    for (name, spec) in session.models {
+        let mut errors = None;
+        match build_model(&spec, &mut errors) {
+            Some(model) if errors.is_none() => simulation.add_model(name, model),
+            _ => {
+                return Err(Error::At(format!(
+                    "Invalid model {} in {}:\n{}",
+                    name,
+                    config.display(),
+                    errors.unwrap_or_default()
+                )))
+            }
+        }
+    }
+    for (n1, n2) in session.comparisons.iter().cloned() {
+        simulation.start_comparison(n1, n2);
+    }
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        simulation.step();
+    }
+    let elapsed = start.elapsed();
+
+    let (errors, model_errors) = simulation.take_errors();
+    for e in errors {
+        eprintln!("{}", e);
+    }
+    for (name, e) in model_errors {
+        eprintln!("{}: {}", name, e);
+    }
+
+    println!(
+        "Ran {} steps of {} models in {:.3?}",
+        steps, model_count, elapsed
+    );
+    simulation.sync_references();
+    let info = simulation.info();
+    let mut comparisons = vec![];
+    for model in info.iter() {
+        for (other, difference) in &model.comparisons {
+            if &model.name < other {
+                println!(
+                    "{} vs {}: L2 = {:.6e}, peak {:.6e}, avg RMS over run {:.6e}",
+                    model.name, other, difference.current, difference.peak, difference.average_rms
+                );
+                comparisons.push(ComparisonSummary {
+                    model_1: model.name.clone(),
+                    model_2: other.clone(),
+                    l2: difference.current,
+                    peak: difference.peak,
+                    average_rms: difference.average_rms,
+                });
+            }
+        }
+    }
+    let stable = info.iter().all(|m| !m.diverged);
+
+    if let Some(dir) = scenario.output_dir {
+        call!(fs::create_dir_all(&dir))?;
+        for model in info.iter() {
+            let path = dir.join(format!("{}.csv", model.name));
+            call!(write_samples(
+                &path,
+                &model.positions,
+                &model.nodes,
+                &model.units
+            ))?;
+        }
+        for (n1, n2) in session.comparisons {
+            let path = dir.join(format!("{}-{}.csv", n1, n2));
+            simulation.export_comparison(n1, n2, path);
+        }
+        for export in simulation.take_exports() {
+            call!(write_history(&export.path, &export.history, &export.units))?;
+        }
+
+        let summary = Summary {
+            steps,
+            seconds: elapsed.as_secs_f64(),
+            models: info
+                .iter()
+                .map(|m| ModelSummary {
+                    name: m.name.clone(),
+                    diverged: m.diverged,
+                    min: m.stats.0,
+                    max: m.stats.1,
+                    mean: m.stats.2,
+                })
+                .collect(),
+            comparisons,
+        };
+        let json = call!(serde_json::to_string_pretty(&summary))?;
+        call!(fs::write(dir.join("summary.json"), json))?;
+    }
+
+    Ok(stable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_is_a_scenario() {
+        let scenario: Scenario =
+            serde_json::from_str(r#"{"models": [], "comparisons": [["a", "b"]]}"#).unwrap();
+        assert_eq!(scenario.session.comparisons.len(), 1);
+        assert!(scenario.steps.is_none());
+        assert!(scenario.output_dir.is_none());
+
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"models": [], "comparisons": [], "steps": 10, "output_dir": "out"}"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.steps, Some(10));
+        assert_eq!(scenario.output_dir, Some(PathBuf::from("out")));
+    }
+}
@@ -1,4 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod app;
-mod event_queue;
+pub mod headless;
 mod model_manager;
+#[cfg(feature = "server")]
+mod server;
+mod session;
 mod ui;
+mod view;
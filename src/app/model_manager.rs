@@ -1,232 +1,2239 @@
-use crate::model::model::Model;
-use crate::ticker::Ticker;
+use crate::model::{
+    compare::{compare_models, compare_to_exact, same_grid, same_length},
+    error::ModelError,
+    model::{check_replay, check_seek_time, Model, Units},
+};
+use crate::renderer::error::Error;
+use crate::ticker::{TickStats, Ticker};
+use indexmap::IndexMap;
 use petgraph::{prelude::*, visit::IntoNodeReferences};
 use rayon::prelude::*;
+use rustfft::{num_complex::Complex, FftPlanner};
 use std::{
-    collections::HashMap,
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
     sync::{
         mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
     },
     thread::{spawn, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-fn compare_models(model_1: &Box<dyn Model>, model_2: &Box<dyn Model>) -> f64 {
+/// Oldest samples of a comparison's history are dropped past this many
+const MAX_HISTORY_SAMPLES: usize = 100_000;
+
+/// Oldest scalar samples of a model are dropped past this many, they are sent with every info
+const MAX_SCALAR_SAMPLES: usize = 10_000;
+
+/// Oldest rows of an error map are dropped past this many, they are sent with every info
+const MAX_ERROR_MAP_ROWS: usize = 256;
+
+/// Most steps a model takes to catch up in one tick at a fixed time rate, past this the
+/// time it couldn't cover is dropped instead of piling up on a machine that can't keep up
+const MAX_STEPS_PER_TICK: usize = 1000;
+
+/// Most steps a model replays toward the time it seeks in one tick,
+/// so a long seek doesn't hold up the other models
+const SEEK_STEPS_PER_TICK: usize = 10_000;
+
+/// Steps in a row a model has to stay under the steady-state tolerance to count as converged
+const STEADY_STEPS: usize = 10;
+
+/// Comparisons are undirected, so their histories are keyed by the names in order
+fn comparison_key(n1: &str, n2: &str) -> (String, String) {
+    if n1 <= n2 {
+        (n1.to_owned(), n2.to_owned())
+    } else {
+        (n2.to_owned(), n1.to_owned())
+    }
+}
+
+fn find_model(comparisons: &UnGraph<String, Difference>, name: &str) -> Option<NodeIndex> {
+    comparisons
+        .node_references()
+        .find(|(_, n)| &n[..] == name)
+        .map(|(a, _)| a)
+}
+
+/// `u1 - u2` at the nodes of `model_1`, `model_2` is interpolated onto them if the grids differ.
+/// NaN where `model_2` can't be evaluated, a 0 there would look like the two agree
+fn node_difference(model_1: &dyn Model, model_2: &dyn Model) -> Vec<f64> {
+    if !same_grid(model_1, model_2) {
+        return model_1
+            .get_cur_nodes()
+            .iter()
+            .zip(model_1.get_node_positions())
+            .map(|(a, x)| model_2.temperature_at(x).map_or(f64::NAN, |b| a - b))
+            .collect();
+    }
     model_1
         .get_cur_nodes()
         .par_iter()
         .zip(model_2.get_cur_nodes().par_iter())
-        .map(|(a, b)| (a - b) * (a - b))
+        .map(|(a, b)| a - b)
+        .collect()
+}
+
+/// `node_difference`, with a `reference` model evaluated at the time of `model_1`
+/// instead of its own
+fn partner_difference(model_1: &dyn Model, model_2: &dyn Model, reference: bool) -> Vec<f64> {
+    if !reference {
+        return node_difference(model_1, model_2);
+    }
+    let time = model_1.get_elapsed_time();
+    model_1
+        .get_cur_nodes()
+        .iter()
+        .zip(model_1.get_node_positions())
+        .map(|(a, x)| model_2.exact_at(x, time).map_or(f64::NAN, |b| a - b))
+        .collect()
+}
+
+/// The other end of the first comparison `a` starts, the one its difference is taken to
+fn difference_partner(comparisons: &UnGraph<String, Difference>, a: NodeIndex) -> Option<&String> {
+    comparisons
+        .edges(a)
+        .find(|e| comparisons.edge_endpoints(e.id()).unwrap().0 == a)
+        .and_then(|e| comparisons.node_weight(e.target()))
+}
+
+/// `|X_k| / n` of the real FFT of `nodes`, for k = 0 up to the Nyquist frequency.
+/// The nodes are zero-padded to the next power of two
+fn magnitude_spectrum(nodes: &[f64]) -> Vec<f64> {
+    let len = nodes.len().next_power_of_two();
+    let mut buffer: Vec<Complex<f64>> = nodes
+        .iter()
+        .map(|&u| Complex::new(u, 0.))
+        .chain(std::iter::repeat(Complex::new(0., 0.)))
+        .take(len)
+        .collect();
+    FftPlanner::new().plan_fft_forward(len).process(&mut buffer);
+    buffer[..=len / 2]
+        .iter()
+        .map(|x| x.norm() / nodes.len() as f64)
+        .collect()
+}
+
+/// L2 difference of `model` to a pinned snapshot, at the snapshot's nodes
+fn pin_difference(model: &dyn Model, pin: &Pin) -> f64 {
+    pin.positions
+        .iter()
+        .zip(&pin.nodes)
+        .filter_map(|(&x, a)| model.temperature_at(x).map(|b| (a - b) * (a - b)))
         .sum::<f64>()
         .sqrt()
 }
 
+/// A frozen copy of a model's nodes, kept until unpinned and drawn as is
+#[derive(Clone)]
+pub struct Pin {
+    /// Elapsed time of the model when it was pinned
+    pub time: f64,
+    pub positions: Vec<f64>,
+    pub nodes: Vec<f64>,
+    /// L2 difference of the live model to the snapshot
+    pub difference: f64,
+}
+
+/// Scalars of a model taken after a step, plotted over time
+#[derive(Clone, Copy, Debug)]
+pub struct ScalarSample {
+    pub time: f64,
+    pub total_heat: f64,
+    pub max: f64,
+    /// Heat flowing in through both edges, NaN for models that don't know it
+    pub net_flux: f64,
+}
+
+impl ScalarSample {
+    fn of(model: &dyn Model) -> Self {
+        Self {
+            time: model.get_elapsed_time(),
+            total_heat: model.total_heat(),
+            max: model.temperature_stats().1,
+            net_flux: model.boundary_flux().map_or(f64::NAN, |(l, r)| l + r),
+        }
+    }
+}
+
+/// `|u1 - u2|` at the nodes of a model after every step, drawn over x and time
+#[derive(Clone, Default)]
+pub struct ErrorMap {
+    /// The other end of the comparison, the same one `ModelInfo::difference` is taken to
+    pub other: String,
+    /// (elapsed time, difference at each node), oldest first
+    pub rows: VecDeque<(f64, Vec<f64>)>,
+}
+
+impl ErrorMap {
+    /// Elapsed time of the oldest and newest row
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        self.rows
+            .front()
+            .zip(self.rows.back())
+            .map(|(a, b)| (a.0, b.0))
+    }
+
+    pub fn peak(&self) -> f64 {
+        self.rows
+            .iter()
+            .flat_map(|(_, row)| row)
+            .fold(0., |acc: f64, d| acc.max(*d))
+    }
+}
+
+/// A comparison history asked for with `export_comparison`
+pub struct HistoryExport {
+    pub path: PathBuf,
+    /// (elapsed time, difference) after every step since the comparison was (re)started
+    pub history: Vec<(f64, f64)>,
+    /// Units of the first model of the comparison
+    pub units: Units,
+}
+
+/// One refinement level of a convergence study
+pub struct ConvergenceRow {
+    pub node_count: usize,
+    /// Node spacing `h`
+    pub spacing: f64,
+    /// Relative L2 error against the analytic solution
+    pub error: f64,
+    /// `ln(e_prev / e) / ln(h_prev / h)` against the previous, coarser level
+    pub order: Option<f64>,
+}
+
+pub struct ConvergenceStudy {
+    pub name: String,
+    pub time: f64,
+    pub rows: Vec<ConvergenceRow>,
+}
+
+/// A finished study, or the model it failed on with why
+type StudyResult = Result<ConvergenceStudy, (String, ModelError)>;
+
+/// Runs every (numeric, analytic) pair to `time` and compares them, coarsest pair first
+fn run_convergence_study(
+    name: String,
+    time: f64,
+    runs: Vec<(Box<dyn Model>, Box<dyn Model>)>,
+) -> Result<ConvergenceStudy, ModelError> {
+    let mut rows: Vec<ConvergenceRow> = vec![];
+    for (mut numeric, mut reference) in runs {
+        numeric.set_time(time)?;
+        reference.set_time(time)?;
+
+        let norm = reference
+            .get_cur_nodes()
+            .iter()
+            .map(|a| a * a)
+            .sum::<f64>()
+            .sqrt();
+        let mut error = compare_models(numeric.as_ref(), reference.as_ref()).l2;
+        if norm > 0. {
+            error /= norm;
+        }
+        let node_count = numeric.get_node_count();
+        let spacing = *numeric.get_length() / (node_count - 1) as f64;
+        rows.push(ConvergenceRow {
+            node_count,
+            spacing,
+            error,
+            // h = L / (N - 1) doesn't halve when N doubles, so the ratio of spacings is used
+            order: rows
+                .last()
+                .map(|prev| (prev.error / error).ln() / (prev.spacing / spacing).ln()),
+        });
+    }
+
+    Ok(ConvergenceStudy { name, time, rows })
+}
+
 enum MessageToThread {
     SetMinTickTime(Duration),
+    SetSpeedMultiplier(f64),
     AddModel(String, Box<dyn Model>),
+    UpdateModel(String, Box<dyn Model>),
     RemoveModel(String),
+    DuplicateModel(String, String),
     StartComparison(String, String),
     StopComparison(String, String),
     Exit,
     RequestNodes,
+    /// Sends the info straight to the given channel instead of the one of the `ModelManager`
+    #[cfg(feature = "server")]
+    SendInfoTo(Sender<Vec<ModelInfo>>),
     RestartModel(String),
+    /// (model, (left, right)), boxed as the expressions are large
+    ReseedEdges(String, Box<(exmex::FlatEx<f64>, exmex::FlatEx<f64>)>),
+    /// (destination, source)
+    CopyStateFrom(String, String),
+    SeekTo(String, f64),
+    RunConvergenceStudy(String, f64, Vec<(Box<dyn Model>, Box<dyn Model>)>),
+    ExportComparison(String, String, PathBuf),
+    SetSteadyState(f64, bool),
+    SetSpectrum(bool),
+    PinModel(String),
+    UnpinModel(String, usize),
+    SetReference(String, bool),
+    SetScalarHistory(bool),
+    SetErrorMap(bool),
+    SetTimeRate(Option<f64>),
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Difference {
+    /// L2 difference now
+    pub current: f64,
+    /// Largest pointwise difference now
+    pub max: f64,
+    /// Difference of the total heat of both models now
+    pub heat: f64,
+    /// Largest `current` seen since the comparison was (re)started
+    pub peak: f64,
+    /// Mean of the per-node RMS difference over every step since the comparison was (re)started
+    pub average_rms: f64,
+    /// Steps averaged into `average_rms`
+    pub steps: usize,
 }
 
 pub struct ModelInfo {
     pub name: String,
     pub nodes: Vec<f64>,
+    pub positions: Vec<f64>,
     pub length: f64,
-    pub comparisons: HashMap<String, f64>,
+    pub node_count: usize,
+    pub time_step: f64,
+    pub elapsed_time: f64,
+    /// Steps taken since the model was last reset
+    pub steps: usize,
+    pub comparisons: IndexMap<String, Difference>,
+    /// Index into the palette, assigned round-robin when the model is added
+    pub color: usize,
+    /// (min, max, mean) temperature
+    pub stats: (f64, f64, f64),
+    /// (min, max) temperature, not finite once the model has blown up
+    pub value_range: (f64, f64),
+    /// Heat flowing in through the (left, right) edge, numeric models only
+    pub boundary_flux: Option<(f64, f64)>,
+    /// Stopped stepping because its temperatures are no longer finite
+    pub diverged: bool,
+    /// Stopped stepping because a step or reset returned an error or panicked
+    pub failed: bool,
+    pub units: Units,
+    /// Largest change of a node during the last step
+    pub max_step_delta: f64,
+    /// `max_step_delta` stayed under the steady-state tolerance for the last `STEADY_STEPS` steps
+    pub converged: bool,
+    /// Highest temperature seen since the model was last reset
+    pub all_time_max: f64,
+    /// Pointwise difference to the other end of the first comparison this model starts,
+    /// at this model's nodes
+    pub difference: Option<(String, Vec<f64>)>,
+    /// Magnitude of each spatial frequency of the nodes, only computed while the spectrum is shown
+    pub spectrum: Option<Vec<f64>>,
+    pub pins: Vec<Pin>,
+    /// Samples since the model was last reset, only sent while the scalars are plotted
+    pub scalar_history: Vec<ScalarSample>,
+    /// Only recorded while the error map is shown
+    pub error_map: Option<ErrorMap>,
+    /// Not stepped, only evaluated at the time of the models it is compared against
+    pub reference: bool,
+    /// Time the model is replaying to, it doesn't step on until it gets there
+    pub seek_target: Option<f64>,
+    /// The expressions and parameters the model was built from, see `Model::details`
+    pub details: Vec<(String, String)>,
 }
 
 enum MessageFromThread {
-    SendInfo((Vec<ModelInfo>, usize)),
+    SendInfo((Vec<ModelInfo>, TickStats)),
+    Error(String),
+    ModelError(String, ModelError),
+    ConvergenceStudy(ConvergenceStudy),
+    ComparisonHistory(HistoryExport),
 }
 
-pub struct ModelManager {
-    physics_thread: Option<JoinHandle<()>>,
-    tx: Sender<MessageToThread>,
-    rx: Receiver<MessageFromThread>,
+/// The models and their comparisons, stepped together by the physics thread or a headless run
+pub struct Simulation {
+    /// In the order they were added, so models always step and report errors in the same order
+    models: IndexMap<String, Box<dyn Model>>,
+    comparisons: UnGraph<String, Difference>,
+    /// Models whose last step or reset returned an error or panicked, they are not stepped until restarted
+    failing: HashSet<String>,
+    /// Models whose nodes stopped being finite, they are not stepped until restarted
+    diverged: HashSet<String>,
+    /// Steps in a row each model changed by less than `steady_tolerance`
+    calm_steps: HashMap<String, usize>,
+    steady_tolerance: f64,
+    /// Stop stepping models once they converge
+    pause_steady: bool,
+    /// Elapsed time of the last step and the highest temperature seen up to it
+    hottest: HashMap<String, (f64, f64)>,
+    colors: HashMap<String, usize>,
+    next_color: usize,
+    /// (elapsed time, difference) after every step since the comparison was (re)started
+    histories: HashMap<(String, String), VecDeque<(f64, f64)>>,
+    exports: Vec<HistoryExport>,
+    errors: Vec<String>,
+    model_errors: Vec<(String, ModelError)>,
+    studies: Vec<ConvergenceStudy>,
+    /// Studies run on threads of their own and report back here
+    finished_studies: (Sender<StudyResult>, Receiver<StudyResult>),
+    /// Add the spectrum of every model to its info
+    spectrum: bool,
+    /// Snapshots of each model in the order they were pinned
+    pins: HashMap<String, Vec<Pin>>,
+    scalar_histories: HashMap<String, VecDeque<ScalarSample>>,
+    /// Add the scalar history of every model to its info
+    scalar_history: bool,
+    error_maps: HashMap<String, ErrorMap>,
+    /// Record an error map for every model that is compared to another
+    error_map: bool,
+    /// Simulated seconds to cover per wall-clock second, `None` takes one step per tick
+    time_rate: Option<f64>,
+    /// Simulated time each model is owed but hasn't been stepped through yet
+    owed_time: HashMap<String, f64>,
+    /// Time each numeric model is replaying to, a chunk of steps per tick
+    seeks: HashMap<String, f64>,
+    /// Closed-form models that aren't stepped, comparisons evaluate them at the time
+    /// and nodes of the other model instead
+    references: HashSet<String>,
 }
 
-impl ModelManager {
-    pub fn new(min_tick_time: Duration) -> Self {
-        let (tx_from_thread, rx_from_thread) = channel();
-        let (tx_from_main, rx_from_main) = channel();
-
-        let physics_thread = spawn(move || {
-            let mut models = HashMap::new();
-            let tx = tx_from_thread;
-            let rx = rx_from_main;
-            let mut is_running = true;
-            let mut comparisons = UnGraph::<String, f64>::new_undirected();
-            let mut ticker = Ticker::new(min_tick_time);
-
-            while is_running {
-                ticker.start_tick();
-
-                let mut send_info = false;
-                match rx.try_recv() {
-                    Err(e) => match e {
-                        std::sync::mpsc::TryRecvError::Disconnected => {
-                            panic!("Other side disconnected")
-                        }
-                        std::sync::mpsc::TryRecvError::Empty => (),
-                    },
-                    Ok(m) => match m {
-                        MessageToThread::StartComparison(n1, n2) => {
-                            let (a, _) = comparisons
-                                .node_references()
-                                .filter(|(_, n)| &n[..] == &n1[..])
-                                .last()
-                                .unwrap();
-                            let (b, _) = comparisons
-                                .node_references()
-                                .filter(|(_, n)| &n[..] == &n2[..])
-                                .last()
-                                .unwrap();
-                            comparisons.update_edge(a, b, 0.0);
-                            models.get_mut(&n1).map(|m: &mut Box<dyn Model>| m.reset());
-                            models.get_mut(&n2).map(|m| m.reset());
-                        }
-                        MessageToThread::StopComparison(n1, n2) => {
-                            let (a, _) = comparisons
-                                .node_references()
-                                .filter(|(_, n)| &n[..] == &n1[..])
-                                .last()
-                                .unwrap();
-                            let b = comparisons
-                                .neighbors(a)
-                                .filter(|b| comparisons.node_weight(*b).unwrap() == &n2)
-                                .last()
-                                .unwrap();
-                            comparisons.remove_edge(
-                                comparisons.edges_connecting(a, b).last().unwrap().id(),
-                            );
-                        }
-                        MessageToThread::Exit => {
-                            is_running = false;
-                        }
-                        MessageToThread::RestartModel(s) => {
-                            models.get_mut(&s).map(|m| m.reset());
-                        }
-                        MessageToThread::AddModel(s, m) => {
-                            if comparisons
-                                .node_references()
-                                .find(|(_, n)| &n[..] == &s[..])
-                                .is_none()
-                            {
-                                models.insert(s.clone(), m);
-                                comparisons.add_node(s);
-                            }
-                        }
-                        MessageToThread::RemoveModel(s) => {
-                            let n = comparisons
-                                .node_references()
-                                .filter(|(_, n)| &n[..] == &s[..])
-                                .last();
-                            match n {
-                                Some((a, _)) => {
-                                    comparisons.remove_node(a);
-                                    models.remove(&s);
-                                }
-                                None => (),
-                            }
-                        }
-                        MessageToThread::RequestNodes => send_info = true,
-                        MessageToThread::SetMinTickTime(t) => ticker.set_min_tick_time(t),
-                    },
-                }
-
-                models.iter_mut().for_each(|(_, m)| m.run_step());
-                comparisons.edge_indices().for_each(|e| {
-                    let (n1, n2) = comparisons.edge_endpoints(e).unwrap();
-                    let m1 = comparisons.node_weight(n1).unwrap();
-                    let m2 = comparisons.node_weight(n2).unwrap();
-                    let new_diff =
-                        compare_models(&models.get(m1).unwrap(), &models.get(m2).unwrap());
-                    *comparisons.edge_weight_mut(e).unwrap() = new_diff;
-                });
+impl Simulation {
+    pub fn new() -> Self {
+        Self {
+            models: IndexMap::new(),
+            comparisons: UnGraph::new_undirected(),
+            failing: HashSet::new(),
+            diverged: HashSet::new(),
+            calm_steps: HashMap::new(),
+            steady_tolerance: 1e-6,
+            pause_steady: false,
+            hottest: HashMap::new(),
+            colors: HashMap::new(),
+            next_color: 0,
+            histories: HashMap::new(),
+            exports: vec![],
+            errors: vec![],
+            model_errors: vec![],
+            studies: vec![],
+            finished_studies: channel(),
+            spectrum: false,
+            pins: HashMap::new(),
+            scalar_histories: HashMap::new(),
+            scalar_history: false,
+            error_maps: HashMap::new(),
+            error_map: false,
+            time_rate: None,
+            owed_time: HashMap::new(),
+            seeks: HashMap::new(),
+            references: HashSet::new(),
+        }
+    }
+
+    pub fn add_model(&mut self, s: String, m: Box<dyn Model>) {
+        if find_model(&self.comparisons, &s).is_none() {
+            self.models.insert(s.clone(), m);
+            self.colors.insert(s.clone(), self.next_color);
+            self.next_color += 1;
+            self.comparisons.add_node(s);
+        } else {
+            self.errors
+                .push(format!("Cannot add {}: name already taken", s));
+        }
+    }
 
-                if send_info {
-                    let info = (comparisons.node_references().map(|(a, n1)| ModelInfo {
-                        name: n1.clone(),
-                        length: models.get(n1).unwrap().get_length().clone(),
-                        nodes: Vec::from(models.get(n1).unwrap().get_cur_nodes().clone()),
-                        comparisons: comparisons
-                            .edges(a)
-                            .map(|e| {
-                                (
-                                    comparisons.node_weight(e.target()).unwrap().clone(),
-                                    e.weight().clone(),
-                                )
-                            })
-                            .collect(),
-                    }))
+    pub fn update_model(&mut self, s: String, m: Box<dyn Model>) {
+        match find_model(&self.comparisons, &s) {
+            Some(a) => {
+                // The new model starts at t = 0, restart whatever it is compared against
+                let others: Vec<String> = self
+                    .comparisons
+                    .neighbors(a)
+                    .map(|b| self.comparisons.node_weight(b).unwrap().clone())
                     .collect();
+                for other in others {
+                    self.reset_model(&other);
+                }
+                if !m.is_closed_form() {
+                    self.references.remove(&s);
+                }
+                self.models.insert(s.clone(), m);
+                self.seeks.remove(&s);
+                self.failing.remove(&s);
+                self.diverged.remove(&s);
+                self.calm_steps.remove(&s);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot update {}: no such model", s)),
+        }
+    }
+
+    pub fn remove_model(&mut self, s: String) {
+        match find_model(&self.comparisons, &s) {
+            Some(a) => {
+                self.comparisons.remove_node(a);
+                self.models.shift_remove(&s);
+                self.failing.remove(&s);
+                self.diverged.remove(&s);
+                self.calm_steps.remove(&s);
+                self.hottest.remove(&s);
+                self.colors.remove(&s);
+                self.pins.remove(&s);
+                self.scalar_histories.remove(&s);
+                self.error_maps.remove(&s);
+                self.owed_time.remove(&s);
+                self.seeks.remove(&s);
+                self.references.remove(&s);
+                self.histories.retain(|(n1, n2), _| n1 != &s && n2 != &s);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot remove {}: no such model", s)),
+        }
+    }
+
+    /// Adds a copy of `s` named `copy`, starting over from t = 0
+    pub fn duplicate_model(&mut self, s: String, copy: String) {
+        match self.models.get(&s) {
+            Some(_) if find_model(&self.comparisons, &copy).is_some() => self.errors.push(format!(
+                "Cannot duplicate {} as {}: name already taken",
+                s, copy
+            )),
+            Some(m) => {
+                let m = m.clone_box();
+                self.add_model(copy.clone(), m);
+                self.reset_model(&copy);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot duplicate {}: no such model", s)),
+        }
+    }
 
-                    tx.send(MessageFromThread::SendInfo((info, ticker.get_tps())))
-                        .unwrap();
+    pub fn start_comparison(&mut self, n1: String, n2: String) {
+        let models = &mut self.models;
+        match (
+            find_model(&self.comparisons, &n1),
+            find_model(&self.comparisons, &n2),
+        ) {
+            (Some(a), Some(b)) if a == b => {
+                self.errors
+                    .push(format!("Cannot compare {} with itself", n1));
+            }
+            (Some(_), Some(_)) if !same_length(models[&n1].as_ref(), models[&n2].as_ref()) => {
+                self.errors.push(format!(
+                    "Cannot compare {} and {}: lengths {} and {} differ",
+                    n1,
+                    n2,
+                    models[&n1].get_length(),
+                    models[&n2].get_length()
+                ));
+            }
+            (Some(a), Some(b)) => {
+                self.comparisons.update_edge(a, b, Difference::default());
+                self.histories.remove(&comparison_key(&n1, &n2));
+                self.reset_model(&n1);
+                self.reset_model(&n2);
+            }
+            (a, _) => {
+                let missing = if a.is_none() { &n1 } else { &n2 };
+                self.errors.push(format!(
+                    "Cannot compare {} and {}: no model named {}",
+                    n1, n2, missing
+                ));
+            }
+        }
+    }
+
+    pub fn stop_comparison(&mut self, n1: String, n2: String) {
+        let edge = find_model(&self.comparisons, &n1)
+            .zip(find_model(&self.comparisons, &n2))
+            .and_then(|(a, b)| self.comparisons.find_edge(a, b));
+        match edge {
+            Some(e) => {
+                self.comparisons.remove_edge(e);
+                self.histories.remove(&comparison_key(&n1, &n2));
+            }
+            None => self.errors.push(format!(
+                "Cannot stop comparing {} and {}: no such comparison",
+                n1, n2
+            )),
+        }
+    }
+
+    /// Resets a model, pausing it like a failed step if that fails
+    fn reset_model(&mut self, s: &str) {
+        if let Some(m) = self.models.get_mut(s) {
+            match guarded(|| m.reset()) {
+                Ok(()) => {
+                    self.failing.remove(s);
+                }
+                Err(e) => {
+                    self.failing.insert(s.to_owned());
+                    self.model_errors.push((s.to_owned(), e));
+                }
+            }
+        }
+    }
+
+    pub fn restart_model(&mut self, s: String) {
+        match self.models.get(&s) {
+            Some(_) => {
+                self.reset_model(&s);
+                self.seeks.remove(&s);
+                self.diverged.remove(&s);
+                self.calm_steps.remove(&s);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot restart {}: no such model", s)),
+        }
+    }
+
+    /// Swaps in new edge conditions at the model's current time, keeping its interior
+    pub fn reseed_edges(&mut self, s: String, left: exmex::FlatEx<f64>, right: exmex::FlatEx<f64>) {
+        match self.models.get_mut(&s) {
+            Some(m) => {
+                if let Err(e) = guarded(|| m.reseed_edges(left, right)) {
+                    self.model_errors.push((s, e));
                 }
+            }
+            None => self
+                .errors
+                .push(format!("Cannot reseed the edges of {}: no such model", s)),
+        }
+    }
 
-                ticker.end_tick();
+    /// Sets the nodes of `dst` to the current ones of `src`, interpolated onto its grid
+    /// if they differ, and lets it go on from there
+    pub fn copy_state_from(&mut self, dst: String, src: String) {
+        let nodes = match (self.models.get(&dst), self.models.get(&src)) {
+            (Some(d), Some(s)) if same_grid(d.as_ref(), s.as_ref()) => s.get_cur_nodes().to_vec(),
+            (Some(d), Some(s)) if same_length(d.as_ref(), s.as_ref()) => d
+                .get_node_positions()
+                .iter()
+                .map(|&x| s.temperature_at(x).unwrap_or(f64::NAN))
+                .collect(),
+            (Some(_), Some(_)) => {
+                return self.errors.push(format!(
+                    "Cannot copy the state of {} into {}: the rods have different lengths",
+                    src, dst
+                ))
+            }
+            _ => {
+                return self.errors.push(format!(
+                    "Cannot copy the state of {} into {}: no such model",
+                    src, dst
+                ))
+            }
+        };
+        let m = self.models.get_mut(&dst).unwrap();
+        match guarded(|| m.set_nodes(&nodes)) {
+            Ok(()) => {
+                self.diverged.remove(&dst);
+                self.calm_steps.remove(&dst);
             }
+            Err(e) => self.model_errors.push((dst, e)),
+        }
+    }
+
+    /// Keeps the current nodes of the model to compare it against later on
+    pub fn pin_model(&mut self, s: String) {
+        match self.models.get(&s) {
+            Some(m) => {
+                let pin = Pin {
+                    time: m.get_elapsed_time(),
+                    positions: m.get_node_positions(),
+                    nodes: m.get_cur_nodes().to_vec(),
+                    difference: 0.,
+                };
+                self.pins.entry(s).or_default().push(pin);
+            }
+            None => self.errors.push(format!("Cannot pin {}: no such model", s)),
+        }
+    }
+
+    pub fn unpin_model(&mut self, s: String, index: usize) {
+        match self.pins.get_mut(&s).filter(|pins| index < pins.len()) {
+            Some(pins) => {
+                pins.remove(index);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot unpin {}: no pin {}", s, index)),
+        }
+    }
+
+    /// Moves a model to the first step at or after `t`. Closed-form models get there at once,
+    /// numeric ones restart and replay up to `SEEK_STEPS_PER_TICK` steps a tick until they do
+    pub fn seek_to(&mut self, s: String, t: f64) {
+        let m = match self.models.get_mut(&s) {
+            Some(m) => m,
+            None => {
+                return self
+                    .errors
+                    .push(format!("Cannot seek {}: no such model", s))
+            }
+        };
+        let checked = if m.is_closed_form() {
+            check_seek_time(t)
+        } else {
+            check_replay(t, m.get_time_step())
+        };
+        if let Err(e) = checked {
+            return self.model_errors.push((s, e));
+        }
+
+        self.diverged.remove(&s);
+        self.calm_steps.remove(&s);
+        self.seeks.remove(&s);
+        if m.is_closed_form() {
+            match guarded(|| m.set_time(t)) {
+                Ok(()) => {
+                    self.failing.remove(&s);
+                }
+                Err(e) => {
+                    self.failing.insert(s.clone());
+                    self.model_errors.push((s, e));
+                }
+            }
+            return;
+        }
+        self.reset_model(&s);
+        if !self.failing.contains(&s) {
+            self.seeks.insert(s, t);
+        }
+    }
+
+    /// Replays the next chunk of every seek, a model that gets there steps on as usual
+    fn continue_seeks(&mut self) {
+        let mut done = vec![];
+        for (name, &target) in self.seeks.iter() {
+            let m = match self.models.get_mut(name) {
+                Some(m) => m,
+                None => {
+                    done.push(name.clone());
+                    continue;
+                }
+            };
+            let replayed = guarded(|| {
+                for _ in 0..SEEK_STEPS_PER_TICK {
+                    if m.get_elapsed_time() >= target {
+                        break;
+                    }
+                    m.run_step()?;
+                }
+                Ok(())
+            });
+            match replayed {
+                Err(e) => {
+                    self.failing.insert(name.clone());
+                    self.model_errors.push((name.clone(), e));
+                }
+                Ok(()) if m.get_cur_nodes().iter().any(|v| !v.is_finite()) => {
+                    self.diverged.insert(name.clone());
+                    self.errors.push(format!(
+                        "{} diverged at t = {} while seeking, paused until restarted",
+                        name,
+                        m.get_elapsed_time()
+                    ));
+                }
+                Ok(()) if m.get_elapsed_time() < target => continue,
+                Ok(()) => (),
+            }
+            done.push(name.clone());
+        }
+        for name in done {
+            self.seeks.remove(&name);
+            self.owed_time.remove(&name);
+        }
+    }
+
+    pub fn set_steady_state(&mut self, tolerance: f64, pause: bool) {
+        self.steady_tolerance = tolerance;
+        self.pause_steady = pause;
+    }
+
+    pub fn set_spectrum(&mut self, spectrum: bool) {
+        self.spectrum = spectrum;
+    }
+
+    /// Nothing is sampled while the history is off, it starts over when turned back on
+    pub fn set_scalar_history(&mut self, scalar_history: bool) {
+        self.scalar_history = scalar_history;
+        if !scalar_history {
+            self.scalar_histories.clear();
+        }
+    }
+
+    /// Turning the error map off forgets what was recorded, it starts over when turned back on
+    pub fn set_error_map(&mut self, error_map: bool) {
+        self.error_map = error_map;
+        if !error_map {
+            self.error_maps.clear();
+        }
+    }
+
+    pub fn set_time_rate(&mut self, time_rate: Option<f64>) {
+        self.time_rate = time_rate;
+        self.owed_time.clear();
+    }
+
+    /// Stops stepping a closed-form model, its comparisons evaluate it where and when
+    /// the other model is instead. Numeric models can't be references
+    pub fn set_reference(&mut self, s: String, reference: bool) {
+        match self.models.get(&s) {
+            Some(m) if reference && !m.is_closed_form() => self.errors.push(format!(
+                "Cannot make {} a reference: only analytic models can be evaluated at any time",
+                s
+            )),
+            Some(_) if reference => {
+                self.references.insert(s);
+            }
+            Some(_) => {
+                self.references.remove(&s);
+            }
+            None => self
+                .errors
+                .push(format!("Cannot make {} a reference: no such model", s)),
+        }
+    }
+
+    /// Moves every reference to the time of a stepped model it is compared against,
+    /// only needed where its own nodes are shown
+    pub fn sync_references(&mut self) {
+        let comparisons = &self.comparisons;
+        let targets: Vec<(String, f64)> = self
+            .references
+            .iter()
+            .filter(|r| !self.failing.contains(*r))
+            .filter_map(|r| {
+                let a = find_model(comparisons, r)?;
+                comparisons
+                    .neighbors(a)
+                    .map(|b| &comparisons[b])
+                    .filter(|n| !self.references.contains(*n))
+                    .find_map(|n| self.models.get(n))
+                    .map(|m| (r.clone(), m.get_elapsed_time()))
+            })
+            .collect();
+        for (name, time) in targets {
+            if let Some(m) = self.models.get_mut(&name) {
+                if let Err(e) = guarded(|| m.set_time(time)) {
+                    self.failing.insert(name.clone());
+                    self.model_errors.push((name, e));
+                }
+            }
+        }
+    }
+
+    fn is_converged(&self, name: &str) -> bool {
+        self.calm_steps
+            .get(name)
+            .is_some_and(|&n| n >= STEADY_STEPS)
+    }
+
+    /// Queues the history of the comparison for `take_exports`
+    pub fn export_comparison(&mut self, n1: String, n2: String, path: PathBuf) {
+        let edge = find_model(&self.comparisons, &n1)
+            .zip(find_model(&self.comparisons, &n2))
+            .and_then(|(a, b)| self.comparisons.find_edge(a, b));
+        match edge {
+            Some(_) => {
+                let history = self
+                    .histories
+                    .get(&comparison_key(&n1, &n2))
+                    .map(|h| h.iter().copied().collect())
+                    .unwrap_or_default();
+                let units = self
+                    .models
+                    .get(&n1)
+                    .map(|m| m.get_units().clone())
+                    .unwrap_or_default();
+                self.exports.push(HistoryExport {
+                    path,
+                    history,
+                    units,
+                });
+            }
+            None => self.errors.push(format!(
+                "Cannot export {} and {}: no such comparison",
+                n1, n2
+            )),
+        }
+    }
+
+    /// Runs the study on a thread of its own, so replaying the runs doesn't stall the models
+    pub fn run_convergence_study(
+        &mut self,
+        s: String,
+        t: f64,
+        runs: Vec<(Box<dyn Model>, Box<dyn Model>)>,
+    ) {
+        let sender = self.finished_studies.0.clone();
+        spawn(move || {
+            let res = guarded(|| run_convergence_study(s.clone(), t, runs)).map_err(|e| (s, e));
+            // The simulation may be gone by the time the study finishes
+            let _ = sender.send(res);
         });
+    }
+
+    /// Moves the studies that finished since the last call to `studies` or `model_errors`
+    fn collect_studies(&mut self) {
+        for res in self.finished_studies.1.try_iter() {
+            match res {
+                Ok(study) => self.studies.push(study),
+                Err(e) => self.model_errors.push(e),
+            }
+        }
+    }
+
+    /// Advances the models by `wall` seconds of wall-clock time at the fixed time rate,
+    /// each by as many whole steps as cover it, the remainder is carried to the next call.
+    /// Without a time rate every model takes one step
+    pub fn advance(&mut self, wall: f64) {
+        let rate = match self.time_rate {
+            Some(rate) => rate,
+            None => return self.step(),
+        };
+        self.continue_seeks();
+        let mut due = HashMap::new();
+        for (name, m) in self.models.iter() {
+            // A seeking model isn't owed the time it spends replaying
+            if self.seeks.contains_key(name) {
+                continue;
+            }
+            let time_step = m.get_time_step();
+            let owed = self.owed_time.entry(name.clone()).or_default();
+            *owed += wall * rate;
+            let steps = (*owed / time_step).floor().min(MAX_STEPS_PER_TICK as f64);
+            *owed -= steps * time_step;
+            if steps as usize == MAX_STEPS_PER_TICK {
+                *owed = owed.min(time_step);
+            }
+            due.insert(name.clone(), steps as usize);
+        }
+
+        let rounds = due.values().copied().max().unwrap_or(0);
+        for round in 0..rounds {
+            self.step_only(|name| due.get(name).is_some_and(|&steps| steps > round));
+        }
+    }
+
+    /// Advances every model by one time step and updates the comparisons
+    pub fn step(&mut self) {
+        self.continue_seeks();
+        self.step_only(|_| true)
+    }
+
+    /// Advances the models `due` picks by one time step and updates the comparisons
+    fn step_only(&mut self, due: impl Fn(&str) -> bool) {
+        let skipped: HashSet<String> = self
+            .models
+            .keys()
+            .filter(|name| {
+                !due(name)
+                    || self.seeks.contains_key(*name)
+                    || self.references.contains(*name)
+                    || self.diverged.contains(*name)
+                    || self.failing.contains(*name)
+                    || (self.pause_steady && self.is_converged(name))
+            })
+            .cloned()
+            .collect();
+        for (name, m) in self.models.iter_mut() {
+            if skipped.contains(name) {
+                continue;
+            }
+            match guarded(|| m.run_step()) {
+                Ok(()) if m.get_cur_nodes().iter().any(|v| !v.is_finite()) => {
+                    self.diverged.insert(name.clone());
+                    self.errors.push(format!(
+                        "{} diverged at t = {}, paused until restarted",
+                        name,
+                        m.get_elapsed_time()
+                    ));
+                }
+                Ok(()) => {
+                    let calm = self.calm_steps.entry(name.clone()).or_default();
+                    if m.max_step_delta() < self.steady_tolerance {
+                        *calm += 1;
+                    } else {
+                        *calm = 0;
+                    }
+                }
+                Err(e) => {
+                    self.failing.insert(name.clone());
+                    self.model_errors.push((name.clone(), e));
+                }
+            }
+        }
+
+        for (name, m) in self.models.iter() {
+            let (_, max, _) = m.temperature_stats();
+            let time = m.get_elapsed_time();
+            let hottest = self.hottest.entry(name.clone()).or_insert((time, max));
+            // Time only goes backwards when the model was reset
+            if time < hottest.0 {
+                *hottest = (time, max);
+            }
+            *hottest = (time, hottest.1.max(max));
+        }
+
+        // Sampling takes a pass over the nodes, so only while the scalars are plotted
+        if self.scalar_history {
+            for (name, m) in self.models.iter() {
+                let time = m.get_elapsed_time();
+                let history = self.scalar_histories.entry(name.clone()).or_default();
+                match history.back() {
+                    // Paused models don't move on
+                    Some(last) if last.time == time => continue,
+                    Some(last) if last.time > time => history.clear(),
+                    _ => (),
+                }
+                if history.len() == MAX_SCALAR_SAMPLES {
+                    history.pop_front();
+                }
+                history.push_back(ScalarSample::of(m.as_ref()));
+            }
+        }
+
+        let (models, comparisons) = (&self.models, &mut self.comparisons);
+        // A diverged model keeps its last comparison instead of turning it into NaN
+        let live = |n: &String| models.get(n).filter(|_| !self.diverged.contains(n));
+        for e in comparisons.edge_indices() {
+            let (n1, n2) = comparisons.edge_endpoints(e).unwrap();
+            let m1 = comparisons.node_weight(n1).and_then(live);
+            let m2 = comparisons.node_weight(n2).and_then(live);
+            if let (Some(m1), Some(m2)) = (m1, m2) {
+                let reference = |n| self.references.contains(&comparisons[n]);
+                // A reference is evaluated at the time and nodes of the stepped model,
+                // resampled comparisons are taken at the nodes of the coarser model
+                let (metrics, time, compared) = match (reference(n1), reference(n2)) {
+                    (false, true) => (
+                        compare_to_exact(m1.as_ref(), m2.as_ref()),
+                        m1.get_elapsed_time(),
+                        m1.get_node_count(),
+                    ),
+                    (true, false) => (
+                        compare_to_exact(m2.as_ref(), m1.as_ref()),
+                        m2.get_elapsed_time(),
+                        m2.get_node_count(),
+                    ),
+                    _ => (
+                        compare_models(m1.as_ref(), m2.as_ref()),
+                        m1.get_elapsed_time(),
+                        m1.get_node_count().min(m2.get_node_count()),
+                    ),
+                };
+                let current = metrics.l2;
+                let rms = current / (compared as f64).sqrt();
+
+                let key = comparison_key(&comparisons[n1], &comparisons[n2]);
+                let history = self.histories.entry(key).or_default();
+                let difference = comparisons.edge_weight_mut(e).unwrap();
+                // Time only goes backwards when a model was reset
+                if history.back().is_some_and(|&(t, _)| t > time) {
+                    history.clear();
+                    difference.steps = 0;
+                    difference.average_rms = 0.;
+                }
+                difference.current = current;
+                difference.max = metrics.max;
+                difference.heat = metrics.heat;
+                difference.peak = difference.peak.max(current);
+                difference.steps += 1;
+                difference.average_rms += (rms - difference.average_rms) / difference.steps as f64;
+
+                if history.len() == MAX_HISTORY_SAMPLES {
+                    history.pop_front();
+                }
+                history.push_back((time, current));
+            }
+        }
+
+        if self.error_map {
+            self.record_error_maps();
+        }
+    }
+
+    fn record_error_maps(&mut self) {
+        let (comparisons, models) = (&self.comparisons, &self.models);
+        for (a, n1) in comparisons.node_references() {
+            let pair = difference_partner(comparisons, a)
+                .and_then(|other| models.get(n1).zip(models.get(other)).map(|m| (other, m)));
+            let (other, (m1, m2)) = match pair {
+                Some(pair) => pair,
+                None => {
+                    self.error_maps.remove(n1);
+                    continue;
+                }
+            };
+            if self.diverged.contains(n1) {
+                continue;
+            }
+            let map = self.error_maps.entry(n1.clone()).or_default();
+            let time = m1.get_elapsed_time();
+            match map.rows.back() {
+                _ if &map.other != other => {
+                    map.other = other.clone();
+                    map.rows.clear();
+                }
+                // Paused models don't move on
+                Some(&(t, _)) if t == time => continue,
+                // Time only goes backwards when a model was reset
+                Some(&(t, _)) if t > time => map.rows.clear(),
+                _ => (),
+            }
+            let row: Vec<f64> =
+                partner_difference(m1.as_ref(), m2.as_ref(), self.references.contains(other))
+                    .into_iter()
+                    .map(f64::abs)
+                    .collect();
+            // Rows taken before the model was rebuilt on another grid don't line up
+            if map.rows.back().is_some_and(|(_, r)| r.len() != row.len()) {
+                map.rows.clear();
+            }
+            if map.rows.len() == MAX_ERROR_MAP_ROWS {
+                map.rows.pop_front();
+            }
+            map.rows.push_back((time, row));
+        }
+    }
+
+    pub fn info(&self) -> Vec<ModelInfo> {
+        let comparisons = &self.comparisons;
+        self.models
+            .iter()
+            .filter_map(|(n1, m)| find_model(comparisons, n1).map(|a| (a, n1, m)))
+            .map(|(a, n1, m)| ModelInfo {
+                name: n1.clone(),
+                length: *m.get_length(),
+                node_count: m.get_node_count(),
+                time_step: m.get_time_step(),
+                elapsed_time: m.get_elapsed_time(),
+                steps: (m.get_elapsed_time() / m.get_time_step()).round() as usize,
+                nodes: Vec::from(m.get_cur_nodes()),
+                positions: m.get_node_positions(),
+                color: self.colors.get(n1).copied().unwrap_or_default(),
+                stats: m.temperature_stats(),
+                value_range: m.value_range(),
+                diverged: self.diverged.contains(n1),
+                failed: self.failing.contains(n1),
+                units: m.get_units().clone(),
+                max_step_delta: m.max_step_delta(),
+                converged: self.is_converged(n1),
+                boundary_flux: m.boundary_flux(),
+                all_time_max: self.hottest.get(n1).map_or(f64::NAN, |h| h.1),
+                comparisons: {
+                    let mut others: IndexMap<_, _> = comparisons
+                        .edges(a)
+                        .map(|e| {
+                            (
+                                comparisons.node_weight(e.target()).unwrap().clone(),
+                                *e.weight(),
+                            )
+                        })
+                        .collect();
+                    // In the order the models were added rather than the order of the edges
+                    others.sort_by_cached_key(|other, _| self.models.get_index_of(other));
+                    others
+                },
+                difference: difference_partner(comparisons, a).and_then(|other| {
+                    self.models.get(other).map(|o| {
+                        let reference = self.references.contains(other);
+                        (
+                            other.clone(),
+                            partner_difference(m.as_ref(), o.as_ref(), reference),
+                        )
+                    })
+                }),
+                spectrum: self.spectrum.then(|| magnitude_spectrum(m.get_cur_nodes())),
+                pins: self
+                    .pins
+                    .get(n1)
+                    .into_iter()
+                    .flatten()
+                    .map(|pin| Pin {
+                        difference: pin_difference(m.as_ref(), pin),
+                        ..pin.clone()
+                    })
+                    .collect(),
+                scalar_history: self
+                    .scalar_histories
+                    .get(n1)
+                    .filter(|_| self.scalar_history)
+                    .map_or(vec![], |h| h.iter().copied().collect()),
+                error_map: self.error_maps.get(n1).filter(|_| self.error_map).cloned(),
+                reference: self.references.contains(n1),
+                seek_target: self.seeks.get(n1).copied(),
+                details: m.details(),
+            })
+            .collect()
+    }
+
+    /// Everything that went wrong since the last call, model errors reported once per failure
+    pub fn take_errors(&mut self) -> (Vec<String>, Vec<(String, ModelError)>) {
+        self.collect_studies();
+        (
+            std::mem::take(&mut self.errors),
+            std::mem::take(&mut self.model_errors),
+        )
+    }
+
+    pub fn take_convergence_studies(&mut self) -> Vec<ConvergenceStudy> {
+        self.collect_studies();
+        std::mem::take(&mut self.studies)
+    }
+
+    /// Comparison histories asked for with `export_comparison`, with the path to write them to
+    pub fn take_exports(&mut self) -> Vec<HistoryExport> {
+        std::mem::take(&mut self.exports)
+    }
+}
+
+fn physics_loop(
+    min_tick_time: Duration,
+    tx: Sender<MessageFromThread>,
+    rx: Receiver<MessageToThread>,
+) {
+    let mut simulation = Simulation::new();
+    let mut is_running = true;
+    let mut ticker = Ticker::new(min_tick_time);
+    let mut last_tick = Instant::now();
+
+    while is_running {
+        ticker.start_tick();
+        let now = Instant::now();
+        let wall = now.duration_since(last_tick).as_secs_f64();
+        last_tick = now;
+
+        let mut send_info = false;
+        match rx.try_recv() {
+            Err(e) => match e {
+                std::sync::mpsc::TryRecvError::Disconnected => return,
+                std::sync::mpsc::TryRecvError::Empty => (),
+            },
+            Ok(m) => match m {
+                MessageToThread::StartComparison(n1, n2) => simulation.start_comparison(n1, n2),
+                MessageToThread::StopComparison(n1, n2) => simulation.stop_comparison(n1, n2),
+                MessageToThread::Exit => {
+                    is_running = false;
+                }
+                MessageToThread::RestartModel(s) => simulation.restart_model(s),
+                MessageToThread::ReseedEdges(s, edges) => {
+                    let (l, r) = *edges;
+                    simulation.reseed_edges(s, l, r)
+                }
+                MessageToThread::CopyStateFrom(dst, src) => simulation.copy_state_from(dst, src),
+                MessageToThread::SeekTo(s, t) => simulation.seek_to(s, t),
+                MessageToThread::RunConvergenceStudy(s, t, runs) => {
+                    simulation.run_convergence_study(s, t, runs)
+                }
+                MessageToThread::ExportComparison(n1, n2, path) => {
+                    simulation.export_comparison(n1, n2, path)
+                }
+                MessageToThread::AddModel(s, m) => simulation.add_model(s, m),
+                MessageToThread::UpdateModel(s, m) => simulation.update_model(s, m),
+                MessageToThread::RemoveModel(s) => simulation.remove_model(s),
+                MessageToThread::DuplicateModel(s, copy) => simulation.duplicate_model(s, copy),
+                MessageToThread::RequestNodes => send_info = true,
+                #[cfg(feature = "server")]
+                MessageToThread::SendInfoTo(reply) => {
+                    simulation.sync_references();
+                    let _ = reply.send(simulation.info());
+                }
+                MessageToThread::SetMinTickTime(t) => ticker.set_min_tick_time(t),
+                MessageToThread::SetSpeedMultiplier(m) => ticker.set_speed_multiplier(m),
+                MessageToThread::SetSteadyState(tolerance, pause) => {
+                    simulation.set_steady_state(tolerance, pause)
+                }
+                MessageToThread::SetSpectrum(spectrum) => simulation.set_spectrum(spectrum),
+                MessageToThread::PinModel(s) => simulation.pin_model(s),
+                MessageToThread::UnpinModel(s, i) => simulation.unpin_model(s, i),
+                MessageToThread::SetReference(s, on) => simulation.set_reference(s, on),
+                MessageToThread::SetScalarHistory(on) => simulation.set_scalar_history(on),
+                MessageToThread::SetErrorMap(on) => simulation.set_error_map(on),
+                MessageToThread::SetTimeRate(rate) => simulation.set_time_rate(rate),
+            },
+        }
+
+        simulation.advance(wall);
+
+        if send_info {
+            simulation.sync_references();
+            let info = simulation.info();
+            let (errors, model_errors) = simulation.take_errors();
+            let sent = errors
+                .into_iter()
+                .map(MessageFromThread::Error)
+                .chain(
+                    model_errors
+                        .into_iter()
+                        .map(|(name, e)| MessageFromThread::ModelError(name, e)),
+                )
+                .chain(
+                    simulation
+                        .take_convergence_studies()
+                        .into_iter()
+                        .map(MessageFromThread::ConvergenceStudy),
+                )
+                .chain(
+                    simulation
+                        .take_exports()
+                        .into_iter()
+                        .map(MessageFromThread::ComparisonHistory),
+                )
+                .chain(Some(MessageFromThread::SendInfo((
+                    info,
+                    ticker.get_stats(),
+                ))))
+                .all(|m| tx.send(m).is_ok());
+            if !sent {
+                return;
+            }
+        }
+
+        ticker.end_tick();
+    }
+}
+
+fn spawn_physics_thread(
+    min_tick_time: Duration,
+) -> (
+    JoinHandle<()>,
+    Sender<MessageToThread>,
+    Receiver<MessageFromThread>,
+) {
+    let (tx_from_thread, rx_from_thread) = channel();
+    let (tx_from_main, rx_from_main) = channel();
+    let physics_thread = spawn(move || physics_loop(min_tick_time, tx_from_thread, rx_from_main));
+    (physics_thread, tx_from_main, rx_from_thread)
+}
+
+/// Runs model code, turning a panic into an error so one bad expression can't take down the
+/// physics thread and every other model with it
+fn guarded<T>(f: impl FnOnce() -> Result<T, ModelError>) -> Result<T, ModelError> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => Err(ModelError::Eval(format!(
+            "panicked: {}",
+            panic_message(payload)
+        ))),
+    }
+}
+
+/// Extracts the message a thread panicked with
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_owned())
+}
+
+pub struct ModelManager {
+    physics_thread: RefCell<Option<JoinHandle<()>>>,
+    /// Shared with every `Remote`, so they follow the thread across restarts
+    tx: Arc<Mutex<Sender<MessageToThread>>>,
+    rx: RefCell<Receiver<MessageFromThread>>,
+    min_tick_time: Cell<Duration>,
+    speed_multiplier: Cell<f64>,
+    spectrum: Cell<bool>,
+    scalar_history: Cell<bool>,
+    error_map: Cell<bool>,
+    time_rate: Cell<Option<f64>>,
+    /// Tolerance and whether converged models are paused
+    steady_state: Cell<(f64, bool)>,
+    errors: RefCell<Vec<String>>,
+    studies: RefCell<Vec<ConvergenceStudy>>,
+    histories: RefCell<Vec<HistoryExport>>,
+}
+
+impl ModelManager {
+    pub fn new(min_tick_time: Duration) -> Self {
+        let (physics_thread, tx, rx) = spawn_physics_thread(min_tick_time);
         Self {
-            physics_thread: Some(physics_thread),
-            tx: tx_from_main,
-            rx: rx_from_thread,
+            physics_thread: RefCell::new(Some(physics_thread)),
+            tx: Arc::new(Mutex::new(tx)),
+            rx: RefCell::new(rx),
+            min_tick_time: Cell::new(min_tick_time),
+            speed_multiplier: Cell::new(1.),
+            spectrum: Cell::new(false),
+            scalar_history: Cell::new(false),
+            error_map: Cell::new(false),
+            time_rate: Cell::new(None),
+            steady_state: Cell::new((1e-6, false)),
+            errors: RefCell::new(vec![]),
+            studies: RefCell::new(vec![]),
+            histories: RefCell::new(vec![]),
         }
     }
+
+    /// A failed send means the physics thread is gone, which `get_info` reports
+    fn send(&self, message: MessageToThread) {
+        let _ = self.tx.lock().unwrap().send(message);
+    }
+
+    /// Joins the dead physics thread and starts a fresh one without any models
+    pub fn restart(&self) -> String {
+        let reason = match self.physics_thread.borrow_mut().take().map(|t| t.join()) {
+            Some(Err(payload)) => panic_message(payload),
+            _ => "physics thread stopped".to_owned(),
+        };
+
+        let (physics_thread, tx, rx) = spawn_physics_thread(self.min_tick_time.get());
+        *self.physics_thread.borrow_mut() = Some(physics_thread);
+        *self.tx.lock().unwrap() = tx;
+        *self.rx.borrow_mut() = rx;
+        self.send(MessageToThread::SetSpeedMultiplier(
+            self.speed_multiplier.get(),
+        ));
+        self.send(MessageToThread::SetSpectrum(self.spectrum.get()));
+        self.send(MessageToThread::SetScalarHistory(self.scalar_history.get()));
+        self.send(MessageToThread::SetErrorMap(self.error_map.get()));
+        self.send(MessageToThread::SetTimeRate(self.time_rate.get()));
+        let (tolerance, pause) = self.steady_state.get();
+        self.send(MessageToThread::SetSteadyState(tolerance, pause));
+        reason
+    }
+
     pub fn add_model(&self, name: &str, model: Box<dyn Model>) {
-        self.tx
-            .send(MessageToThread::AddModel(name.to_owned(), model))
-            .unwrap();
+        self.send(MessageToThread::AddModel(name.to_owned(), model));
+    }
+    pub fn update_model(&self, name: &str, model: Box<dyn Model>) {
+        self.send(MessageToThread::UpdateModel(name.to_owned(), model));
     }
     pub fn remove_model(&self, name: &str) {
+        self.send(MessageToThread::RemoveModel(name.to_owned()));
+    }
+    pub fn duplicate_model(&self, name: &str, copy: &str) {
+        self.send(MessageToThread::DuplicateModel(
+            name.to_owned(),
+            copy.to_owned(),
+        ));
+    }
+
+    pub fn get_info(&self) -> Result<(Vec<ModelInfo>, TickStats), Error> {
+        let disconnected = || Error::PhysicsThread("channel disconnected".to_owned());
         self.tx
-            .send(MessageToThread::RemoveModel(name.to_owned()))
-            .unwrap();
+            .lock()
+            .unwrap()
+            .send(MessageToThread::RequestNodes)
+            .map_err(|_| disconnected())?;
+        loop {
+            let message = self.rx.borrow().recv().map_err(|_| disconnected())?;
+            match message {
+                MessageFromThread::SendInfo(n) => return Ok(n),
+                MessageFromThread::Error(e) => self.errors.borrow_mut().push(e),
+                MessageFromThread::ModelError(name, e) => {
+                    self.errors.borrow_mut().push(format!("{}: {}", name, e))
+                }
+                MessageFromThread::ConvergenceStudy(s) => self.studies.borrow_mut().push(s),
+                MessageFromThread::ComparisonHistory(export) => {
+                    self.histories.borrow_mut().push(export)
+                }
+            }
+        }
     }
 
-    pub fn get_info(&self) -> (Vec<ModelInfo>, usize) {
-        self.tx.send(MessageToThread::RequestNodes).unwrap();
-        match self.rx.recv().unwrap() {
-            MessageFromThread::SendInfo(n) => n,
+    /// A handle that sends commands straight to the physics thread, usable from any thread
+    #[cfg(feature = "server")]
+    pub fn remote(&self) -> Remote {
+        Remote {
+            tx: self.tx.clone(),
         }
     }
+
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.take()
+    }
+    pub fn take_convergence_studies(&self) -> Vec<ConvergenceStudy> {
+        self.studies.take()
+    }
+    /// Histories requested with `export_comparison` since the last call
+    pub fn take_comparison_histories(&self) -> Vec<HistoryExport> {
+        self.histories.take()
+    }
+    pub fn export_comparison(&self, model_1: &str, model_2: &str, path: PathBuf) {
+        self.send(MessageToThread::ExportComparison(
+            model_1.to_owned(),
+            model_2.to_owned(),
+            path,
+        ));
+    }
     pub fn set_min_tick_time(&self, min_tick_time: Duration) {
-        self.tx
-            .send(MessageToThread::SetMinTickTime(min_tick_time))
-            .unwrap();
+        self.min_tick_time.set(min_tick_time);
+        self.send(MessageToThread::SetMinTickTime(min_tick_time));
+    }
+    /// Scales how often the physics thread ticks without touching the time step of the models
+    pub fn set_speed_multiplier(&self, multiplier: f64) {
+        self.speed_multiplier.set(multiplier);
+        self.send(MessageToThread::SetSpeedMultiplier(multiplier));
+    }
+    /// Models count as converged once they change by less than `tolerance` for several steps
+    pub fn set_steady_state(&self, tolerance: f64, pause: bool) {
+        self.steady_state.set((tolerance, pause));
+        self.send(MessageToThread::SetSteadyState(tolerance, pause));
+    }
+    /// Whether model info carries the spectrum of the nodes, computing it every tick isn't free
+    pub fn set_spectrum(&self, spectrum: bool) {
+        self.spectrum.set(spectrum);
+        self.send(MessageToThread::SetSpectrum(spectrum));
+    }
+    /// Whether model info carries the scalars sampled after every step
+    pub fn set_scalar_history(&self, scalar_history: bool) {
+        self.scalar_history.set(scalar_history);
+        self.send(MessageToThread::SetScalarHistory(scalar_history));
+    }
+    /// Whether to record and send the difference of every compared model after each step
+    pub fn set_error_map(&self, error_map: bool) {
+        self.error_map.set(error_map);
+        self.send(MessageToThread::SetErrorMap(error_map));
+    }
+    /// Advance the models by `rate` simulated seconds per wall-clock second however fast the
+    /// physics thread ticks, or by one step per tick with `None`
+    pub fn set_time_rate(&self, rate: Option<f64>) {
+        self.time_rate.set(rate);
+        self.send(MessageToThread::SetTimeRate(rate));
     }
     pub fn start_comparison(&self, model_1: &str, model_2: &str) {
-        self.tx
-            .send(MessageToThread::StartComparison(
-                model_1.to_owned(),
-                model_2.to_owned(),
-            ))
-            .unwrap();
+        self.send(MessageToThread::StartComparison(
+            model_1.to_owned(),
+            model_2.to_owned(),
+        ));
     }
     pub fn stop_comparison(&self, model_1: &str, model_2: &str) {
-        self.tx
-            .send(MessageToThread::StopComparison(
-                model_1.to_owned(),
-                model_2.to_owned(),
-            ))
-            .unwrap();
+        self.send(MessageToThread::StopComparison(
+            model_1.to_owned(),
+            model_2.to_owned(),
+        ));
+    }
+    pub fn seek_to(&self, model: &str, target_time: f64) {
+        self.send(MessageToThread::SeekTo(model.to_owned(), target_time));
+    }
+    pub fn run_convergence_study(
+        &self,
+        model: &str,
+        time: f64,
+        runs: Vec<(Box<dyn Model>, Box<dyn Model>)>,
+    ) {
+        self.send(MessageToThread::RunConvergenceStudy(
+            model.to_owned(),
+            time,
+            runs,
+        ));
     }
     pub fn restart_model(&self, model: &str) {
-        self.tx
-            .send(MessageToThread::RestartModel(model.to_owned()))
-            .unwrap();
+        self.send(MessageToThread::RestartModel(model.to_owned()));
+    }
+    /// Replaces the edge conditions of `model` without restarting it
+    pub fn reseed_edges(&self, model: &str, left: exmex::FlatEx<f64>, right: exmex::FlatEx<f64>) {
+        self.send(MessageToThread::ReseedEdges(
+            model.to_owned(),
+            Box::new((left, right)),
+        ));
+    }
+    /// Warm-starts `dst` from the current temperatures of `src`
+    pub fn copy_state_from(&self, dst: &str, src: &str) {
+        self.send(MessageToThread::CopyStateFrom(
+            dst.to_owned(),
+            src.to_owned(),
+        ));
+    }
+    pub fn pin_model(&self, model: &str) {
+        self.send(MessageToThread::PinModel(model.to_owned()));
+    }
+    pub fn unpin_model(&self, model: &str, index: usize) {
+        self.send(MessageToThread::UnpinModel(model.to_owned(), index));
+    }
+    /// Whether `model` is only evaluated at the time of the models it is compared against
+    pub fn set_reference(&self, model: &str, reference: bool) {
+        self.send(MessageToThread::SetReference(model.to_owned(), reference));
+    }
+}
+
+/// Drives the physics thread of a `ModelManager` from another thread, bypassing the GUI
+#[cfg(feature = "server")]
+#[derive(Clone)]
+pub struct Remote {
+    tx: Arc<Mutex<Sender<MessageToThread>>>,
+}
+
+#[cfg(feature = "server")]
+impl Remote {
+    fn send(&self, message: MessageToThread) {
+        let _ = self.tx.lock().unwrap().send(message);
+    }
+
+    pub fn add_model(&self, name: &str, model: Box<dyn Model>) {
+        self.send(MessageToThread::AddModel(name.to_owned(), model));
+    }
+    pub fn remove_model(&self, name: &str) {
+        self.send(MessageToThread::RemoveModel(name.to_owned()));
+    }
+    pub fn restart_model(&self, name: &str) {
+        self.send(MessageToThread::RestartModel(name.to_owned()));
+    }
+    pub fn start_comparison(&self, n1: &str, n2: &str) {
+        self.send(MessageToThread::StartComparison(
+            n1.to_owned(),
+            n2.to_owned(),
+        ));
+    }
+    pub fn stop_comparison(&self, n1: &str, n2: &str) {
+        self.send(MessageToThread::StopComparison(
+            n1.to_owned(),
+            n2.to_owned(),
+        ));
+    }
+
+    /// The models once the physics thread got to everything sent before,
+    /// `None` if it doesn't answer within `timeout`
+    pub fn get_info(&self, timeout: Duration) -> Option<Vec<ModelInfo>> {
+        let (tx, rx) = channel();
+        self.send(MessageToThread::SendInfoTo(tx));
+        rx.recv_timeout(timeout).ok()
     }
 }
 
 impl Drop for ModelManager {
     fn drop(&mut self) {
-        self.tx.send(MessageToThread::Exit).unwrap();
-        self.physics_thread.take().map(|t| t.join());
+        self.send(MessageToThread::Exit);
+        self.physics_thread.get_mut().take().map(|t| t.join());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::analytic::AnalyticModel;
+    use crate::model::differential::*;
+    use crate::model::model::{Coefficient, InitialCondition};
+
+    fn make_model() -> Box<dyn Model> {
+        let func = exmex::parse::<f64>("100*exp(-t)*sin(PI*x/200)").unwrap();
+        Box::new(AnalyticModel::new(func, 200., 10, 1.).unwrap())
+    }
+
+    /// Forward Euler on the rod of `make_model`, stable with a step of 1 up to 100 nodes
+    fn make_numeric(node_count: u32) -> Box<dyn Model> {
+        Box::new(
+            DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("100*sin(PI*x/200)").unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                200.,
+                DifferentialOptions::new(node_count, 1.),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn remove_model_during_comparison() {
+        let manager = ModelManager::new(Duration::from_micros(1));
+        manager.add_model("a", make_model());
+        manager.add_model("b", make_model());
+        manager.start_comparison("a", "b");
+        manager.get_info().unwrap();
+        manager.remove_model("b");
+
+        let (info, _) = manager.get_info().unwrap();
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].name, "a");
+        assert!(info[0].comparisons.is_empty());
+        assert!(manager.take_errors().is_empty());
+    }
+
+    #[test]
+    fn compare_with_missing_model() {
+        let manager = ModelManager::new(Duration::from_micros(1));
+        manager.add_model("a", make_model());
+        manager.start_comparison("a", "missing");
+        manager.start_comparison("a", "a");
+
+        let (info, _) = manager.get_info().unwrap();
+        assert_eq!(info.len(), 1);
+        assert!(info[0].comparisons.is_empty());
+        assert_eq!(manager.take_errors().len(), 2);
+
+        manager.add_model("b", make_model());
+        manager.start_comparison("a", "b");
+        let (info, _) = manager.get_info().unwrap();
+        assert_eq!(info.len(), 2);
+    }
+
+    #[test]
+    fn compare_different_lengths() {
+        let manager = ModelManager::new(Duration::from_micros(1));
+        let func = exmex::parse::<f64>("100*exp(-t)*sin(PI*x/100)").unwrap();
+        manager.add_model("a", make_model());
+        manager.add_model(
+            "short",
+            Box::new(AnalyticModel::new(func, 100., 10, 1.).unwrap()),
+        );
+        manager.start_comparison("a", "short");
+
+        let (info, _) = manager.get_info().unwrap();
+        assert!(info.iter().all(|m| m.comparisons.is_empty()));
+        assert_eq!(manager.take_errors().len(), 1);
+
+        let func = exmex::parse::<f64>("100*exp(-t)*sin(PI*x/200)").unwrap();
+        manager.add_model(
+            "fine",
+            Box::new(AnalyticModel::new(func, 200., 40, 1.).unwrap()),
+        );
+        manager.start_comparison("a", "fine");
+        let (info, _) = manager.get_info().unwrap();
+        let a = info.iter().find(|m| m.name == "a").unwrap();
+        assert!(a.comparisons.contains_key("fine"));
+        assert!(manager.take_errors().is_empty());
+    }
+
+    #[test]
+    fn different_grids_are_resampled() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("coarse".to_owned(), make_numeric(10));
+        // Every other node of the fine grid is a node of the coarse one
+        simulation.add_model("fine".to_owned(), make_numeric(19));
+        simulation.start_comparison("coarse".to_owned(), "fine".to_owned());
+        for _ in 0..10 {
+            simulation.step();
+        }
+
+        // The grids only differ by discretization error, a truncating zip would be off by
+        // tens of degrees
+        let info = simulation.info();
+        let coarse = info.iter().find(|m| m.name == "coarse").unwrap();
+        assert!(coarse.comparisons["fine"].current < 1e-2);
+        let (_, difference) = coarse.difference.as_ref().unwrap();
+        assert_eq!(difference.len(), 10);
+        assert!(
+            difference.iter().all(|d| d.abs() < 1e-2),
+            "{:?}",
+            difference
+        );
+    }
+
+    #[test]
+    fn diverged_model_is_paused() {
+        let mut simulation = Simulation::new();
+        let func = exmex::parse::<f64>("1/(5-t)+0*x").unwrap();
+        simulation.add_model(
+            "blows up".to_owned(),
+            Box::new(AnalyticModel::new(func, 200., 10, 1.).unwrap()),
+        );
+        simulation.add_model("b".to_owned(), make_model());
+        simulation.start_comparison("blows up".to_owned(), "b".to_owned());
+        for _ in 0..10 {
+            simulation.step();
+        }
+
+        let (errors, _) = simulation.take_errors();
+        assert_eq!(errors.len(), 1);
+        let info = simulation.info();
+        let diverged = info.iter().find(|m| m.name == "blows up").unwrap();
+        assert!(diverged.diverged);
+        assert_eq!(simulation.models["blows up"].get_elapsed_time(), 5.);
+        assert!(diverged.comparisons["b"].current.is_finite());
+
+        simulation.restart_model("blows up".to_owned());
+        assert!(simulation.info().iter().all(|m| !m.diverged));
+    }
+
+    /// Panics on its third step, like an expression exmex can't evaluate
+    struct Panicking {
+        nodes: Vec<f64>,
+        steps: u32,
+        units: Units,
+    }
+
+    impl Model for Panicking {
+        fn reset(&mut self) -> Result<(), ModelError> {
+            self.steps = 0;
+            Ok(())
+        }
+        fn run_step(&mut self) -> Result<(), ModelError> {
+            self.steps += 1;
+            assert!(self.steps < 3, "bad expression");
+            Ok(())
+        }
+        fn get_elapsed_time(&self) -> f64 {
+            self.steps as f64
+        }
+        fn max_step_delta(&self) -> f64 {
+            f64::INFINITY
+        }
+        fn get_units(&self) -> &Units {
+            &self.units
+        }
+        fn set_units(&mut self, units: Units) {
+            self.units = units;
+        }
+        fn get_length(&self) -> &f64 {
+            &200.
+        }
+        fn get_cur_nodes(&self) -> &[f64] {
+            &self.nodes
+        }
+        fn get_node_step(&self) -> &f64 {
+            &20.
+        }
+        fn get_time_step(&self) -> f64 {
+            1.
+        }
+        fn clone_box(&self) -> Box<dyn Model> {
+            Box::new(Panicking {
+                nodes: self.nodes.clone(),
+                steps: self.steps,
+                units: self.units.clone(),
+            })
+        }
+    }
+
+    #[test]
+    fn panicking_model_is_paused() {
+        let mut simulation = Simulation::new();
+        simulation.add_model(
+            "panics".to_owned(),
+            Box::new(Panicking {
+                nodes: vec![0.; 11],
+                steps: 0,
+                units: Units::default(),
+            }),
+        );
+        simulation.add_model("b".to_owned(), make_model());
+        for _ in 0..10 {
+            simulation.step();
+        }
+
+        let (_, model_errors) = simulation.take_errors();
+        assert_eq!(model_errors.len(), 1);
+        assert_eq!(model_errors[0].0, "panics");
+        let info = simulation.info();
+        assert!(info.iter().find(|m| m.name == "panics").unwrap().failed);
+        assert_eq!(simulation.models["b"].get_elapsed_time(), 10.);
+
+        simulation.restart_model("panics".to_owned());
+        assert!(simulation.info().iter().all(|m| !m.failed));
+    }
+
+    #[test]
+    fn models_keep_the_order_they_were_added_in() {
+        let mut simulation = Simulation::new();
+        for name in ["c", "a", "b"] {
+            simulation.add_model(name.to_owned(), make_model());
+        }
+        simulation.start_comparison("c".to_owned(), "b".to_owned());
+        simulation.start_comparison("c".to_owned(), "a".to_owned());
+        simulation.remove_model("a".to_owned());
+        simulation.add_model("a".to_owned(), make_model());
+        simulation.step();
+
+        let info = simulation.info();
+        let names: Vec<_> = info.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["c", "b", "a"]);
+        simulation.start_comparison("c".to_owned(), "a".to_owned());
+        let info = simulation.info();
+        let others: Vec<_> = info[0].comparisons.keys().map(String::as_str).collect();
+        assert_eq!(others, ["b", "a"]);
+    }
+
+    #[test]
+    fn duplicate_starts_over() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        for _ in 0..5 {
+            simulation.step();
+        }
+        simulation.duplicate_model("a".to_owned(), "a (copy)".to_owned());
+        simulation.duplicate_model("missing".to_owned(), "b".to_owned());
+
+        assert_eq!(simulation.models["a"].get_elapsed_time(), 5.);
+        assert_eq!(simulation.models["a (copy)"].get_elapsed_time(), 0.);
+        let (errors, _) = simulation.take_errors();
+        assert_eq!(errors.len(), 1);
+        let info = simulation.info();
+        assert_eq!(info.len(), 2);
+        assert_ne!(info[0].color, info[1].color);
+        assert_eq!((info[0].elapsed_time, info[0].steps), (5., 5));
+        assert_eq!((info[1].elapsed_time, info[1].steps), (0., 0));
+    }
+
+    #[test]
+    fn duplicate_leaves_a_taken_name_alone() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        simulation.add_model("b".to_owned(), make_model());
+        for _ in 0..5 {
+            simulation.step();
+        }
+        simulation.duplicate_model("a".to_owned(), "b".to_owned());
+
+        assert_eq!(simulation.models["b"].get_elapsed_time(), 5.);
+        let (errors, _) = simulation.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(simulation.info().len(), 2);
+    }
+
+    #[test]
+    fn steady_model_is_paused() {
+        let mut simulation = Simulation::new();
+        let func = exmex::parse::<f64>("20+0*x+0*t").unwrap();
+        simulation.add_model(
+            "flat".to_owned(),
+            Box::new(AnalyticModel::new(func, 200., 10, 1.).unwrap()),
+        );
+        simulation.set_steady_state(1e-6, true);
+        for _ in 0..STEADY_STEPS + 5 {
+            simulation.step();
+        }
+
+        let info = simulation.info();
+        assert!(info[0].converged);
+        assert_eq!(info[0].max_step_delta, 0.);
+        assert_eq!(
+            simulation.models["flat"].get_elapsed_time(),
+            STEADY_STEPS as f64
+        );
+
+        simulation.restart_model("flat".to_owned());
+        assert!(!simulation.info()[0].converged);
+    }
+
+    #[test]
+    fn average_rms_over_run() {
+        let mut simulation = Simulation::new();
+        for (name, func) in [("zero", "0*x+0*t"), ("ramp", "t+0*x")] {
+            let func = exmex::parse::<f64>(func).unwrap();
+            simulation.add_model(
+                name.to_owned(),
+                Box::new(AnalyticModel::new(func, 200., 10, 1.).unwrap()),
+            );
+        }
+        simulation.start_comparison("zero".to_owned(), "ramp".to_owned());
+        for _ in 0..4 {
+            simulation.step();
+        }
+
+        // Every node is off by t, averaged over t = 1..4
+        let difference = simulation.info()[0].comparisons["ramp"];
+        assert_eq!(difference.steps, 4);
+        assert!((difference.average_rms - 2.5).abs() < 1e-9);
+
+        simulation.start_comparison("zero".to_owned(), "ramp".to_owned());
+        simulation.step();
+        let difference = simulation.info()[0].comparisons["ramp"];
+        assert!((difference.average_rms - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn export_comparison_history() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        simulation.add_model("b".to_owned(), make_model());
+        simulation.start_comparison("a".to_owned(), "b".to_owned());
+        for _ in 0..3 {
+            simulation.step();
+        }
+
+        simulation.export_comparison("b".to_owned(), "a".to_owned(), PathBuf::from("a-b.csv"));
+        let exports = simulation.take_exports();
+        assert_eq!(exports.len(), 1);
+        let times: Vec<f64> = exports[0].history.iter().map(|(t, _)| *t).collect();
+        assert_eq!(times, vec![1., 2., 3.]);
+
+        simulation.stop_comparison("a".to_owned(), "b".to_owned());
+        simulation.export_comparison("a".to_owned(), "b".to_owned(), PathBuf::from("a-b.csv"));
+        assert!(simulation.take_exports().is_empty());
+        assert_eq!(simulation.take_errors().0.len(), 1);
+    }
+
+    #[test]
+    fn spectrum_peaks_at_the_wave_number() {
+        let nodes: Vec<f64> = (0..16)
+            .map(|i| (2. * std::f64::consts::PI * 3. * i as f64 / 16.).cos())
+            .collect();
+        let spectrum = magnitude_spectrum(&nodes);
+        assert_eq!(spectrum.len(), 9);
+        for (k, magnitude) in spectrum.iter().enumerate() {
+            let expected = if k == 3 { 0.5 } else { 0. };
+            assert!((magnitude - expected).abs() < 1e-12, "k = {}", k);
+        }
+
+        // Padded up to 16
+        assert_eq!(magnitude_spectrum(&nodes[..12]).len(), 9);
+    }
+
+    #[test]
+    fn pins_stay_frozen() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        simulation.pin_model("a".to_owned());
+        simulation.pin_model("missing".to_owned());
+        for _ in 0..3 {
+            simulation.step();
+        }
+        simulation.pin_model("a".to_owned());
+
+        let info = simulation.info();
+        let pins = &info[0].pins;
+        assert_eq!(pins.len(), 2);
+        assert_eq!((pins[0].time, pins[1].time), (0., 3.));
+        assert!(pins[0].difference > 0.);
+        assert!(pins[1].difference.abs() < 1e-12);
+        assert_ne!(pins[0].nodes, info[0].nodes);
+
+        simulation.unpin_model("a".to_owned(), 0);
+        simulation.unpin_model("a".to_owned(), 5);
+        assert_eq!(simulation.info()[0].pins[0].time, 3.);
+        assert_eq!(simulation.take_errors().0.len(), 2);
+
+        simulation.remove_model("a".to_owned());
+        simulation.add_model("a".to_owned(), make_model());
+        assert!(simulation.info()[0].pins.is_empty());
+    }
+
+    #[test]
+    fn scalar_history_follows_the_decay() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        simulation.step();
+        assert!(simulation.info()[0].scalar_history.is_empty());
+        assert!(simulation.scalar_histories.is_empty());
+
+        simulation.restart_model("a".to_owned());
+        simulation.set_scalar_history(true);
+        for _ in 0..3 {
+            simulation.step();
+        }
+        let history = simulation.info()[0].scalar_history.clone();
+        let times: Vec<f64> = history.iter().map(|s| s.time).collect();
+        assert_eq!(times, vec![1., 2., 3.]);
+        // The profile keeps its shape, so its heat decays like exp(-t)
+        let ratio = history[1].total_heat / history[0].total_heat;
+        assert!((ratio - (-1f64).exp()).abs() < 1e-9, "ratio {}", ratio);
+        assert!(history.iter().all(|s| s.net_flux.is_nan()));
+
+        simulation.restart_model("a".to_owned());
+        simulation.step();
+        assert_eq!(simulation.info()[0].scalar_history.len(), 1);
+    }
+
+    #[test]
+    fn error_map_records_every_step() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        let func = exmex::parse::<f64>("90*exp(-t)*sin(PI*x/200)").unwrap();
+        let cooler = Box::new(AnalyticModel::new(func, 200., 10, 1.).unwrap());
+        simulation.add_model("b".to_owned(), cooler);
+        simulation.start_comparison("a".to_owned(), "b".to_owned());
+        simulation.step();
+        assert!(simulation.info()[0].error_map.is_none());
+
+        simulation.set_error_map(true);
+        for _ in 0..3 {
+            simulation.step();
+        }
+        let info = simulation.info();
+        let map = info[0].error_map.as_ref().unwrap();
+        assert_eq!(map.other, "b");
+        assert_eq!(map.time_range(), Some((2., 4.)));
+        let (_, difference) = info[0].difference.as_ref().unwrap();
+        let newest = &map.rows.back().unwrap().1;
+        assert!(newest
+            .iter()
+            .zip(difference)
+            .all(|(e, d)| (e - d.abs()).abs() < 1e-12));
+        // The difference decays with the models, so the oldest row holds the peak
+        let oldest = &map.rows.front().unwrap().1;
+        assert_eq!(map.peak(), oldest.iter().cloned().fold(0., f64::max));
+        // Only the model that started the comparison has one
+        assert!(info[1].error_map.is_none());
+
+        simulation.restart_model("a".to_owned());
+        simulation.step();
+        let rows = simulation.info()[0].error_map.as_ref().unwrap().rows.len();
+        assert_eq!(rows, 1);
+
+        simulation.stop_comparison("a".to_owned(), "b".to_owned());
+        simulation.step();
+        assert!(simulation.info()[0].error_map.is_none());
+    }
+
+    #[test]
+    fn time_rate_covers_wall_time_in_whole_steps() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        let func = exmex::parse::<f64>("100*exp(-t)*sin(PI*x/200)").unwrap();
+        let fine = Box::new(AnalyticModel::new(func, 200., 10, 0.25).unwrap());
+        simulation.add_model("fine".to_owned(), fine);
+        simulation.advance(0.1);
+        assert_eq!(simulation.info()[0].elapsed_time, 1.);
+
+        simulation.set_time_rate(Some(2.));
+        // Half a second of simulated time: "a" owes half a step, "fine" takes two
+        simulation.advance(0.25);
+        let elapsed =
+            |s: &Simulation| -> Vec<f64> { s.info().iter().map(|m| m.elapsed_time).collect() };
+        assert_eq!(elapsed(&simulation), vec![1., 0.75]);
+        for _ in 0..3 {
+            simulation.advance(0.25);
+        }
+        assert_eq!(elapsed(&simulation), vec![3., 2.25]);
+
+        // A stall only catches up so far
+        simulation.advance(1e9);
+        let steps = simulation.info()[0].steps;
+        assert_eq!(steps, 3 + MAX_STEPS_PER_TICK);
+    }
+
+    #[test]
+    fn copied_state_is_resampled_onto_the_grid() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("exact".to_owned(), make_model());
+        let fine = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            200.,
+            DifferentialOptions::new(19, 1.),
+        )
+        .unwrap();
+        simulation.add_model("fine".to_owned(), Box::new(fine));
+        simulation.step();
+
+        simulation.copy_state_from("fine".to_owned(), "exact".to_owned());
+        let info = simulation.info();
+        let fine = &info[1];
+        assert_eq!(fine.elapsed_time, 1.);
+        for (x, u) in fine.positions.iter().zip(&fine.nodes) {
+            let expected = 100. * (-1f64).exp() * (std::f64::consts::PI * x / 200.).sin();
+            assert!((u - expected).abs() < 1e-9, "x = {}", x);
+        }
+
+        // Analytic models follow their expression and can't take nodes
+        simulation.copy_state_from("exact".to_owned(), "fine".to_owned());
+        simulation.copy_state_from("fine".to_owned(), "missing".to_owned());
+        let (errors, model_errors) = simulation.take_errors();
+        assert_eq!((errors.len(), model_errors.len()), (1, 1));
+    }
+
+    #[test]
+    fn references_are_only_evaluated_at_the_compared_time() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("a".to_owned(), make_model());
+        // The same solution on a finer grid with a shorter step
+        let func = exmex::parse::<f64>("100*exp(-t)*sin(PI*x/200)").unwrap();
+        let exact = Box::new(AnalyticModel::new(func, 200., 19, 0.5).unwrap());
+        simulation.add_model("exact".to_owned(), exact);
+        simulation.start_comparison("a".to_owned(), "exact".to_owned());
+        simulation.set_reference("exact".to_owned(), true);
+        for _ in 0..3 {
+            simulation.step();
+        }
+
+        assert_eq!(simulation.models["exact"].get_elapsed_time(), 0.);
+        let info = simulation.info();
+        assert!(info[1].reference);
+        assert!(info[0].comparisons["exact"].current < 1e-9);
+        let (_, difference) = info[0].difference.as_ref().unwrap();
+        assert!(difference.iter().all(|d| d.abs() < 1e-9));
+        simulation.sync_references();
+        assert_eq!(simulation.models["exact"].get_elapsed_time(), 3.);
+
+        // A numeric model has no closed form to evaluate
+        let numeric = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            200.,
+            DifferentialOptions::new(10, 1.),
+        )
+        .unwrap();
+        simulation.add_model("numeric".to_owned(), Box::new(numeric));
+        simulation.set_reference("numeric".to_owned(), true);
+        let (errors, _) = simulation.take_errors();
+        assert_eq!(errors.len(), 1);
+        simulation.step();
+        assert_eq!(simulation.models["numeric"].get_elapsed_time(), 1.);
+    }
+
+    #[test]
+    fn convergence_order_uses_the_node_spacing() {
+        let mut simulation = Simulation::new();
+        let runs = [10, 20]
+            .into_iter()
+            .map(|node_count| {
+                let func = exmex::parse::<f64>("100*exp(-PI*PI/40000*t)*sin(PI*x/200)").unwrap();
+                let exact = AnalyticModel::new(func, 200., node_count, 1.).unwrap();
+                (make_numeric(node_count), Box::new(exact) as Box<dyn Model>)
+            })
+            .collect();
+        simulation.run_convergence_study("numeric".to_owned(), 1000., runs);
+
+        // The study runs on a thread of its own, the simulation keeps stepping meanwhile
+        let started = Instant::now();
+        let studies = loop {
+            let studies = simulation.take_convergence_studies();
+            if !studies.is_empty() || started.elapsed() > Duration::from_secs(10) {
+                break studies;
+            }
+            simulation.step();
+        };
+        assert_eq!(studies.len(), 1);
+        // h goes from 200/9 to 200/19, which isn't a halving, log2 of the ratio gives 2.2
+        let order = studies[0].rows[1].order.unwrap();
+        assert!((order - 2.).abs() < 0.1, "order {}", order);
+    }
+
+    #[test]
+    fn long_seeks_replay_over_several_ticks() {
+        let mut simulation = Simulation::new();
+        simulation.add_model("numeric".to_owned(), make_numeric(10));
+        simulation.add_model("exact".to_owned(), make_model());
+        let seek = (SEEK_STEPS_PER_TICK * 2 + SEEK_STEPS_PER_TICK / 2) as f64;
+        simulation.seek_to("numeric".to_owned(), seek);
+        simulation.seek_to("exact".to_owned(), 1e9);
+
+        simulation.step();
+        let elapsed = |s: &Simulation, n: &str| s.models[n].get_elapsed_time();
+        let target = |s: &Simulation| {
+            s.info()
+                .iter()
+                .find(|m| m.name == "numeric")
+                .unwrap()
+                .seek_target
+        };
+        assert_eq!(elapsed(&simulation, "numeric"), SEEK_STEPS_PER_TICK as f64);
+        assert_eq!(target(&simulation), Some(seek));
+        // Closed forms get there at once and step on
+        assert_eq!(elapsed(&simulation, "exact"), 1e9 + 1.);
+        simulation.step();
+        assert_eq!(
+            elapsed(&simulation, "numeric"),
+            2. * SEEK_STEPS_PER_TICK as f64
+        );
+        // Once there the model steps on in the same tick
+        simulation.step();
+        assert_eq!(elapsed(&simulation, "numeric"), seek + 1.);
+        assert!(target(&simulation).is_none());
+
+        for t in [-1., f64::NAN, 1e12] {
+            simulation.seek_to("numeric".to_owned(), t);
+        }
+        let (_, model_errors) = simulation.take_errors();
+        assert_eq!(model_errors.len(), 3);
+        assert!(simulation.seeks.is_empty());
+        assert_eq!(elapsed(&simulation, "numeric"), seek + 1.);
     }
 }
@@ -0,0 +1,170 @@
+use std::thread::{spawn, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Response};
+
+use super::model_manager::{ModelInfo, Remote};
+use super::session::{build_model, ModelSpec};
+use crate::renderer::error::Error;
+
+/// How long a `nodes` request waits for the physics thread before giving up
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Body of a `POST /command`, tagged by `op`
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Command {
+    AddModel { name: String, spec: Box<ModelSpec> },
+    RemoveModel { name: String },
+    StartComparison { model_1: String, model_2: String },
+    StopComparison { model_1: String, model_2: String },
+    RestartModel { name: String },
+    Nodes,
+}
+
+#[derive(Serialize)]
+pub struct ModelNodes {
+    pub name: String,
+    pub positions: Vec<f64>,
+    pub nodes: Vec<f64>,
+    /// L2 difference to every model this one is compared against
+    pub comparisons: Vec<(String, f64)>,
+}
+
+impl From<&ModelInfo> for ModelNodes {
+    fn from(info: &ModelInfo) -> Self {
+        Self {
+            name: info.name.clone(),
+            positions: info.positions.clone(),
+            nodes: info.nodes.clone(),
+            comparisons: info
+                .comparisons
+                .iter()
+                .map(|(other, d)| (other.clone(), d.current))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+    Ok,
+    Models(Vec<ModelNodes>),
+    Error(String),
+}
+
+/// Accepts commands over HTTP on its own thread and sends them straight to the physics thread,
+/// so they don't wait for the GUI to draw a frame
+pub struct Server {
+    _thread: JoinHandle<()>,
+}
+
+impl Server {
+    pub fn start(address: &str, remote: Remote) -> Result<Self, Error> {
+        let server = tiny_http::Server::http(address)
+            .map_err(|e| Error::At(format!("Cannot listen on {}: {}", address, e)))?;
+        let thread = spawn(move || serve(server, remote));
+        Ok(Self { _thread: thread })
+    }
+}
+
+fn serve(server: tiny_http::Server, remote: Remote) {
+    for mut request in server.incoming_requests() {
+        let (status, reply) = match (request.method(), request.url()) {
+            (Method::Post, "/command") => {
+                let mut body = String::new();
+                match request.as_reader().read_to_string(&mut body) {
+                    Ok(_) => handle(&body, &remote),
+                    Err(e) => (400, Reply::Error(format!("Could not read body: {}", e))),
+                }
+            }
+            (_, url) => (404, Reply::Error(format!("Unknown endpoint {}", url))),
+        };
+
+        let json = serde_json::to_string(&reply).unwrap_or_default();
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        let response = Response::from_string(json)
+            .with_status_code(status)
+            .with_header(header);
+        // The client hanging up is its own problem
+        let _ = request.respond(response);
+    }
+}
+
+fn handle(body: &str, remote: &Remote) -> (u16, Reply) {
+    let command = match serde_json::from_str::<Command>(body) {
+        Ok(command) => command,
+        Err(e) => return (400, Reply::Error(format!("Invalid command: {}", e))),
+    };
+    match command {
+        Command::AddModel { name, spec } => {
+            let mut errors = None;
+            match build_model(&spec, &mut errors) {
+                Some(model) if errors.is_none() => remote.add_model(&name, model),
+                _ => return (422, Reply::Error(errors.unwrap_or_default())),
+            }
+        }
+        Command::RemoveModel { name } => remote.remove_model(&name),
+        Command::StartComparison { model_1, model_2 } => {
+            remote.start_comparison(&model_1, &model_2)
+        }
+        Command::StopComparison { model_1, model_2 } => remote.stop_comparison(&model_1, &model_2),
+        Command::RestartModel { name } => remote.restart_model(&name),
+        Command::Nodes => {
+            return match remote.get_info(REPLY_TIMEOUT) {
+                Some(info) => (
+                    200,
+                    Reply::Models(info.iter().map(ModelNodes::from).collect()),
+                ),
+                None => (
+                    504,
+                    Reply::Error("No reply from the physics thread".to_owned()),
+                ),
+            }
+        }
+    }
+    (200, Reply::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::model_manager::ModelManager;
+
+    #[test]
+    fn commands_reach_the_physics_thread_without_frames() {
+        let manager = ModelManager::new(Duration::from_micros(1));
+        let remote = manager.remote();
+        let add = r#"{"op": "add_model", "name": "a", "spec": {
+            "kind": "Analytic", "start_conditions": "0*x", "left_edge_conditions": "0*t",
+            "right_edge_conditions": "0*t", "coefficient": "1+0*x", "actual": "exp(-t)*sin(PI*x)",
+            "length": 1, "node_count": 5, "time_step": 0.1, "sigma": 0.5
+        }}"#;
+        assert_eq!(handle(add, &remote).0, 200);
+
+        // Nothing reads the channel of the manager, as if no frame was drawn
+        match handle(r#"{"op": "nodes"}"#, &remote) {
+            (200, Reply::Models(models)) => assert_eq!(models[0].name, "a"),
+            (status, _) => panic!("status {}", status),
+        }
+    }
+
+    #[test]
+    fn parses_tagged_commands() {
+        let command = serde_json::from_str::<Command>(
+            r#"{"op": "start_comparison", "model_1": "a", "model_2": "b"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            command,
+            Command::StartComparison { model_1, model_2 } if model_1 == "a" && model_2 == "b"
+        ));
+        assert!(matches!(
+            serde_json::from_str::<Command>(r#"{"op": "nodes"}"#).unwrap(),
+            Command::Nodes
+        ));
+        assert!(serde_json::from_str::<Command>(r#"{"op": "explode"}"#).is_err());
+    }
+}
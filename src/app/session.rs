@@ -0,0 +1,700 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use exmex::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::call;
+use crate::model::{
+    analytic::AnalyticModel,
+    differential::{
+        graded_positions, Boundaries, BoundaryKind, CoefficientStencil, DifferentialModel,
+        DifferentialOptions, SpatialOrder, TimeIntegrator,
+    },
+    error::ModelError,
+    model::{uses_time, Coefficient, InitialCondition, Model, Units},
+    series::AnalyticSeriesModel,
+    system::{ImplicitScheme, SystemModel, SystemOptions},
+};
+use crate::renderer::error::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ModelKind {
+    Differential,
+    Analytic,
+    System,
+    /// Sum of `series_term` over n = 1..=`term_count`
+    AnalyticSeries,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ModelSpec {
+    pub kind: ModelKind,
+    pub start_conditions: String,
+    pub left_edge_conditions: String,
+    pub right_edge_conditions: String,
+    pub coefficient: String,
+    pub actual: String,
+    pub length: f64,
+    pub node_count: u32,
+    pub time_step: f64,
+    pub sigma: f64,
+    /// Spacing ratio between neighbouring intervals, only used by differential models
+    #[serde(default = "uniform_grading")]
+    pub grading: f64,
+    #[serde(default)]
+    pub integrator: TimeIntegrator,
+    #[serde(default)]
+    pub spatial_order: SpatialOrder,
+    /// Only used by numeric models with an expression coefficient
+    #[serde(default)]
+    pub coefficient_stencil: CoefficientStencil,
+    /// Only used by system models
+    #[serde(default)]
+    pub implicit_scheme: ImplicitScheme,
+    /// Steps over which differential models blend their Dirichlet edges in from the
+    /// start conditions, 0 applies the edge conditions from the start
+    #[serde(default)]
+    pub ramp_steps: u32,
+    #[serde(default)]
+    pub left_boundary: BoundaryKind,
+    #[serde(default)]
+    pub right_boundary: BoundaryKind,
+    /// Measured `(x, temperature)` pairs used instead of `start_conditions` when set
+    #[serde(default)]
+    pub start_samples: Option<Vec<(f64, f64)>>,
+    /// `(x_boundary, a(x))` segments of a piecewise coefficient, used instead of `coefficient` when set
+    #[serde(default)]
+    pub coefficient_segments: Vec<(f64, String)>,
+    #[serde(default)]
+    pub units: Units,
+    /// Term of an analytic series in n, t and x
+    #[serde(default)]
+    pub series_term: String,
+    #[serde(default)]
+    pub term_count: u32,
+}
+
+fn uniform_grading() -> f64 {
+    1.
+}
+
+impl ModelSpec {
+    /// Whether `other` is this numeric model with only new edge conditions,
+    /// which the running model can take without starting over
+    pub fn differs_only_in_edges(&self, other: &ModelSpec) -> bool {
+        let with_other_edges = ModelSpec {
+            left_edge_conditions: other.left_edge_conditions.clone(),
+            right_edge_conditions: other.right_edge_conditions.clone(),
+            ..self.clone()
+        };
+        matches!(self.kind, ModelKind::Differential | ModelKind::System)
+            && self != other
+            && with_other_edges == *other
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Session {
+    pub models: Vec<(String, ModelSpec)>,
+    pub comparisons: Vec<(String, String)>,
+}
+
+impl Session {
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = call!(serde_json::to_string_pretty(self))?;
+        call!(fs::write(path, json))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let json = call!(fs::read_to_string(path))?;
+        call!(serde_json::from_str(&json))
+    }
+}
+
+/// Named sessions kept in one file, loaded on top of whatever models are already running
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Presets {
+    pub presets: Vec<(String, Session)>,
+}
+
+impl Presets {
+    /// A missing file is an empty preset list
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(json) => call!(serde_json::from_str(&json)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => call!(Err(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = call!(serde_json::to_string_pretty(self))?;
+        if let Some(dir) = path.parent() {
+            call!(fs::create_dir_all(dir))?;
+        }
+        call!(fs::write(path, json))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Session> {
+        self.presets.iter().find(|(n, _)| n == name).map(|(_, s)| s)
+    }
+
+    /// Replaces the preset with the same name if there is one
+    pub fn insert(&mut self, name: String, session: Session) {
+        match self.presets.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, s)) => *s = session,
+            None => self.presets.push((name, session)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.presets.retain(|(n, _)| n != name);
+    }
+}
+
+/// Why `read_samples` could not load a file
+#[derive(Debug)]
+pub enum CsvError {
+    Io(std::io::Error),
+    /// The 1-based line number and what is wrong with it
+    Line(usize, String),
+    Empty,
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO - {}", e),
+            Self::Line(line, e) => write!(f, "line {}: {}", line, e),
+            Self::Empty => write!(f, "no samples"),
+        }
+    }
+}
+
+/// Reads `x,temperature` lines, sorted by x. A header line that is not numeric is skipped
+pub fn read_samples(path: &Path) -> Result<Vec<(f64, f64)>, CsvError> {
+    let csv = fs::read_to_string(path).map_err(CsvError::Io)?;
+    let mut samples = vec![];
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (x, t) = line
+            .split_once(',')
+            .ok_or_else(|| CsvError::Line(i + 1, "expected x,temperature".to_owned()))?;
+        match (x.trim().parse::<f64>(), t.trim().parse::<f64>()) {
+            // `parse` takes "NaN" and "inf", which would spread through the whole model
+            (Ok(x), Ok(t)) if !x.is_finite() || !t.is_finite() => {
+                return Err(CsvError::Line(i + 1, "values have to be finite".to_owned()))
+            }
+            (Ok(x), Ok(t)) => samples.push((x, t)),
+            _ if i == 0 => {}
+            (Err(e), _) | (_, Err(e)) => return Err(CsvError::Line(i + 1, e.to_string())),
+        }
+    }
+    if samples.is_empty() {
+        return Err(CsvError::Empty);
+    }
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(samples)
+}
+
+/// Writes the nodes as `x,temperature` lines that `read_samples` can load back,
+/// the header names the units
+pub fn write_samples(
+    path: &Path,
+    positions: &[f64],
+    nodes: &[f64],
+    units: &Units,
+) -> Result<(), Error> {
+    let header = format!(
+        "x{},temperature{}",
+        Units::bracketed(&units.length),
+        Units::bracketed(&units.temperature)
+    );
+    write_pairs(
+        path,
+        &header,
+        positions.iter().copied().zip(nodes.iter().copied()),
+    )
+}
+
+/// Writes a comparison history as `time,difference` lines, the header names the units
+pub fn write_history(path: &Path, history: &[(f64, f64)], units: &Units) -> Result<(), Error> {
+    let header = format!(
+        "time{},difference{}",
+        Units::bracketed(&units.time),
+        Units::bracketed(&units.temperature)
+    );
+    write_pairs(path, &header, history.iter().copied())
+}
+
+fn write_pairs(
+    path: &Path,
+    header: &str,
+    pairs: impl Iterator<Item = (f64, f64)>,
+) -> Result<(), Error> {
+    let csv = pairs.fold(format!("{}\n", header), |acc, (a, b)| {
+        acc + &format!("{},{}\n", a, b)
+    });
+    call!(fs::write(path, csv))
+}
+
+/// Parses the coefficient of every segment, each holding up to its boundary
+fn make_segments(
+    segments: &[(f64, String)],
+    error_accumulator: &mut Option<String>,
+) -> Vec<(f64, exmex::FlatEx<f64>)> {
+    if !segments.windows(2).all(|w| w[0].0 < w[1].0) {
+        *error_accumulator = Some(format!(
+            "{}Invalid coefficient segments: boundaries must increase\n",
+            error_accumulator.as_ref().unwrap_or(&"".to_owned()),
+        ));
+    }
+    segments
+        .iter()
+        .map(|(x, a)| {
+            let message = format!("Invalid coefficient of the segment up to {}", x);
+            (*x, make_coefficient(a, &message, error_accumulator))
+        })
+        .collect()
+}
+
+/// Like `make_expr` of x, but a coefficient may also use t and is then left unpadded,
+/// since padding would shift which variable t and x are evaluated as
+fn make_coefficient(
+    expr_str: &str,
+    error_message: &str,
+    error_accumulator: &mut Option<String>,
+) -> exmex::FlatEx<f64> {
+    match exmex::parse::<f64>(expr_str) {
+        Ok(expr) if uses_time(&expr) => {
+            if !expr.var_names().iter().all(|v| v == "t" || v == "x") {
+                *error_accumulator = Some(format!(
+                    "{}{}: only t and x can be used\n",
+                    error_accumulator.as_ref().unwrap_or(&"".to_owned()),
+                    error_message,
+                ));
+            }
+            expr
+        }
+        _ => make_expr(expr_str, error_message, &["x"], error_accumulator),
+    }
+}
+
+/// Parses an expression of `variables`, given in alphabetical order like exmex evaluates them.
+/// Variables it doesn't use are added as `+v-v`, so the model can always pass all of them.
+/// A parse error or a variable not in `variables` is appended to `error_accumulator`
+fn make_expr(
+    expr_str: &str,
+    error_message: &str,
+    variables: &[&str],
+    error_accumulator: &mut Option<String>,
+) -> exmex::FlatEx<f64> {
+    let expr = match exmex::parse::<f64>(expr_str) {
+        Ok(expr) => expr,
+        Err(e) => {
+            *error_accumulator = Some(format!(
+                "{}{}: {}\n",
+                error_accumulator.as_ref().unwrap_or(&"".to_owned()),
+                error_message,
+                e
+            ));
+            return make_expr("0", error_message, variables, error_accumulator);
+        }
+    };
+    if let Some(v) = expr
+        .var_names()
+        .iter()
+        .find(|v| !variables.contains(&v.as_str()))
+    {
+        *error_accumulator = Some(format!(
+            "{}{}: unknown variable {}, expected {}\n",
+            error_accumulator.as_ref().unwrap_or(&"".to_owned()),
+            error_message,
+            v,
+            variables.join(" and ")
+        ));
+        return expr;
+    }
+    let padded = variables
+        .iter()
+        .filter(|v| !expr.var_names().iter().any(|n| n == *v))
+        .fold(format!("({})", expr_str), |acc, v| {
+            format!("{}+{}-{}", acc, v, v)
+        });
+    exmex::parse::<f64>(&padded).unwrap_or(expr)
+}
+
+pub fn build_model(spec: &ModelSpec, errors: &mut Option<String>) -> Option<Box<dyn Model>> {
+    let mut model = build_dimensionless_model(spec, errors)?;
+    model.set_units(spec.units.clone());
+    Some(model)
+}
+
+/// Parses the (left, right) edge conditions of a numeric model
+pub fn build_edges(
+    spec: &ModelSpec,
+    errors: &mut Option<String>,
+) -> (exmex::FlatEx<f64>, exmex::FlatEx<f64>) {
+    let left = make_expr(
+        &spec.left_edge_conditions[..],
+        "Invalid left edge conditions",
+        &["t"],
+        errors,
+    );
+    let right = make_expr(
+        &spec.right_edge_conditions[..],
+        "Invalid right edge coditions",
+        &["t"],
+        errors,
+    );
+    (left, right)
+}
+
+fn build_dimensionless_model(
+    spec: &ModelSpec,
+    errors: &mut Option<String>,
+) -> Option<Box<dyn Model>> {
+    match spec.kind {
+        ModelKind::Analytic => {
+            let f = make_expr(
+                &spec.actual[..],
+                "Invalid actual field",
+                &["t", "x"],
+                errors,
+            );
+            if errors.is_some() {
+                return None;
+            }
+            started(
+                AnalyticModel::new(f, spec.length, spec.node_count, spec.time_step),
+                errors,
+            )
+        }
+        ModelKind::AnalyticSeries => {
+            let term = match exmex::parse::<f64>(&spec.series_term) {
+                Ok(term) => term,
+                Err(e) => {
+                    *errors = Some(format!(
+                        "{}Invalid series term field: {}\n",
+                        errors.as_ref().unwrap_or(&"".to_owned()),
+                        e
+                    ));
+                    return None;
+                }
+            };
+            started(
+                AnalyticSeriesModel::new(
+                    term,
+                    spec.term_count,
+                    spec.length,
+                    spec.node_count,
+                    spec.time_step,
+                ),
+                errors,
+            )
+        }
+        ModelKind::Differential | ModelKind::System => {
+            let sc = match &spec.start_samples {
+                Some(samples) => InitialCondition::Samples(samples.clone()),
+                None => InitialCondition::Expr(Box::new(make_expr(
+                    &spec.start_conditions[..],
+                    "Invalid start conditions field",
+                    &["x"],
+                    errors,
+                ))),
+            };
+            let (lc, rc) = build_edges(spec, errors);
+            let c = make_coefficient(&spec.coefficient[..], "Invalid coefficient field", errors);
+            let segments = if spec.coefficient_segments.is_empty() {
+                None
+            } else if spec.kind == ModelKind::System {
+                *errors = Some(format!(
+                    "{}Invalid coefficient segments: piecewise coefficients need a Differential Model\n",
+                    errors.as_ref().unwrap_or(&"".to_owned()),
+                ));
+                None
+            } else {
+                Some(make_segments(&spec.coefficient_segments, errors))
+            };
+            // Each edge of a differential model can have its own kind, a periodic one
+            // is checked for a periodic partner by the model
+            let boundaries = Boundaries {
+                left: spec.left_boundary,
+                right: spec.right_boundary,
+            };
+            if spec.kind == ModelKind::System && boundaries != BoundaryKind::Dirichlet.into() {
+                *errors = Some(format!(
+                    "{}Invalid boundaries: periodic and Neumann boundaries need a Differential Model\n",
+                    errors.as_ref().unwrap_or(&"".to_owned()),
+                ));
+            }
+            if errors.is_some() {
+                return None;
+            }
+
+            if spec.kind == ModelKind::System {
+                started(
+                    SystemModel::new(
+                        sc,
+                        lc,
+                        rc,
+                        c,
+                        spec.length,
+                        SystemOptions {
+                            sigma: spec.sigma,
+                            scheme: spec.implicit_scheme,
+                            coefficient_stencil: spec.coefficient_stencil,
+                            ..SystemOptions::new(spec.node_count, spec.time_step)
+                        },
+                    ),
+                    errors,
+                )
+            } else {
+                started(
+                    DifferentialModel::new(
+                        sc,
+                        lc,
+                        rc,
+                        match segments {
+                            Some(segments) => Coefficient::Piecewise(segments),
+                            None => Coefficient::Expr(Box::new(c)),
+                        },
+                        spec.length,
+                        DifferentialOptions {
+                            grading: spec.grading,
+                            integrator: spec.integrator,
+                            boundaries,
+                            spatial_order: spec.spatial_order,
+                            coefficient_stencil: spec.coefficient_stencil,
+                            ramp_steps: spec.ramp_steps,
+                            ..DifferentialOptions::new(spec.node_count, spec.time_step)
+                        },
+                    ),
+                    errors,
+                )
+            }
+        }
+    }
+}
+
+/// Largest a²·dt/h² of a numeric spec at t = 0, what decides whether an explicit scheme is stable.
+/// `None` while the coefficient can't be evaluated
+pub fn stability_factor(spec: &ModelSpec) -> Option<f64> {
+    let mut errors = None;
+    let coefficient = if spec.coefficient_segments.is_empty() || spec.kind == ModelKind::System {
+        Coefficient::Expr(Box::new(make_coefficient(
+            &spec.coefficient,
+            "",
+            &mut errors,
+        )))
+    } else {
+        Coefficient::Piecewise(make_segments(&spec.coefficient_segments, &mut errors))
+    };
+    if errors.is_some() || spec.node_count < 2 {
+        return None;
+    }
+
+    // System Models ignore the grading
+    let grading = if spec.kind == ModelKind::System {
+        1.
+    } else {
+        spec.grading
+    };
+    let positions = graded_positions(spec.length, spec.node_count, grading);
+    let h = positions
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .fold(f64::INFINITY, f64::min);
+    let mut a2 = 0.;
+    for x in positions {
+        let a = coefficient.eval(x, 0.).ok()?;
+        a2 = f64::max(a2, a * a);
+    }
+    Some(a2 * spec.time_step / (h * h))
+}
+
+/// A model whose start or edge conditions can't be evaluated is reported like an invalid field
+fn started<M: Model + 'static>(
+    model: Result<M, ModelError>,
+    errors: &mut Option<String>,
+) -> Option<Box<dyn Model>> {
+    match model {
+        Ok(model) => Some(Box::new(model)),
+        Err(e) => {
+            *errors = Some(format!(
+                "{}Cannot start model: {}\n",
+                errors.as_ref().unwrap_or(&"".to_owned()),
+                e
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_session_keeps_io_cause() {
+        let path = std::env::temp_dir().join("thermal-no-such-dir/session.json");
+        let e = Session::load(&path).unwrap_err();
+        assert!(matches!(
+            e.cause(),
+            Error::Io(io) if io.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn presets_replace_by_name() {
+        let path = std::env::temp_dir().join("thermal-presets-round-trip.json");
+        let _ = fs::remove_file(&path);
+        let mut presets = Presets::load(&path).unwrap();
+        assert!(presets.presets.is_empty());
+
+        let mut session = Session::default();
+        session.comparisons.push(("a".to_owned(), "b".to_owned()));
+        presets.insert("demo".to_owned(), Session::default());
+        presets.insert("other".to_owned(), Session::default());
+        presets.insert("demo".to_owned(), session);
+        presets.save(&path).unwrap();
+
+        let mut presets = Presets::load(&path).unwrap();
+        assert_eq!(presets.presets.len(), 2);
+        assert_eq!(presets.presets[0].0, "demo");
+        assert_eq!(presets.get("demo").unwrap().comparisons.len(), 1);
+        presets.remove("demo");
+        assert!(presets.get("demo").is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stability_factor_of_unit_rod() {
+        let mut spec: ModelSpec = serde_json::from_str(
+            r#"{"kind": "Differential", "start_conditions": "0", "left_edge_conditions": "0",
+                "right_edge_conditions": "0", "coefficient": "2", "actual": "0",
+                "length": 1, "node_count": 11, "time_step": 0.001, "sigma": 0}"#,
+        )
+        .unwrap();
+        assert!((stability_factor(&spec).unwrap() - 0.4).abs() < 1e-12);
+
+        spec.coefficient = "1+x".to_owned();
+        assert!((stability_factor(&spec).unwrap() - 0.4).abs() < 1e-12);
+
+        spec.coefficient = "1+".to_owned();
+        assert!(stability_factor(&spec).is_none());
+    }
+
+    #[test]
+    fn only_new_edges_keep_the_model_running() {
+        let spec: ModelSpec = serde_json::from_str(
+            r#"{"kind": "Differential", "start_conditions": "0", "left_edge_conditions": "0",
+                "right_edge_conditions": "0", "coefficient": "1", "actual": "0",
+                "length": 1, "node_count": 11, "time_step": 0.001, "sigma": 0}"#,
+        )
+        .unwrap();
+        assert!(!spec.differs_only_in_edges(&spec));
+
+        let mut edges = spec.clone();
+        edges.left_edge_conditions = "50+t".to_owned();
+        assert!(spec.differs_only_in_edges(&edges));
+        let mut errors = None;
+        let (left, _) = build_edges(&edges, &mut errors);
+        assert!(errors.is_none());
+        assert_eq!(left.eval(&[1.]).unwrap(), 51.);
+
+        let mut grid = edges.clone();
+        grid.node_count = 21;
+        assert!(!spec.differs_only_in_edges(&grid));
+        let analytic = ModelSpec {
+            kind: ModelKind::Analytic,
+            ..spec.clone()
+        };
+        let edges = ModelSpec {
+            kind: ModelKind::Analytic,
+            ..edges
+        };
+        assert!(!analytic.differs_only_in_edges(&edges));
+    }
+
+    #[test]
+    fn samples_round_trip() {
+        let path = std::env::temp_dir().join("thermal-samples-round-trip.csv");
+        let positions = [0., 0.5, 1.];
+        let nodes = [10., 20.25, -3.];
+        let units = Units {
+            length: "m".to_owned(),
+            temperature: "K".to_owned(),
+            time: "s".to_owned(),
+            diffusivity: "".to_owned(),
+        };
+        write_samples(&path, &positions, &nodes, &units).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("x (m),temperature (K)\n"));
+
+        let samples = read_samples(&path).unwrap();
+        assert_eq!(samples, vec![(0., 10.), (0.5, 20.25), (1., -3.)]);
+
+        fs::write(&path, "1,2\n0,1\n").unwrap();
+        assert_eq!(read_samples(&path).unwrap(), vec![(0., 1.), (1., 2.)]);
+        fs::write(&path, "x,temperature\n0,hot\n").unwrap();
+        assert!(read_samples(&path).is_err());
+        fs::write(&path, "0,1\n0.5,NaN\n1,2\n").unwrap();
+        assert!(matches!(read_samples(&path), Err(CsvError::Line(2, _))));
+        fs::write(&path, "0,1\ninf,2\n").unwrap();
+        assert!(matches!(read_samples(&path), Err(CsvError::Line(2, _))));
+
+        write_history(&path, &[(1., 0.5)], &units).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "time (s),difference (K)\n1,0.5\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn expressions_only_take_their_variables() {
+        let mut errors = None;
+        let expr = make_expr("x^2", "Invalid actual field", &["t", "x"], &mut errors);
+        assert!(errors.is_none());
+        assert_eq!(expr.eval(&[5., 3.]).unwrap(), 9.);
+        // Only t used to end up evaluated at x
+        let expr = make_expr("2*t", "Invalid actual field", &["t", "x"], &mut errors);
+        assert_eq!(expr.eval(&[5., 3.]).unwrap(), 10.);
+        let expr = make_expr("7", "Invalid left edge conditions", &["t"], &mut errors);
+        assert_eq!(expr.eval(&[5.]).unwrap(), 7.);
+        assert!(errors.is_none());
+
+        // One variable like the start conditions expect, but not the right one
+        make_expr(
+            "100*sin(y)",
+            "Invalid start conditions field",
+            &["x"],
+            &mut errors,
+        );
+        make_expr("1+", "Invalid left edge conditions", &["t"], &mut errors);
+        let errors = errors.unwrap();
+        assert!(errors.contains("Invalid start conditions field: unknown variable y, expected x"));
+        assert!(errors.contains("Invalid left edge conditions"));
+
+        let mut spec: ModelSpec = serde_json::from_str(
+            r#"{"kind": "Differential", "start_conditions": "100*sin(y)",
+                "left_edge_conditions": "0", "right_edge_conditions": "0", "coefficient": "1",
+                "actual": "0", "length": 1, "node_count": 11, "time_step": 0.001, "sigma": 0}"#,
+        )
+        .unwrap();
+        let mut errors = None;
+        assert!(build_model(&spec, &mut errors).is_none());
+        assert!(errors.unwrap().contains("unknown variable y"));
+
+        spec.start_conditions = "100*sin(x)".to_owned();
+        let mut errors = None;
+        assert!(build_model(&spec, &mut errors).is_some(), "{:?}", errors);
+    }
+}
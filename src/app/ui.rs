@@ -1,106 +1,817 @@
-use std::{collections::HashMap, rc::Rc, time::Duration};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
 
 use crate::model::{
-    analytic::AnalyticModel, differential::DifferentialModel, model::Model, system::SystemModel,
+    differential::{BoundaryKind, CoefficientStencil, SpatialOrder, TimeIntegrator},
+    model::{Model, Units},
+    system::ImplicitScheme,
 };
-use egui;
-use exmex::prelude::*;
+use native_dialog::FileDialog;
 
-use super::model_manager::ModelInfo;
+use super::model_manager::{ConvergenceStudy, ModelInfo, ScalarSample};
+use super::session::{
+    build_edges, build_model, read_samples, stability_factor, write_samples, ModelKind, ModelSpec,
+    Presets, Session,
+};
+use super::view::{picked_color, StripLayout};
 
 pub trait Reducer<POST, GET> {
     fn reduce(&mut self, op: POST);
     fn request(&mut self, op: &mut GET);
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    Heatmap,
+    LineGraph,
+    /// The difference of each comparison over x and time
+    ErrorMap,
+}
+
+/// The log scale assumes positive temperatures, anything below this is shown as this
+pub const LOG_SCALE_EPSILON: f32 = 1e-6;
+
+/// How temperatures are spread over the color range of the heatmap
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorScale {
+    Linear,
+    /// Evenly spaced in `log10`, for fields spanning several orders of magnitude
+    Log,
+    /// Evenly spaced in `sign(u)·ln(1 + |u|)`, like `Log` far from zero but linear
+    /// around it, so zero and negative temperatures keep their own colors
+    SignedLog,
+}
+
+impl ColorScale {
+    /// Temperature at the fraction `t` of the color range `(min, max)`
+    pub fn value_at(self, t: f32, range: (f32, f32)) -> f32 {
+        let (min, max) = range;
+        match self {
+            Self::Linear => min + t * (max - min),
+            Self::Log => {
+                let min = min.max(LOG_SCALE_EPSILON).log10();
+                let max = max.max(LOG_SCALE_EPSILON).log10();
+                10f32.powf(min + t * (max - min))
+            }
+            Self::SignedLog => {
+                let (min, max) = (signed_log(min), signed_log(max));
+                let s = min + t * (max - min);
+                s.signum() * s.abs().exp_m1()
+            }
+        }
+    }
+
+    /// The `uColorScale` the heatmap shader takes
+    pub fn shader_index(self) -> i32 {
+        match self {
+            Self::Linear => 0,
+            Self::Log => 1,
+            Self::SignedLog => 2,
+        }
+    }
+}
+
+/// `sign(u)·ln(1 + |u|)`
+fn signed_log(u: f32) -> f32 {
+    u.signum() * u.abs().ln_1p()
+}
+
 pub enum UiPost {
     AddModel(String, Box<dyn Model>),
+    UpdateModel(String, Box<dyn Model>),
     RemoveModel(String),
+    /// Adds a copy of the first model under the second name, starting from t = 0
+    DuplicateModel(String, String),
     StartComparison(String, String),
     StopComparison(String, String),
     RestartModel(String),
+    /// Replace the (left, right) edge conditions, keeping the interior and the time.
+    /// Boxed as the expressions are large
+    ReseedEdges(String, Box<(exmex::FlatEx<f64>, exmex::FlatEx<f64>)>),
+    /// (destination, source)
+    CopyStateFrom(String, String),
+    /// Keep the model's current temperatures as a static reference
+    PinModel(String),
+    /// Drop the model's nth pinned snapshot
+    UnpinModel(String, usize),
+    /// Stop stepping an analytic model and evaluate it at the time of what it is compared against
+    SetReference(String, bool),
+    JumpToTime(String, f64),
     SetMinTickTime(Duration),
-    SetMinFrameTime(Duration),
+    /// Playback speed relative to the min tick time, 1.0 is unchanged
+    SetSpeedMultiplier(f64),
+    /// Simulated seconds per wall-clock second, `None` steps once per tick
+    SetTimeRate(Option<f64>),
+    /// Background behind the models, RGB
+    SetClearColor([f32; 3]),
+    TakeScreenshot,
+    /// Save every Nth frame to the directory until stopped
+    StartRecording(PathBuf, usize),
+    /// Stop recording, encoding the frames with ffmpeg if true
+    StopRecording(bool),
+    SetColorRange(f32, f32),
+    SetColorScale(ColorScale),
+    /// Replaces the palette color of a model
+    SetModelColor(String, [f32; 3]),
+    /// Moving average radius for displaying the heatmap, 0 turns it off
+    SetSmoothing(usize),
+    SetRenderMode(RenderMode),
+    /// Height and spacing of the heatmap strips
+    SetStripLayout(StripLayout),
+    /// Draw `|u1 - u2|` of each model's first comparison below it
+    SetShowDifferences(bool),
+    /// Compute the spatial spectrum of every model's nodes
+    SetShowSpectrum(bool),
+    /// Record total heat, max temperature and net flux of every model after each step
+    SetShowScalars(bool),
+    RunConvergenceStudy(String, f64, Vec<(Box<dyn Model>, Box<dyn Model>)>),
+    ExportComparison(String, String, PathBuf),
+    /// Steady-state tolerance and whether converged models stop stepping
+    SetSteadyState(f64, bool),
 }
 
 pub enum UiGet {
     ModelInfo(Option<Rc<Vec<ModelInfo>>>),
     GetTps(Option<usize>),
-    GetFps(Option<usize>),
+    GetTickTimes(Option<(f64, f64)>),
+    GetMultisampleSamples(Option<u8>),
+    /// Frames saved by the current recording, `Some(None)` when not recording
+    RecordedFrames(Option<Option<usize>>),
+    Errors(Option<Vec<String>>),
+    ConvergenceStudies(Option<Vec<ConvergenceStudy>>),
+    /// Colors picked in the model list, the other models keep the one of their index
+    ModelColors(Option<HashMap<String, [f32; 3]>>),
 }
 
-fn make_expr(
-    expr_str: &str,
-    error_message: &str,
-    expected_args: usize,
-    error_accumulator: &mut Option<String>,
-) -> exmex::FlatEx<f64> {
-    let mut expr = exmex::parse::<f64>(expr_str).unwrap_or_else(|e| {
-        *error_accumulator = Some(format!(
-            "{}{}: {}\n",
-            error_accumulator.as_ref().unwrap_or(&"".to_owned()),
-            error_message,
-            e
-        ));
-        make_expr("x-x", error_message, expected_args, error_accumulator)
-    });
-    if expr.var_names().len() < expected_args {
-        let new_expr = (0..(expected_args - expr.var_names().len()))
-            .map(|n| format!("+arg{}-arg{}", n, n))
-            .fold(expr_str.to_owned(), |acc, elem| acc + &elem);
-        expr = exmex::parse::<f64>(&new_expr).unwrap();
-    } else if expr.var_names().len() > expected_args {
-        *error_accumulator = Some(format!(
-            "{}{}: too many arguments, expected{}\n",
-            error_accumulator.as_ref().unwrap_or(&"".to_owned()),
-            error_message,
-            expected_args
-        ));
+/// What the plot over time shows of each model
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlottedScalar {
+    TotalHeat,
+    MaxTemperature,
+    /// Heat flowing in through both edges
+    NetFlux,
+}
+
+impl PlottedScalar {
+    fn of(&self, sample: &ScalarSample) -> f64 {
+        match self {
+            Self::TotalHeat => sample.total_heat,
+            Self::MaxTemperature => sample.max,
+            Self::NetFlux => sample.net_flux,
+        }
+    }
+}
+
+/// Brightness of a model's tint in the heatmap, `t` is the normalized temperature
+fn temperature_color(t: f32) -> egui::Color32 {
+    let v = (t.clamp(0., 1.) * 255.) as u8;
+    egui::Color32::from_rgb(v, v, v)
+}
+
+/// The colors picked in the model list, kept by the reducer so the heatmap uses them too
+fn model_colors(reducer: &mut dyn Reducer<UiPost, UiGet>) -> HashMap<String, [f32; 3]> {
+    let mut colors = UiGet::ModelColors(None);
+    reducer.request(&mut colors);
+    match colors {
+        UiGet::ModelColors(Some(colors)) => colors,
+        _ => HashMap::new(),
     }
-    expr
 }
+
 pub struct Controls {
     start_conditions: String,
     left_edge_conditions: String,
     right_edge_conditions: String,
     coefficient: String,
+    /// `(x_boundary, a(x))` of each material, replaces `coefficient` when not empty
+    coefficient_segments: Vec<(f64, String)>,
+    units: Units,
     actual: String,
+    series_term: String,
+    term_count: u32,
     node_count: u32,
+    node_count_max: u32,
+    /// Loaded from a CSV, replaces the starting conditions expression while set
+    start_samples: Option<Vec<(f64, f64)>>,
     time_step: f64,
     length: f64,
     sigma: f64,
+    grading: f64,
+    integrator: TimeIntegrator,
+    spatial_order: SpatialOrder,
+    coefficient_stencil: CoefficientStencil,
+    implicit_scheme: ImplicitScheme,
+    ramp_steps: u32,
+    left_boundary: BoundaryKind,
+    right_boundary: BoundaryKind,
     model_name: String,
     add_comparison: HashMap<String, String>,
+    jump_time: HashMap<String, f64>,
+    specs: HashMap<String, ModelSpec>,
+    editing: Option<String>,
     min_tick_time: u64,
-    min_frame_time: u64,
+    speed_multiplier: f64,
+    fixed_time_rate: bool,
+    time_rate: f64,
+    clear_color: [f32; 3],
+    record_dir: String,
+    record_every: usize,
+    encode_recording: bool,
+    color_min: f32,
+    color_max: f32,
+    color_scale: ColorScale,
+    smoothing: bool,
+    smoothing_radius: usize,
+    show_differences: bool,
+    show_spectrum: bool,
+    /// Plot `log10` of the spectrum magnitudes
+    spectrum_log: bool,
+    show_scalars: bool,
+    plotted_scalar: PlottedScalar,
+    /// Plot `log10 |value|`, exponential decay turns into a straight line
+    scalar_log: bool,
+    steady_tolerance: f64,
+    pause_steady: bool,
+    render_mode: RenderMode,
+    strip_layout: StripLayout,
+    study_time: f64,
+    convergence_studies: Vec<ConvergenceStudy>,
+    study_node_counts: String,
+    /// Analytic reference and the (node count, name) of every model compared against it
+    live_studies: Vec<(String, Vec<(u32, String)>)>,
+
+    presets: Presets,
+    preset_name: String,
 
     errors: Option<String>,
 }
 
+/// Where comparison presets are kept, in the user's config directory so they don't
+/// depend on where the simulator was started from
+fn presets_path() -> PathBuf {
+    match dirs_next::config_dir() {
+        Some(dir) => dir.join("thermal-conductivity-sim").join("presets.json"),
+        None => PathBuf::from("presets.json"),
+    }
+}
+
 impl Controls {
     pub fn new() -> Self {
+        let (presets, errors) = match Presets::load(&presets_path()) {
+            Ok(presets) => (presets, None),
+            Err(e) => (
+                Presets::default(),
+                Some(format!("Could not load presets: {}\n", e.cause())),
+            ),
+        };
         Self {
             coefficient: "1".to_owned(),
+            coefficient_segments: vec![],
+            units: Units::default(),
             left_edge_conditions: "0".to_owned(),
             right_edge_conditions: "0".to_owned(),
             start_conditions: "100*sin(PI*x/200)".to_owned(),
             actual: "100*exp(-(PI/200)*(PI/200)*t)*sin(PI*x/200)".to_owned(),
+            series_term: "200*(1-cos(n*PI))/(n*PI)*exp(0-(n*PI/200)^2*t)*sin(n*PI*x/200)"
+                .to_owned(),
+            term_count: 50,
             length: 200.,
             node_count: 100,
+            node_count_max: 300,
+            start_samples: None,
             time_step: 1.,
             sigma: 0.5,
+            grading: 1.,
+            integrator: TimeIntegrator::ForwardEuler,
+            spatial_order: SpatialOrder::Second,
+            coefficient_stencil: CoefficientStencil::Faces,
+            implicit_scheme: ImplicitScheme::Theta,
+            ramp_steps: 0,
+            left_boundary: BoundaryKind::Dirichlet,
+            right_boundary: BoundaryKind::Dirichlet,
             model_name: String::new(),
             add_comparison: HashMap::new(),
-            errors: None,
-            min_frame_time: 10,
+            jump_time: HashMap::new(),
+            specs: HashMap::new(),
+            editing: None,
+            clear_color: [0.5, 0.5, 0.5],
+            record_dir: "recording".to_owned(),
+            record_every: 1,
+            encode_recording: true,
             min_tick_time: 1,
+            speed_multiplier: 1.,
+            fixed_time_rate: false,
+            time_rate: 1.,
+            color_min: 0.,
+            color_max: 100.,
+            color_scale: ColorScale::Linear,
+            smoothing: false,
+            smoothing_radius: 1,
+            show_differences: true,
+            show_spectrum: false,
+            spectrum_log: true,
+            show_scalars: false,
+            plotted_scalar: PlottedScalar::TotalHeat,
+            scalar_log: false,
+            steady_tolerance: 1e-6,
+            pause_steady: false,
+            render_mode: RenderMode::Heatmap,
+            strip_layout: StripLayout::default(),
+            study_time: 100.,
+            convergence_studies: vec![],
+            study_node_counts: "25, 50, 100, 200".to_owned(),
+            live_studies: vec![],
+            presets,
+            preset_name: "".to_owned(),
+            errors,
+        }
+    }
+
+    fn current_spec(&self, kind: ModelKind) -> ModelSpec {
+        ModelSpec {
+            kind,
+            start_conditions: self.start_conditions.clone(),
+            left_edge_conditions: self.left_edge_conditions.clone(),
+            right_edge_conditions: self.right_edge_conditions.clone(),
+            coefficient: self.coefficient.clone(),
+            actual: self.actual.clone(),
+            series_term: self.series_term.clone(),
+            term_count: self.term_count,
+            length: self.length,
+            node_count: self.node_count,
+            time_step: self.time_step,
+            sigma: self.sigma,
+            grading: self.grading,
+            integrator: self.integrator,
+            spatial_order: self.spatial_order,
+            coefficient_stencil: self.coefficient_stencil,
+            implicit_scheme: self.implicit_scheme,
+            ramp_steps: self.ramp_steps,
+            left_boundary: self.left_boundary,
+            right_boundary: self.right_boundary,
+            start_samples: self.start_samples.clone(),
+            coefficient_segments: self.coefficient_segments.clone(),
+            units: self.units.clone(),
+        }
+    }
+
+    fn add_model(&mut self, kind: ModelKind, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        self.errors = None;
+        let spec = self.current_spec(kind);
+        let model = build_model(&spec, &mut self.errors);
+
+        if self.model_name.is_empty() {
+            self.errors = Some(format!(
+                "{}Invalid model name field: no model name\n",
+                &self.errors.as_ref().unwrap_or(&"".to_owned())
+            ));
+        }
+
+        if let (None, Some(model)) = (&self.errors, model) {
+            self.push_model(self.model_name.clone(), spec, model, reducer);
+            self.model_name.clear();
+        }
+    }
+
+    fn edit_model(&mut self, name: String) {
+        if let Some(spec) = self.specs.get(&name) {
+            self.start_conditions = spec.start_conditions.clone();
+            self.left_edge_conditions = spec.left_edge_conditions.clone();
+            self.right_edge_conditions = spec.right_edge_conditions.clone();
+            self.coefficient = spec.coefficient.clone();
+            self.actual = spec.actual.clone();
+            self.series_term = spec.series_term.clone();
+            self.term_count = spec.term_count;
+            self.length = spec.length;
+            self.node_count = spec.node_count;
+            self.node_count_max = self.node_count_max.max(spec.node_count);
+            self.time_step = spec.time_step;
+            self.sigma = spec.sigma;
+            self.grading = spec.grading;
+            self.integrator = spec.integrator;
+            self.spatial_order = spec.spatial_order;
+            self.coefficient_stencil = spec.coefficient_stencil;
+            self.implicit_scheme = spec.implicit_scheme;
+            self.ramp_steps = spec.ramp_steps;
+            self.left_boundary = spec.left_boundary;
+            self.right_boundary = spec.right_boundary;
+            self.start_samples = spec.start_samples.clone();
+            self.coefficient_segments = spec.coefficient_segments.clone();
+            self.units = spec.units.clone();
+            self.editing = Some(name);
+        }
+    }
+
+    fn update_model(&mut self, name: String, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let kind = match self.specs.get(&name) {
+            Some(spec) => spec.kind,
+            None => {
+                self.editing = None;
+                return;
+            }
+        };
+
+        self.errors = None;
+        let spec = self.current_spec(kind);
+        // New edges alone don't need the model to start over
+        if self.specs[&name].differs_only_in_edges(&spec) {
+            self.reseed_edges(name, reducer);
+            self.editing = None;
+            return;
+        }
+        if let Some(model) = build_model(&spec, &mut self.errors) {
+            reducer.reduce(UiPost::UpdateModel(name.clone(), model));
+            self.specs.insert(name, spec);
+            self.editing = None;
+        }
+    }
+
+    /// Hands the edge conditions of the edit form to the running model
+    fn reseed_edges(&mut self, name: String, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let spec = match self.specs.get(&name) {
+            Some(spec) => ModelSpec {
+                left_edge_conditions: self.left_edge_conditions.clone(),
+                right_edge_conditions: self.right_edge_conditions.clone(),
+                ..spec.clone()
+            },
+            None => return,
+        };
+        self.errors = None;
+        let (left, right) = build_edges(&spec, &mut self.errors);
+        if self.errors.is_none() {
+            reducer.reduce(UiPost::ReseedEdges(name.clone(), Box::new((left, right))));
+            self.specs.insert(name, spec);
+        }
+    }
+
+    fn push_model(
+        &mut self,
+        name: String,
+        spec: ModelSpec,
+        model: Box<dyn Model>,
+        reducer: &mut dyn Reducer<UiPost, UiGet>,
+    ) {
+        reducer.reduce(UiPost::AddModel(name.clone(), model));
+        self.add_comparison.insert(name.clone(), "".to_owned());
+        self.specs.insert(name, spec);
+    }
+
+    /// Adds an analytic model with the discretization of `name` and starts comparing them
+    fn add_analytic_reference(
+        &mut self,
+        name: String,
+        node_count: u32,
+        time_step: f64,
+        reducer: &mut dyn Reducer<UiPost, UiGet>,
+    ) {
+        let spec = match self.specs.get(&name) {
+            Some(spec) => ModelSpec {
+                kind: ModelKind::Analytic,
+                actual: self.actual.clone(),
+                node_count,
+                time_step,
+                ..spec.clone()
+            },
+            None => return,
+        };
+        let reference_name = format!("{} (analytic)", name);
+
+        self.errors = None;
+        if let Some(model) = build_model(&spec, &mut self.errors) {
+            self.push_model(reference_name.clone(), spec, model, reducer);
+            reducer.reduce(UiPost::StartComparison(name, reference_name.clone()));
+            reducer.reduce(UiPost::SetReference(reference_name, true));
+        }
+    }
+
+    /// Runs `name` at 1, 2 and 4 times its node count against the Analytical field.
+    /// The time step shrinks with h² so the explicit scheme stays stable
+    fn run_convergence_study(&mut self, name: String, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let spec = match self.specs.get(&name) {
+            Some(spec) => spec.clone(),
+            None => return,
+        };
+
+        self.errors = None;
+        let mut runs = vec![];
+        for refinement in [1, 2, 4] {
+            let node_count = spec.node_count * refinement;
+            let ratio = (spec.node_count - 1) as f64 / (node_count - 1) as f64;
+            let numeric = ModelSpec {
+                node_count,
+                time_step: spec.time_step * ratio * ratio,
+                ..spec.clone()
+            };
+            let reference = ModelSpec {
+                kind: ModelKind::Analytic,
+                actual: self.actual.clone(),
+                ..numeric.clone()
+            };
+            match (
+                build_model(&numeric, &mut self.errors),
+                build_model(&reference, &mut self.errors),
+            ) {
+                (Some(n), Some(r)) => runs.push((n, r)),
+                _ => return,
+            }
+        }
+        reducer.reduce(UiPost::RunConvergenceStudy(name, self.study_time, runs));
+    }
+
+    /// Adds a differential model per node count, all compared against one analytic reference
+    fn add_convergence_study(&mut self, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        self.errors = None;
+        let mut node_counts = match self
+            .study_node_counts
+            .split(',')
+            .map(|n| n.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(n) if !n.is_empty() && n.iter().all(|&n| n >= 3) => n,
+            _ => {
+                self.errors = Some(
+                    "Invalid node counts field: expected a list like 25, 50, 100\n".to_owned(),
+                );
+                return;
+            }
+        };
+        node_counts.sort_unstable();
+        node_counts.dedup();
+        if self.model_name.is_empty() {
+            self.errors = Some("Invalid model name field: no model name\n".to_owned());
+            return;
+        }
+
+        // The models are compared while running, so they share the time step
+        // that keeps the finest one stable
+        let spec = self.current_spec(ModelKind::Differential);
+        let finest = *node_counts.last().unwrap();
+        let ratio = (spec.node_count - 1) as f64 / (finest - 1) as f64;
+        let time_step = spec.time_step * (ratio * ratio).min(1.);
+
+        let reference_spec = ModelSpec {
+            kind: ModelKind::Analytic,
+            node_count: finest,
+            time_step,
+            ..spec.clone()
+        };
+        let reference = match build_model(&reference_spec, &mut self.errors) {
+            Some(m) => m,
+            None => return,
+        };
+        let mut models = vec![];
+        for node_count in node_counts {
+            let spec = ModelSpec {
+                node_count,
+                time_step,
+                ..spec.clone()
+            };
+            match build_model(&spec, &mut self.errors) {
+                Some(m) => models.push((spec, m)),
+                None => return,
+            }
+        }
+
+        let reference_name = format!("{} (analytic)", self.model_name);
+        self.push_model(reference_name.clone(), reference_spec, reference, reducer);
+        let mut rows = vec![];
+        for (spec, model) in models {
+            let name = format!("{} N={}", self.model_name, spec.node_count);
+            rows.push((spec.node_count, name.clone()));
+            self.push_model(name.clone(), spec, model, reducer);
+            reducer.reduce(UiPost::StartComparison(name, reference_name.clone()));
+        }
+        self.live_studies.push((reference_name, rows));
+        self.model_name.clear();
+    }
+
+    fn current_session(&self, model_info: &[ModelInfo]) -> Session {
+        let mut session = Session::default();
+        for model in model_info {
+            if let Some(spec) = self.specs.get(&model.name) {
+                session.models.push((model.name.clone(), spec.clone()));
+            }
+            for comp_name in model.comparisons.keys() {
+                if &model.name < comp_name {
+                    session
+                        .comparisons
+                        .push((model.name.clone(), comp_name.clone()));
+                }
+            }
+        }
+        session
+    }
+
+    fn save_session(&mut self, path: &Path, model_info: &[ModelInfo]) {
+        if let Err(e) = self.current_session(model_info).save(path) {
+            self.errors = Some(format!("Could not save session: {}\n", e.cause()));
+        }
+    }
+
+    fn load_session(
+        &mut self,
+        path: &Path,
+        model_info: &[ModelInfo],
+        reducer: &mut dyn Reducer<UiPost, UiGet>,
+    ) {
+        let session = match Session::load(path) {
+            Ok(s) => s,
+            Err(e) => {
+                self.errors = Some(format!("Could not load session: {}\n", e.cause()));
+                return;
+            }
+        };
+
+        for model in model_info {
+            reducer.reduce(UiPost::RemoveModel(model.name.clone()));
+        }
+        self.specs.clear();
+
+        self.errors = None;
+        self.add_session(session, reducer);
+    }
+
+    /// Adds the models of a session before starting its comparisons, so both ends exist
+    fn add_session(&mut self, session: Session, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        for (name, spec) in session.models {
+            let mut errors = None;
+            match build_model(&spec, &mut errors) {
+                Some(model) if errors.is_none() => self.push_model(name, spec, model, reducer),
+                _ => {
+                    self.errors = Some(format!(
+                        "{}Invalid model {} in session:\n{}",
+                        self.errors.as_ref().unwrap_or(&"".to_owned()),
+                        name,
+                        errors.unwrap_or_default()
+                    ))
+                }
+            }
+        }
+        for (n1, n2) in session.comparisons {
+            reducer.reduce(UiPost::StartComparison(n1, n2));
         }
     }
 
     pub fn draw(&mut self, ctx: &egui::CtxRef, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let mut errors = UiGet::Errors(None);
+        reducer.request(&mut errors);
+        if let UiGet::Errors(Some(errors)) = errors {
+            for e in errors {
+                self.errors = Some(format!(
+                    "{}{}\n",
+                    self.errors.as_ref().unwrap_or(&"".to_owned()),
+                    e
+                ));
+            }
+        }
+
         egui::Window::new("Model Creator").show(ctx, |ui| self.draw_model_creator(ui, reducer));
         egui::Window::new("Current Models").show(ctx, |ui| self.draw_model_list(ui, reducer));
         egui::Window::new("Info").show(ctx, |ui| self.draw_info(ui, reducer));
+        egui::Window::new("Legend").show(ctx, |ui| self.draw_legend(ui, reducer));
+
+        let mut studies = UiGet::ConvergenceStudies(None);
+        reducer.request(&mut studies);
+        if let UiGet::ConvergenceStudies(Some(studies)) = studies {
+            for study in studies {
+                self.convergence_studies.retain(|s| s.name != study.name);
+                self.convergence_studies.push(study);
+            }
+        }
+        if !self.convergence_studies.is_empty() || !self.live_studies.is_empty() {
+            egui::Window::new("Convergence").show(ctx, |ui| self.draw_convergence(ui, reducer));
+        }
+        if self.show_spectrum {
+            let mut open = true;
+            egui::Window::new("Spectrum")
+                .open(&mut open)
+                .show(ctx, |ui| self.draw_spectrum(ui, reducer));
+            if !open {
+                self.show_spectrum = false;
+                reducer.reduce(UiPost::SetShowSpectrum(false));
+            }
+        }
+        if self.show_scalars {
+            let mut open = true;
+            egui::Window::new("Over time")
+                .open(&mut open)
+                .show(ctx, |ui| self.draw_scalars(ui, reducer));
+            if !open {
+                self.show_scalars = false;
+                reducer.reduce(UiPost::SetShowScalars(false));
+            }
+        }
+    }
+
+    /// Line color of each model in the plots, the one picked in the model list if any
+    fn line_colors(
+        model_info: &[ModelInfo],
+        reducer: &mut dyn Reducer<UiPost, UiGet>,
+    ) -> HashMap<String, egui::Rgba> {
+        let picked = model_colors(reducer);
+        model_info
+            .iter()
+            .map(|m| {
+                let [r, g, b] = picked_color(&picked, m);
+                (m.name.clone(), egui::Rgba::from_rgb(r, g, b))
+            })
+            .collect()
+    }
+
+    fn draw_scalars(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let mut m = UiGet::ModelInfo(None);
+        reducer.request(&mut m);
+        let model_info = match m {
+            UiGet::ModelInfo(m) => m.unwrap(),
+            _ => panic!("Expected a vec of model info"),
+        };
+
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.plotted_scalar,
+                PlottedScalar::TotalHeat,
+                "Total heat",
+            )
+            .on_hover_text("Integral of the temperature over the rod");
+            ui.radio_value(
+                &mut self.plotted_scalar,
+                PlottedScalar::MaxTemperature,
+                "Max temperature",
+            );
+            ui.radio_value(&mut self.plotted_scalar, PlottedScalar::NetFlux, "Net flux")
+                .on_hover_text("Heat flowing in through both edges, numeric models only");
+            ui.checkbox(&mut self.scalar_log, "log10")
+                .on_hover_text("Exponential decay shows up as a straight line");
+        });
+        let (scalar, log) = (self.plotted_scalar, self.scalar_log);
+        let colors = Self::line_colors(&model_info, reducer);
+        egui::plot::Plot::new("scalars")
+            .view_aspect(2.)
+            .legend(egui::plot::Legend::default())
+            .show(ui, |plot| {
+                for m in model_info.iter() {
+                    let values = m
+                        .scalar_history
+                        .iter()
+                        .map(|s| (s.time, scalar.of(s)))
+                        .filter(|(_, y)| y.is_finite())
+                        .map(|(t, y)| {
+                            let y = if log {
+                                y.abs().max(f64::MIN_POSITIVE).log10()
+                            } else {
+                                y
+                            };
+                            egui::plot::Value::new(t, y)
+                        });
+                    plot.line(
+                        egui::plot::Line::new(egui::plot::Values::from_values_iter(values))
+                            .color(colors[&m.name])
+                            .name(&m.name),
+                    );
+                }
+            });
+    }
+
+    fn draw_spectrum(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let mut m = UiGet::ModelInfo(None);
+        reducer.request(&mut m);
+        let model_info = match m {
+            UiGet::ModelInfo(m) => m.unwrap(),
+            _ => panic!("Expected a vec of model info"),
+        };
+
+        ui.checkbox(&mut self.spectrum_log, "log10")
+            .on_hover_text("Decaying high frequencies are physical, growing ones an instability");
+        let log = self.spectrum_log;
+        let colors = Self::line_colors(&model_info, reducer);
+        egui::plot::Plot::new("spectrum")
+            .view_aspect(2.)
+            .legend(egui::plot::Legend::default())
+            .show(ui, |plot| {
+                for m in model_info.iter() {
+                    let spectrum = match &m.spectrum {
+                        Some(spectrum) => spectrum,
+                        None => continue,
+                    };
+                    let values = spectrum.iter().enumerate().map(|(k, &magnitude)| {
+                        let y = if log {
+                            magnitude.max(f64::MIN_POSITIVE).log10()
+                        } else {
+                            magnitude
+                        };
+                        egui::plot::Value::new(k as f64, y)
+                    });
+                    plot.line(
+                        egui::plot::Line::new(egui::plot::Values::from_values_iter(values))
+                            .color(colors[&m.name])
+                            .name(&m.name),
+                    );
+                }
+            });
     }
 
     fn draw_model_creator(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
@@ -110,174 +821,442 @@ impl Controls {
         });
         ui.horizontal(|ui| {
             ui.label("Starting Conditions: ");
-            ui.text_edit_singleline(&mut self.start_conditions);
+            match &self.start_samples {
+                Some(samples) => {
+                    ui.label(format!("{} samples from CSV", samples.len()));
+                    if ui.button("Clear").clicked() {
+                        self.start_samples = None;
+                    }
+                }
+                None => {
+                    ui.text_edit_singleline(&mut self.start_conditions);
+                }
+            }
+            if ui
+                .button("Load CSV")
+                .on_hover_text("x,temperature lines, interpolated between the samples")
+                .clicked()
+            {
+                let path = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .show_open_single_file();
+                match path {
+                    Ok(Some(path)) => match read_samples(&path) {
+                        Ok(samples) => self.start_samples = Some(samples),
+                        Err(e) => self.errors = Some(format!("Could not load CSV: {}\n", e)),
+                    },
+                    Ok(None) => (),
+                    Err(e) => self.errors = Some(format!("Could not open file dialog: {}\n", e)),
+                }
+            }
         });
         ui.horizontal(|ui| {
             ui.label("Left Edge: ");
             ui.text_edit_singleline(&mut self.left_edge_conditions);
+            ui.selectable_value(
+                &mut self.left_boundary,
+                BoundaryKind::Dirichlet,
+                "Dirichlet",
+            );
+            ui.selectable_value(&mut self.left_boundary, BoundaryKind::Periodic, "Periodic");
+            ui.selectable_value(&mut self.left_boundary, BoundaryKind::Neumann, "Neumann")
+                .on_hover_text("The edge condition is the heat flowing in, 0 insulates the edge");
         });
         ui.horizontal(|ui| {
             ui.label("Right Edge: ");
-            ui.text_edit_singleline(&mut self.right_edge_conditions)
+            ui.text_edit_singleline(&mut self.right_edge_conditions);
+            ui.selectable_value(
+                &mut self.right_boundary,
+                BoundaryKind::Dirichlet,
+                "Dirichlet",
+            );
+            ui.selectable_value(&mut self.right_boundary, BoundaryKind::Periodic, "Periodic");
+            ui.selectable_value(&mut self.right_boundary, BoundaryKind::Neumann, "Neumann")
+                .on_hover_text("The edge condition is the heat flowing in, 0 insulates the edge");
         });
         ui.horizontal(|ui| {
             ui.label("Coefficient: ");
-            ui.text_edit_singleline(&mut self.coefficient);
+            ui.text_edit_singleline(&mut self.coefficient)
+                .on_hover_text("a(x), or a(x, t) for a material that changes over time");
         });
+        ui.horizontal(|ui| {
+            ui.label("Materials: ");
+            if ui
+                .button("Add")
+                .on_hover_text(
+                    "Each material's coefficient holds up to its x, the last one to the end. \
+                     Replaces the coefficient when set, Differential Model only",
+                )
+                .clicked()
+            {
+                let start = self.coefficient_segments.last().map_or(0., |s| s.0);
+                let end = (start + self.length) / 2.;
+                let end = if end > start { end } else { self.length };
+                self.coefficient_segments
+                    .push((end, self.coefficient.clone()));
+            }
+        });
+        let mut removed = None;
+        for (i, (x, a)) in self.coefficient_segments.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(x).prefix("up to x = "));
+                ui.label("a: ");
+                ui.text_edit_singleline(a);
+                if ui.button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            self.coefficient_segments.remove(i);
+        }
         ui.horizontal(|ui| {
             ui.label("Analytical: ");
             ui.text_edit_singleline(&mut self.actual);
         });
+        ui.horizontal(|ui| {
+            ui.label("Series term: ");
+            ui.text_edit_singleline(&mut self.series_term)
+                .on_hover_text("Term of n, t and x, summed over n = 1 to the term count");
+            ui.add(
+                egui::DragValue::new(&mut self.term_count)
+                    .clamp_range(1..=10_000)
+                    .prefix("terms: "),
+            );
+        });
 
-        ui.add(egui::Slider::new(&mut self.node_count, 3..=300).text("Node Count"));
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(&mut self.node_count, 3..=self.node_count_max).text("Node Count"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.node_count_max)
+                    .clamp_range(3..=100_000)
+                    .prefix("max: "),
+            );
+        });
         ui.add(egui::Slider::new(&mut self.time_step, 0.01..=10.).text("Time Step"));
+        ui.horizontal(|ui| {
+            ui.label("Units: ");
+            for (unit, hint) in [
+                (&mut self.units.length, "length"),
+                (&mut self.units.temperature, "temperature"),
+                (&mut self.units.time, "time"),
+                (&mut self.units.diffusivity, "a²"),
+            ] {
+                ui.add(
+                    egui::TextEdit::singleline(unit)
+                        .hint_text(hint)
+                        .desired_width(60.),
+                );
+            }
+        })
+        .response
+        .on_hover_text("Only used for labels and exported CSV headers, e.g. m, °C and m²/s");
         ui.add(egui::Slider::new(&mut self.length, 1.0..=400.).text("Length"));
         ui.add(egui::Slider::new(&mut self.sigma, 0.0..=1.0).text("Sigma"));
-
-        if ui.button("Add Differential Model").clicked() {
-            self.errors = None;
-            let sc = make_expr(
-                &self.start_conditions[..],
-                "Invalid start conditions field",
-                1,
-                &mut self.errors,
+        ui.add(egui::Slider::new(&mut self.grading, 1.0..=1.1).text("Grid grading"))
+            .on_hover_text("Clusters the nodes of Differential Models toward the left edge");
+        egui::ComboBox::from_label("Time integrator")
+            .selected_text(format!("{:?}", self.integrator))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut self.integrator,
+                    TimeIntegrator::ForwardEuler,
+                    "ForwardEuler",
+                );
+                ui.selectable_value(&mut self.integrator, TimeIntegrator::RK4, "RK4");
+            })
+            .response
+            .on_hover_text("Only used by Differential Models");
+        egui::ComboBox::from_label("Spatial order")
+            .selected_text(format!("{:?}", self.spatial_order))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.spatial_order, SpatialOrder::Second, "Second");
+                ui.selectable_value(&mut self.spatial_order, SpatialOrder::Fourth, "Fourth");
+            })
+            .response
+            .on_hover_text(
+                "Only used by Differential Models, Fourth falls back to Second \
+                 on graded grids and piecewise coefficients",
             );
-            let lc = make_expr(
-                &self.left_edge_conditions[..],
-                "Invalid left edge conditions",
-                1,
-                &mut self.errors,
+        egui::ComboBox::from_label("Coefficient stencil")
+            .selected_text(format!("{:?}", self.coefficient_stencil))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut self.coefficient_stencil,
+                    CoefficientStencil::Faces,
+                    "Faces",
+                );
+                ui.selectable_value(
+                    &mut self.coefficient_stencil,
+                    CoefficientStencil::Nodes,
+                    "Nodes",
+                );
+            })
+            .response
+            .on_hover_text(
+                "Used by System Models and by Differential Models with a Second order stencil, Faces takes a \
+                 halfway between the nodes so heat is conserved where a varies, \
+                 Nodes takes it at the node like older versions did",
             );
-            let rc = make_expr(
-                &self.right_edge_conditions[..],
-                "Invalid right edge coditions",
-                1,
-                &mut self.errors,
+        egui::ComboBox::from_label("Implicit scheme")
+            .selected_text(format!("{:?}", self.implicit_scheme))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.implicit_scheme, ImplicitScheme::Theta, "Theta");
+                ui.selectable_value(&mut self.implicit_scheme, ImplicitScheme::BDF2, "BDF2");
+            })
+            .response
+            .on_hover_text(
+                "Only used by System Models, Theta takes Sigma of the second difference \
+                 at the new step (0.5 is Crank–Nicolson), BDF2 doesn't ring at large time \
+                 steps and ignores Sigma",
             );
-            let c = make_expr(
-                &self.coefficient[..],
-                "Invalid coefficient field",
-                1,
-                &mut self.errors,
+        ui.add(egui::Slider::new(&mut self.ramp_steps, 0..=100).text("Edge ramp steps"))
+            .on_hover_text(
+                "Only used by Differential Models with Dirichlet edges, blends the edges from \
+                 the start conditions into the edge conditions over this many steps so a \
+                 mismatched start doesn't make the first steps overshoot, 0 is off",
             );
+        self.draw_stability_factor(ui);
 
-            if self.model_name.len() == 0 {
-                self.errors = Some(format!(
-                    "{}Invalid model name field: no model name\n",
-                    &self.errors.as_ref().unwrap_or(&"".to_owned())
-                ));
+        if let Some(name) = self.editing.clone() {
+            ui.label(format!("Editing {}", name));
+            ui.horizontal(|ui| {
+                if ui.button("Apply changes").clicked() {
+                    self.update_model(name, reducer);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.editing = None;
+                }
+            });
+        } else {
+            if ui.button("Add Differential Model").clicked() {
+                self.add_model(ModelKind::Differential, reducer);
             }
 
-            if self.errors.is_none() {
-                let model = Box::new(DifferentialModel::new(
-                    sc,
-                    lc,
-                    rc,
-                    c,
-                    self.length,
-                    self.node_count,
-                    self.time_step,
-                ));
-                reducer.reduce(UiPost::AddModel(self.model_name.clone(), model));
-                self.add_comparison
-                    .insert(self.model_name.clone(), "".to_owned());
-                self.model_name.clear();
-                self.errors = None;
+            if ui.button("Add Analytic").clicked() {
+                self.add_model(ModelKind::Analytic, reducer);
             }
-        }
 
-        if ui.button("Add Analytic").clicked() {
-            self.errors = None;
+            if ui.button("Add System").clicked() {
+                self.add_model(ModelKind::System, reducer);
+            }
 
-            let f = make_expr(
-                &self.actual[..],
-                "Invalid actual field",
-                2,
-                &mut self.errors,
-            );
-            if self.model_name.len() == 0 {
-                self.errors = Some(format!(
-                    "{}Invalid model name field: no model name\n",
-                    &self.errors.as_ref().unwrap_or(&"".to_owned())
-                ));
+            if ui.button("Add Analytic Series").clicked() {
+                self.add_model(ModelKind::AnalyticSeries, reducer);
             }
 
-            if self.errors.is_none() {
-                let m = Box::new(AnalyticModel::new(
-                    f,
-                    self.length,
-                    self.node_count,
-                    self.time_step,
-                ));
-                reducer.reduce(UiPost::AddModel(self.model_name.clone(), m));
-                self.add_comparison
-                    .insert(self.model_name.clone(), "".to_owned());
-                self.model_name.clear();
-                self.errors = None;
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Node counts: ");
+                ui.text_edit_singleline(&mut self.study_node_counts);
+            });
+            if ui
+                .button("Convergence study")
+                .on_hover_text("Add a Differential Model per node count, compared against the Analytical field")
+                .clicked()
+            {
+                self.add_convergence_study(reducer);
             }
         }
 
-        if ui.button("Add System").clicked() {
-            self.errors = None;
-            let sc = make_expr(
-                &self.start_conditions[..],
-                "Invalid start conditions field",
-                1,
-                &mut self.errors,
-            );
-            let lc = make_expr(
-                &self.left_edge_conditions[..],
-                "Invalid left edge conditions",
-                1,
-                &mut self.errors,
-            );
-            let rc = make_expr(
-                &self.right_edge_conditions[..],
-                "Invalid right edge coditions",
-                1,
-                &mut self.errors,
+        if let Some(e) = &self.errors {
+            ui.label(e);
+        }
+    }
+
+    fn draw_legend(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        const TICKS: usize = 4;
+        let text_color = ui.visuals().text_color();
+
+        let mut m = UiGet::ModelInfo(None);
+        reducer.request(&mut m);
+        let model_info = match m {
+            UiGet::ModelInfo(m) => m.unwrap(),
+            _ => panic!("Expected a vec of model info"),
+        };
+
+        // The color bar is shared, so it only names a unit all the models agree on
+        let unit = match model_info.first() {
+            Some(first)
+                if model_info
+                    .iter()
+                    .all(|m| m.units.temperature == first.units.temperature) =>
+            {
+                Units::bracketed(&first.units.temperature)
+            }
+            _ => String::new(),
+        };
+        ui.label(format!("Temperature{}", unit));
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(80., 160.), egui::Sense::hover());
+        let bar = egui::Rect::from_min_size(rect.min, egui::vec2(20., rect.height()));
+        let mut mesh = egui::epaint::Mesh::default();
+        let segments = 32;
+        for i in 0..=segments {
+            let t = i as f32 / segments as f32;
+            let y = bar.bottom() - t * bar.height();
+            mesh.colored_vertex(egui::pos2(bar.left(), y), temperature_color(t));
+            mesh.colored_vertex(egui::pos2(bar.right(), y), temperature_color(t));
+            if i > 0 {
+                let i = 2 * i as u32;
+                mesh.add_triangle(i - 2, i - 1, i);
+                mesh.add_triangle(i - 1, i, i + 1);
+            }
+        }
+        ui.painter().add(egui::Shape::mesh(mesh));
+        for i in 0..=TICKS {
+            let t = i as f32 / TICKS as f32;
+            let y = bar.bottom() - t * bar.height();
+            let value = self
+                .color_scale
+                .value_at(t, (self.color_min, self.color_max));
+            ui.painter().line_segment(
+                [egui::pos2(bar.right(), y), egui::pos2(bar.right() + 4., y)],
+                (1., text_color),
             );
-            let c = make_expr(
-                &self.coefficient[..],
-                "Invalid coefficient field",
-                1,
-                &mut self.errors,
+            ui.painter().text(
+                egui::pos2(bar.right() + 6., y),
+                egui::Align2::LEFT_CENTER,
+                format!("{:.1}", value),
+                egui::TextStyle::Small,
+                text_color,
             );
+        }
 
-            if self.model_name.len() == 0 {
-                self.errors = Some(format!(
-                    "{}Invalid model name field: no model name\n",
-                    &self.errors.as_ref().unwrap_or(&"".to_owned())
-                ));
+        if ui
+            .button("Auto-scale")
+            .on_hover_text("Fit the color range to the current temperatures")
+            .clicked()
+        {
+            let (min, max) = model_info
+                .iter()
+                .map(|m| m.value_range)
+                .filter(|(min, max)| min.is_finite() && max.is_finite())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |a, b| {
+                    (a.0.min(b.0), a.1.max(b.1))
+                });
+            if min <= max {
+                self.color_min = min as f32;
+                self.color_max = (max as f32).max(self.color_min + 1.);
+                reducer.reduce(UiPost::SetColorRange(self.color_min, self.color_max));
             }
+        }
 
-            if self.errors.is_none() {
-                let model = Box::new(SystemModel::new(
-                    sc,
-                    lc,
-                    rc,
-                    c,
-                    self.sigma,
-                    self.length,
-                    self.node_count,
-                    self.time_step,
-                ));
-                reducer.reduce(UiPost::AddModel(self.model_name.clone(), model));
-                self.add_comparison
-                    .insert(self.model_name.clone(), "".to_owned());
-                self.model_name.clear();
-                self.errors = None;
+        for model in model_info.iter() {
+            ui.separator();
+            ui.label(format!(
+                "{}: position{}",
+                model.name,
+                Units::bracketed(&model.units.length)
+            ));
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(200., 24.), egui::Sense::hover());
+            let axis_y = rect.top() + 4.;
+            let left = rect.left() + 10.;
+            let right = rect.right() - 10.;
+            ui.painter().line_segment(
+                [egui::pos2(left, axis_y), egui::pos2(right, axis_y)],
+                (1., text_color),
+            );
+            for i in 0..=TICKS {
+                let t = i as f32 / TICKS as f32;
+                let x = left + t * (right - left);
+                ui.painter().line_segment(
+                    [egui::pos2(x, axis_y - 3.), egui::pos2(x, axis_y + 3.)],
+                    (1., text_color),
+                );
+                ui.painter().text(
+                    egui::pos2(x, axis_y + 4.),
+                    egui::Align2::CENTER_TOP,
+                    format!("{:.0}", t as f64 * model.length),
+                    egui::TextStyle::Small,
+                    text_color,
+                );
             }
         }
+    }
 
-        if let Some(e) = &self.errors {
-            ui.label(e);
+    fn draw_convergence(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let mut m = UiGet::ModelInfo(None);
+        reducer.request(&mut m);
+        let model_info = match m {
+            UiGet::ModelInfo(m) => m.unwrap(),
+            _ => panic!("Expected a vec of model info"),
+        };
+
+        self.live_studies
+            .retain(|(reference, _)| model_info.iter().any(|m| &m.name == reference));
+        for (reference, rows) in &self.live_studies {
+            ui.label(format!("Against {}", reference));
+            egui::Grid::new(format!("live convergence {}", reference))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Nodes");
+                    ui.label("RMS error");
+                    ui.label("Order");
+                    ui.end_row();
+                    // The error scales with the spacing, which doesn't halve when N doubles
+                    let mut prev: Option<(f64, f64)> = None;
+                    for (node_count, name) in rows {
+                        let model = model_info.iter().find(|m| &m.name == name);
+                        let (spacing, difference) = match model
+                            .and_then(|m| m.comparisons.get(reference).map(|d| (m, d)))
+                        {
+                            Some((m, d)) => (
+                                m.length / (m.node_count - 1) as f64,
+                                d.current / (*node_count as f64).sqrt(),
+                            ),
+                            None => continue,
+                        };
+                        ui.label(node_count.to_string());
+                        ui.label(format!("{:.3e}", difference));
+                        ui.label(prev.map_or("-".to_owned(), |(h, e)| {
+                            format!("{:.2}", (e / difference).ln() / (h / spacing).ln())
+                        }));
+                        ui.end_row();
+                        prev = Some((spacing, difference));
+                    }
+                });
+            ui.separator();
+        }
+
+        let mut removed = None;
+        for (i, study) in self.convergence_studies.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} at t = {}", study.name, study.time));
+                if ui.button("🗑").clicked() {
+                    removed = Some(i);
+                }
+            });
+            egui::Grid::new(format!("convergence {}", study.name))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Nodes");
+                    ui.label("Relative L2 error");
+                    ui.label("Order");
+                    ui.end_row();
+                    for row in &study.rows {
+                        ui.label(row.node_count.to_string());
+                        ui.label(format!("{:.3e}", row.error));
+                        ui.label(row.order.map_or("-".to_owned(), |o| format!("{:.2}", o)));
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+        }
+
+        if let Some(i) = removed {
+            self.convergence_studies.remove(i);
         }
     }
 
     fn draw_model_list(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
         let mut removed_models = vec![];
         let mut removed_comparisons = vec![];
+        let mut analytic_references = vec![];
+        let mut convergence_studies = vec![];
+        let mut edited_model = None;
+        let mut duplicated_models = vec![];
 
         let mut m = UiGet::ModelInfo(None);
         reducer.request(&mut m);
@@ -286,17 +1265,215 @@ impl Controls {
             _ => panic!("Expected a vec of model info"),
         };
 
+        ui.add(
+            egui::DragValue::new(&mut self.study_time)
+                .clamp_range(0.0..=f64::MAX)
+                .prefix("Convergence study time: "),
+        );
+        ui.separator();
+
+        let picked_colors = model_colors(reducer);
         for model in model_info.iter() {
             let name = &model.name;
 
             ui.horizontal(|ui| {
+                let mut color = picked_color(&picked_colors, model);
+                if ui
+                    .color_edit_button_rgb(&mut color)
+                    .on_hover_text("Color of the model's heatmap tint and line")
+                    .changed()
+                {
+                    reducer.reduce(UiPost::SetModelColor(name.clone(), color));
+                }
                 ui.label(name);
                 if ui.button("↺").clicked() {
                     reducer.reduce(UiPost::RestartModel(name.clone()));
                 }
+                if self.editing.as_ref() == Some(name)
+                    && ui
+                        .small_button("↺ edges")
+                        .on_hover_text(
+                            "Apply the edited edge conditions at the current time, \
+                             keeping the interior and the elapsed time",
+                        )
+                        .clicked()
+                {
+                    self.reseed_edges(name.clone(), reducer);
+                }
                 if ui.button("🗑").clicked() {
                     removed_models.push(name.clone());
                 }
+                if self.specs.contains_key(name) && ui.button("Edit").clicked() {
+                    edited_model = Some(name.clone());
+                }
+                if ui
+                    .button("Duplicate")
+                    .on_hover_text("Adds a copy that starts over from t = 0")
+                    .clicked()
+                {
+                    duplicated_models.push(name.clone());
+                }
+                if ui
+                    .button("Pin as reference")
+                    .on_hover_text(
+                        "Keep the current temperatures as a static curve to compare against",
+                    )
+                    .clicked()
+                {
+                    reducer.reduce(UiPost::PinModel(name.clone()));
+                }
+                let is_numeric = self.specs.get(name).is_some_and(|s| {
+                    !matches!(s.kind, ModelKind::Analytic | ModelKind::AnalyticSeries)
+                });
+                let mut reference = model.reference;
+                if self.specs.contains_key(name)
+                    && !is_numeric
+                    && ui
+                        .checkbox(&mut reference, "Reference only")
+                        .on_hover_text(
+                            "Stop stepping the model, comparisons evaluate it at the time and \
+                             nodes of the other model instead",
+                        )
+                        .changed()
+                {
+                    reducer.reduce(UiPost::SetReference(name.clone(), reference));
+                }
+                if is_numeric {
+                    ui.menu_button("Copy state from", |ui| {
+                        for other in model_info.iter().filter(|m| &m.name != name) {
+                            if ui.button(&other.name).clicked() {
+                                reducer.reduce(UiPost::CopyStateFrom(
+                                    name.clone(),
+                                    other.name.clone(),
+                                ));
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Take the current temperatures of another model, \
+                         interpolated onto this grid, and go on from there",
+                    );
+                }
+                if is_numeric
+                    && ui
+                        .button("Add analytic reference")
+                        .on_hover_text("Compare against the Analytical field of the model creator")
+                        .clicked()
+                {
+                    analytic_references.push((name.clone(), model.node_count, model.time_step));
+                }
+                if is_numeric
+                    && ui
+                        .button("Convergence study")
+                        .on_hover_text("Run at N, 2N and 4N nodes against the Analytical field")
+                        .clicked()
+                {
+                    convergence_studies.push(name.clone());
+                }
+                if ui
+                    .button("Export CSV")
+                    .on_hover_text("Save the current temperatures, loadable as starting conditions")
+                    .clicked()
+                {
+                    let path = FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .show_save_single_file();
+                    match path {
+                        Ok(Some(path)) => {
+                            if let Err(e) =
+                                write_samples(&path, &model.positions, &model.nodes, &model.units)
+                            {
+                                self.errors =
+                                    Some(format!("Could not export {}: {}\n", name, e.cause()));
+                            }
+                        }
+                        Ok(None) => (),
+                        Err(e) => {
+                            self.errors = Some(format!("Could not open file dialog: {}\n", e))
+                        }
+                    }
+                }
+            });
+            if model.diverged || model.failed {
+                ui.horizontal(|ui| {
+                    let reason = if model.diverged { "Diverged" } else { "Failed" };
+                    ui.colored_label(egui::Color32::RED, format!("{}, paused", reason));
+                    if ui.button("Restart").clicked() {
+                        reducer.reduce(UiPost::RestartModel(name.clone()));
+                    }
+                });
+            } else {
+                let (min, max) = model.value_range;
+                if !min.is_finite() || !max.is_finite() {
+                    ui.colored_label(egui::Color32::RED, "Temperatures are no longer finite");
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "t = {:.2}, step {}",
+                    model.elapsed_time, model.steps
+                ));
+                ui.label(format!("max Δ per step {:.2e}", model.max_step_delta));
+                if model.converged {
+                    ui.colored_label(egui::Color32::GREEN, "converged");
+                }
+                if let Some(target) = model.seek_target {
+                    ui.label(format!("replaying to t = {:.2}", target));
+                }
+            });
+            let (min, max, mean) = model.stats;
+            let degrees = Units::suffix(&model.units.temperature);
+            ui.label(format!(
+                "min {:.2}{} / max {:.2}{} / mean {:.2}{}, hottest so far {:.2}{}",
+                min, degrees, max, degrees, mean, degrees, model.all_time_max, degrees
+            ));
+            if let Some((left, right)) = model.boundary_flux {
+                ui.label(format!("Heat in: left {:.3} / right {:.3}", left, right))
+                    .on_hover_text("a²·du/dx through each edge, positive when heat flows in");
+            }
+            if !model.units.diffusivity.is_empty() {
+                ui.label(format!("a² in {}", model.units.diffusivity));
+            }
+            egui::CollapsingHeader::new("Details")
+                .id_source(format!("details {}", name))
+                .show(ui, |ui| {
+                    egui::Grid::new(format!("details grid {}", name)).show(ui, |ui| {
+                        for (label, value) in &model.details {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                        let length = Units::suffix(&model.units.length);
+                        ui.label("Length");
+                        ui.label(format!("{}{}", model.length, length));
+                        ui.end_row();
+                        ui.label("Nodes");
+                        ui.label(model.node_count.to_string());
+                        ui.end_row();
+                        ui.label("Time step");
+                        ui.label(model.time_step.to_string());
+                        ui.end_row();
+                    });
+                });
+            ui.horizontal(|ui| {
+                let t = self.jump_time.entry(name.clone()).or_insert(0.);
+                ui.add(
+                    egui::DragValue::new(t)
+                        .clamp_range(0.0..=f64::MAX)
+                        .prefix("Jump to t="),
+                );
+                if ui
+                    .button("Jump")
+                    .on_hover_text(
+                        "Analytic models jump instantly, numeric ones re-run from t = 0 \
+                         over as many ticks as it takes",
+                    )
+                    .clicked()
+                {
+                    reducer.reduce(UiPost::JumpToTime(name.clone(), *t));
+                }
             });
             ui.horizontal(|ui| {
                 let n2 = self.add_comparison.get_mut(name).unwrap();
@@ -307,15 +1484,62 @@ impl Controls {
                 }
             });
 
+            for (i, pin) in model.pins.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Pinned at t = {:.2}: difference {:.4}",
+                        pin.time, pin.difference
+                    ))
+                    .on_hover_text("L2 difference of the live model to the snapshot");
+                    if ui.button("🗑").clicked() {
+                        reducer.reduce(UiPost::UnpinModel(name.clone(), i));
+                    }
+                });
+            }
+
             for (comp_name, difference) in &model.comparisons {
                 ui.horizontal(|ui| {
-                    ui.label(format!("Difference with {}: {:.4}", comp_name, difference));
+                    ui.label(format!(
+                        "Difference with {}: {:.4} / {:.4}, max {:.4}, heat {:.4}, \
+                         avg RMS over run {:.4e}",
+                        comp_name,
+                        difference.current,
+                        difference.peak,
+                        difference.max,
+                        difference.heat,
+                        difference.average_rms
+                    ))
+                    .on_hover_text(
+                        "L2 now / peak L2 since the comparison started, the largest pointwise \
+                         difference and the difference of the total heat now, \
+                         and the per-node RMS difference averaged over every step since then",
+                    );
                     if ui.button("↺").clicked() {
                         reducer.reduce(UiPost::StartComparison(name.clone(), comp_name.clone()));
                     }
                     if ui.button("🗑").clicked() {
                         removed_comparisons.push((name.clone(), comp_name.clone()));
                     }
+                    if ui
+                        .button("Export")
+                        .on_hover_text("Save the difference after every step as time,difference")
+                        .clicked()
+                    {
+                        let path = FileDialog::new()
+                            .add_filter("CSV", &["csv"])
+                            .show_save_single_file();
+                        match path {
+                            Ok(Some(path)) => reducer.reduce(UiPost::ExportComparison(
+                                name.clone(),
+                                comp_name.clone(),
+                                path,
+                            )),
+                            Ok(None) => (),
+                            Err(e) => {
+                                self.errors = Some(format!("Could not open file dialog: {}\n", e))
+                            }
+                        }
+                    }
                 });
             }
             ui.separator();
@@ -325,6 +1549,33 @@ impl Controls {
             reducer.reduce(UiPost::RemoveModel(model_name.clone()));
         }
 
+        if let Some(model_name) = edited_model {
+            self.edit_model(model_name);
+        }
+
+        for model_name in duplicated_models {
+            let taken = |copy: &String| model_info.iter().any(|m| &m.name == copy);
+            let mut copy = format!("{} (copy)", model_name);
+            let mut n = 2;
+            while taken(&copy) {
+                copy = format!("{} (copy {})", model_name, n);
+                n += 1;
+            }
+            if let Some(spec) = self.specs.get(&model_name).cloned() {
+                self.specs.insert(copy.clone(), spec);
+            }
+            self.add_comparison.insert(copy.clone(), "".to_owned());
+            reducer.reduce(UiPost::DuplicateModel(model_name, copy));
+        }
+
+        for (model_name, node_count, time_step) in analytic_references {
+            self.add_analytic_reference(model_name, node_count as u32, time_step, reducer);
+        }
+
+        for model_name in convergence_studies {
+            self.run_convergence_study(model_name, reducer);
+        }
+
         for (n1, n2) in &removed_comparisons {
             reducer.reduce(UiPost::StopComparison(n1.clone(), n2.clone()));
         }
@@ -342,6 +1593,166 @@ impl Controls {
                 self.min_tick_time,
             )));
         }
+        if ui
+            .add(
+                egui::Slider::new(&mut self.speed_multiplier, 0.01..=100.)
+                    .logarithmic(true)
+                    .text("Speed"),
+            )
+            .changed()
+        {
+            reducer.reduce(UiPost::SetSpeedMultiplier(self.speed_multiplier));
+        }
+        ui.horizontal(|ui| {
+            let fixed = ui
+                .checkbox(&mut self.fixed_time_rate, "Fixed time rate")
+                .on_hover_text(
+                    "Cover the same simulated time every second however fast the machine is, \
+                     taking as many steps per tick as that needs",
+                )
+                .changed();
+            let rate = ui
+                .add_enabled(
+                    self.fixed_time_rate,
+                    egui::DragValue::new(&mut self.time_rate)
+                        .speed(0.01)
+                        .clamp_range(1e-6..=1e6)
+                        .suffix(" s per s"),
+                )
+                .changed();
+            if fixed || rate {
+                reducer.reduce(UiPost::SetTimeRate(
+                    self.fixed_time_rate.then_some(self.time_rate),
+                ));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let heatmap = ui.radio_value(&mut self.render_mode, RenderMode::Heatmap, "Heatmap");
+            let graph = ui.radio_value(&mut self.render_mode, RenderMode::LineGraph, "Line graph");
+            let error_map = ui
+                .radio_value(&mut self.render_mode, RenderMode::ErrorMap, "Error map")
+                .on_hover_text(
+                    "|u1 - u2| of every comparison over x and time, recorded while this is shown",
+                );
+            if heatmap.changed() || graph.changed() || error_map.changed() {
+                reducer.reduce(UiPost::SetRenderMode(self.render_mode));
+            }
+        });
+        let height_changed = ui
+            .add(egui::Slider::new(&mut self.strip_layout.height, 5.0..=100.).text("Strip height"))
+            .changed();
+        let spacing_changed = ui
+            .add(
+                egui::Slider::new(&mut self.strip_layout.spacing, 5.0..=120.).text("Strip spacing"),
+            )
+            .on_hover_text("From one heatmap strip to the next, at least the strip height")
+            .changed();
+        if height_changed || spacing_changed {
+            if self.strip_layout.spacing < self.strip_layout.height {
+                self.strip_layout.spacing = self.strip_layout.height;
+            }
+            reducer.reduce(UiPost::SetStripLayout(self.strip_layout));
+        }
+
+        let color_min_changed = ui
+            .add(egui::DragValue::new(&mut self.color_min).prefix("Color min: "))
+            .changed();
+        let color_max_changed = ui
+            .add(egui::DragValue::new(&mut self.color_max).prefix("Color max: "))
+            .changed();
+        if color_min_changed || color_max_changed {
+            if self.color_max <= self.color_min {
+                self.color_max = self.color_min + 1.;
+            }
+            reducer.reduce(UiPost::SetColorRange(self.color_min, self.color_max));
+        }
+        ui.horizontal(|ui| {
+            let linear = ui.radio_value(&mut self.color_scale, ColorScale::Linear, "Linear");
+            let log = ui
+                .radio_value(&mut self.color_scale, ColorScale::Log, "Log")
+                .on_hover_text(format!(
+                    "Assumes positive temperatures, anything below {} gets the coldest color",
+                    LOG_SCALE_EPSILON
+                ));
+            let signed_log = ui
+                .radio_value(&mut self.color_scale, ColorScale::SignedLog, "Signed log")
+                .on_hover_text(
+                    "sign(T)·ln(1 + |T|), logarithmic for large magnitudes \
+                     and still telling apart zero and negative temperatures",
+                );
+            if linear.changed() || log.changed() || signed_log.changed() {
+                reducer.reduce(UiPost::SetColorScale(self.color_scale));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let toggled = ui
+                .checkbox(&mut self.smoothing, "Smooth display")
+                .on_hover_text(
+                    "Cosmetic only: averages neighbouring nodes on screen, \
+                     the simulation itself is unchanged and still unstable if it was",
+                )
+                .changed();
+            let resized = ui
+                .add_enabled(
+                    self.smoothing,
+                    egui::Slider::new(&mut self.smoothing_radius, 1..=3).text("Radius"),
+                )
+                .changed();
+            if toggled || resized {
+                let radius = if self.smoothing {
+                    self.smoothing_radius
+                } else {
+                    0
+                };
+                reducer.reduce(UiPost::SetSmoothing(radius));
+            }
+        });
+        if ui
+            .checkbox(&mut self.show_differences, "Show pointwise differences")
+            .on_hover_text("|u1 - u2| of each model's first comparison, in white")
+            .changed()
+        {
+            reducer.reduce(UiPost::SetShowDifferences(self.show_differences));
+        }
+        if ui
+            .checkbox(&mut self.show_spectrum, "Show spectrum")
+            .on_hover_text("Magnitude of each spatial frequency of the nodes, k = 0 is the mean")
+            .changed()
+        {
+            reducer.reduce(UiPost::SetShowSpectrum(self.show_spectrum));
+        }
+        if ui
+            .checkbox(&mut self.show_scalars, "Plot over time")
+            .on_hover_text("Total heat, max temperature or net flux of every model against time")
+            .changed()
+        {
+            reducer.reduce(UiPost::SetShowScalars(self.show_scalars));
+        }
+
+        ui.horizontal(|ui| {
+            let tolerance = ui
+                .add(
+                    egui::DragValue::new(&mut self.steady_tolerance)
+                        .clamp_range(0.0..=f64::MAX)
+                        .speed(1e-6)
+                        .prefix("Steady below: "),
+                )
+                .on_hover_text(
+                    "Largest change of a node per step for a model to count as converged",
+                )
+                .changed();
+            let pause = ui
+                .checkbox(&mut self.pause_steady, "Pause steady models")
+                .changed();
+            if tolerance || pause {
+                reducer.reduce(UiPost::SetSteadyState(
+                    self.steady_tolerance,
+                    self.pause_steady,
+                ));
+            }
+        });
 
         let mut tps = UiGet::GetTps(None);
         reducer.request(&mut tps);
@@ -351,5 +1762,225 @@ impl Controls {
         };
 
         ui.label(format!("TPS: {}", tps));
+
+        let mut tick_times = UiGet::GetTickTimes(None);
+        reducer.request(&mut tick_times);
+        if let UiGet::GetTickTimes(Some((avg, max))) = tick_times {
+            ui.label(format!("Tick time: avg {:.1} us, max {:.1} us", avg, max));
+        }
+
+        let mut samples = UiGet::GetMultisampleSamples(None);
+        reducer.request(&mut samples);
+        if let UiGet::GetMultisampleSamples(Some(samples)) = samples {
+            ui.label(format!("MSAA: {}x", samples))
+                .on_hover_text("Set with --samples, changing it needs a restart");
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Save session").clicked() {
+                let path = FileDialog::new()
+                    .add_filter("Session", &["json"])
+                    .show_save_single_file();
+                match path {
+                    Ok(Some(path)) => {
+                        let mut m = UiGet::ModelInfo(None);
+                        reducer.request(&mut m);
+                        if let UiGet::ModelInfo(Some(model_info)) = m {
+                            self.save_session(&path, &model_info);
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(e) => self.errors = Some(format!("Could not open file dialog: {}\n", e)),
+                }
+            }
+            if ui.button("Load session").clicked() {
+                let path = FileDialog::new()
+                    .add_filter("Session", &["json"])
+                    .show_open_single_file();
+                match path {
+                    Ok(Some(path)) => {
+                        let mut m = UiGet::ModelInfo(None);
+                        reducer.request(&mut m);
+                        if let UiGet::ModelInfo(Some(model_info)) = m {
+                            self.load_session(&path, &model_info, reducer);
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(e) => self.errors = Some(format!("Could not open file dialog: {}\n", e)),
+                }
+            }
+        });
+        self.draw_presets(ui, reducer);
+
+        ui.horizontal(|ui| {
+            if ui.button("Screenshot").clicked() {
+                reducer.reduce(UiPost::TakeScreenshot);
+            }
+            if ui
+                .color_edit_button_rgb(&mut self.clear_color)
+                .on_hover_text("Background color, black or white give more contrast")
+                .changed()
+            {
+                reducer.reduce(UiPost::SetClearColor(self.clear_color));
+            }
+            ui.label("Background");
+        });
+        self.draw_recording(ui, reducer);
+    }
+
+    /// Live a²·dt/h² of the creator fields against the limit of the chosen integrator
+    fn draw_stability_factor(&self, ui: &mut egui::Ui) {
+        let factor = match stability_factor(&self.current_spec(ModelKind::Differential)) {
+            Some(factor) => factor,
+            None => {
+                ui.label("a²·dt/h² = ?");
+                return;
+            }
+        };
+        let limit = self.integrator.stability_limit() / self.spatial_order.stiffness();
+        let color = if factor <= limit {
+            egui::Color32::GREEN
+        } else {
+            egui::Color32::RED
+        };
+        ui.colored_label(
+            color,
+            format!("a²·dt/h² = {:.4} (limit {:.3})", factor, limit),
+        )
+        .on_hover_text(format!(
+            "Largest over the nodes at t = 0. A Differential Model with {:?} is unstable \
+             and the {:?} order stencil past the limit, a System Model only when Sigma is below 0.5",
+            self.integrator, self.spatial_order
+        ));
+    }
+
+    fn draw_presets(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        ui.separator();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Preset")
+                .selected_text(self.preset_name.clone())
+                .show_ui(ui, |ui| {
+                    for (name, _) in &self.presets.presets {
+                        ui.selectable_value(&mut self.preset_name, name.clone(), name);
+                    }
+                });
+            ui.text_edit_singleline(&mut self.preset_name);
+        });
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let exists = self.presets.get(&self.preset_name).is_some();
+            if ui
+                .add_enabled(exists, egui::Button::new("Load preset"))
+                .on_hover_text("Adds the models and comparisons of the preset")
+                .clicked()
+            {
+                if let Some(session) = self.presets.get(&self.preset_name).cloned() {
+                    self.add_session(session, reducer);
+                }
+            }
+            if ui
+                .add_enabled(
+                    !self.preset_name.is_empty(),
+                    egui::Button::new("Save preset"),
+                )
+                .on_hover_text("Saves the current models and comparisons under this name")
+                .clicked()
+            {
+                let mut m = UiGet::ModelInfo(None);
+                reducer.request(&mut m);
+                if let UiGet::ModelInfo(Some(model_info)) = m {
+                    let session = self.current_session(&model_info);
+                    self.presets.insert(self.preset_name.clone(), session);
+                    changed = true;
+                }
+            }
+            if ui
+                .add_enabled(exists, egui::Button::new("Delete preset"))
+                .clicked()
+            {
+                self.presets.remove(&self.preset_name);
+                changed = true;
+            }
+        });
+
+        if changed {
+            if let Err(e) = self.presets.save(&presets_path()) {
+                self.errors = Some(format!("Could not save presets: {}\n", e.cause()));
+            }
+        }
+    }
+
+    fn draw_recording(&mut self, ui: &mut egui::Ui, reducer: &mut dyn Reducer<UiPost, UiGet>) {
+        let mut frames = UiGet::RecordedFrames(None);
+        reducer.request(&mut frames);
+        let frames = match frames {
+            UiGet::RecordedFrames(frames) => frames.unwrap(),
+            _ => panic!("Expected RecordedFrames"),
+        };
+        match frames {
+            Some(frames) => {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::RED, format!("● {} frames", frames));
+                    ui.checkbox(&mut self.encode_recording, "Encode with ffmpeg");
+                    if ui.button("Stop").clicked() {
+                        reducer.reduce(UiPost::StopRecording(self.encode_recording));
+                    }
+                });
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    ui.label("Record to: ");
+                    ui.text_edit_singleline(&mut self.record_dir);
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.record_every)
+                            .clamp_range(1..=1000)
+                            .prefix("every ")
+                            .suffix(" frames"),
+                    );
+                    if ui
+                        .button("Record")
+                        .on_hover_text("Saves numbered PNGs of the simulation, without the windows")
+                        .clicked()
+                    {
+                        reducer.reduce(UiPost::StartRecording(
+                            PathBuf::from(&self.record_dir),
+                            self.record_every,
+                        ));
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_scale_spreads_decades_evenly() {
+        let range = (0.1, 1000.);
+        assert!((ColorScale::Log.value_at(0.25, range) - 1.).abs() < 1e-4);
+        assert!((ColorScale::Log.value_at(0.5, range) - 10.).abs() < 1e-3);
+        assert!((ColorScale::Linear.value_at(0.5, range) - 500.05).abs() < 1e-3);
+
+        // Non-positive ends are clamped instead of producing NaN
+        let low = ColorScale::Log.value_at(0., (-5., 100.));
+        assert!((low / LOG_SCALE_EPSILON - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn signed_log_scale_keeps_zero_and_negatives() {
+        let range = (-100., 100.);
+        assert!(ColorScale::SignedLog.value_at(0.5, range).abs() < 1e-4);
+        assert!((ColorScale::SignedLog.value_at(0., range) + 100.).abs() < 1e-3);
+        assert!((ColorScale::SignedLog.value_at(1., range) - 100.).abs() < 1e-3);
+        // Three quarters of the way is halfway up ln(1 + |T|), far below 50
+        let upper = ColorScale::SignedLog.value_at(0.75, range);
+        assert!((upper - (101f32.ln() / 2.).exp_m1()).abs() < 1e-3);
+        assert!(upper < 10.);
     }
 }
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use super::model_manager::ModelInfo;
+
+const FIRST_STRIP_Y: f32 = -100.;
+
+/// Per-model colors, the heatmap uses them as a tint and the line graph as is
+const MODEL_COLORS: [(f32, f32, f32, f32); 6] = [
+    (1., 0.2, 0.2, 1.),
+    (0.2, 0.4, 1., 1.),
+    (0.2, 0.8, 0.2, 1.),
+    (1., 0.8, 0.1, 1.),
+    (0.8, 0.3, 0.9, 1.),
+    (0.1, 0.8, 0.8, 1.),
+];
+
+pub fn model_color(index: usize) -> (f32, f32, f32, f32) {
+    MODEL_COLORS[index % MODEL_COLORS.len()]
+}
+
+/// The color picked for a model in the model list, or the one of its index
+pub fn picked_color(picked: &HashMap<String, [f32; 3]>, model: &ModelInfo) -> [f32; 3] {
+    picked.get(&model.name).copied().unwrap_or_else(|| {
+        let (r, g, b, _) = model_color(model.color);
+        [r, g, b]
+    })
+}
+
+/// Size of the heatmap strips, in view units
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StripLayout {
+    pub height: f32,
+    /// From the middle of one strip to the middle of the next
+    pub spacing: f32,
+}
+
+impl Default for StripLayout {
+    fn default() -> Self {
+        Self {
+            height: 30.,
+            spacing: 35.,
+        }
+    }
+}
+
+impl StripLayout {
+    /// Middle of the `i`th strip from the top
+    pub fn strip_y(&self, i: usize) -> f32 {
+        FIRST_STRIP_Y + i as f32 * self.spacing
+    }
+}
@@ -0,0 +1,2 @@
+//! The solvers on their own, without the window, so benchmarks can build models
+pub mod model;
@@ -1,10 +1,11 @@
 mod app;
-mod model;
 mod renderer;
 mod ticker;
 mod window;
 
+use egui_test::model;
 use renderer::error::Error;
+use std::path::Path;
 
 macro_rules! panic_call {
     ($func:expr) => {
@@ -12,7 +13,130 @@ macro_rules! panic_call {
     };
 }
 
+const DEFAULT_WIDTH: u32 = 640;
+const DEFAULT_HEIGHT: u32 = 480;
+const DEFAULT_TITLE: &str = "Hello";
+const DEFAULT_SAMPLES: u8 = 4;
+
+struct Args {
+    width: u32,
+    height: u32,
+    title: String,
+    samples: u8,
+    headless: bool,
+    /// Wins over the steps of the scenario
+    steps: Option<u32>,
+    model_config: Option<String>,
+    /// Address to accept remote commands on, only with the `server` feature
+    listen: Option<String>,
+}
+
+fn parse_size(flag: &str, value: Option<String>, default: u32) -> u32 {
+    match value.as_deref().map(str::parse::<u32>) {
+        Some(Ok(size)) if size > 0 => size,
+        Some(_) => {
+            eprintln!(
+                "Invalid value {:?} for {}, using {}",
+                value.unwrap_or_default(),
+                flag,
+                default
+            );
+            default
+        }
+        None => {
+            eprintln!("Missing value for {}, using {}", flag, default);
+            default
+        }
+    }
+}
+
+fn parse_args() -> Args {
+    let mut res = Args {
+        width: DEFAULT_WIDTH,
+        height: DEFAULT_HEIGHT,
+        title: DEFAULT_TITLE.to_owned(),
+        samples: DEFAULT_SAMPLES,
+        headless: false,
+        steps: None,
+        model_config: None,
+        listen: None,
+    };
+
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--width" => res.width = parse_size("--width", args.next(), DEFAULT_WIDTH),
+            "--height" => res.height = parse_size("--height", args.next(), DEFAULT_HEIGHT),
+            "--title" => match args.next() {
+                Some(title) => res.title = title,
+                None => eprintln!("Missing value for --title, using {:?}", DEFAULT_TITLE),
+            },
+            // 0 turns multisampling off, which helps on integrated GPUs
+            "--samples" => match args.next().as_deref().map(str::parse::<u8>) {
+                Some(Ok(samples)) => res.samples = samples,
+                _ => eprintln!("Invalid value for --samples, using {}", DEFAULT_SAMPLES),
+            },
+            // The scenario can follow directly instead of going through --model-config
+            "--headless" => {
+                res.headless = true;
+                if let Some(path) = args.next_if(|a| !a.starts_with("--")) {
+                    res.model_config = Some(path);
+                }
+            }
+            "--steps" => {
+                let default = app::headless::DEFAULT_STEPS as u32;
+                res.steps = Some(parse_size("--steps", args.next(), default))
+            }
+            "--model-config" => match args.next() {
+                Some(path) => res.model_config = Some(path),
+                None => eprintln!("Missing value for --model-config"),
+            },
+            "--listen" => match args.next() {
+                Some(address) => res.listen = Some(address),
+                None => eprintln!("Missing value for --listen"),
+            },
+            _ => eprintln!("Unknown argument {:?}", arg),
+        }
+    }
+
+    res
+}
+
 fn main() {
-    let mut app = panic_call!(app::app::App::new());
+    let args = parse_args();
+    if args.headless {
+        match &args.model_config {
+            Some(path) => {
+                if !panic_call!(app::headless::run(
+                    Path::new(path),
+                    args.steps.map(|s| s as usize)
+                )) {
+                    eprintln!("Some models diverged");
+                    std::process::exit(2);
+                }
+            }
+            None => {
+                eprintln!("--headless needs a scenario or session file");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut app = panic_call!(app::app::App::new(
+        args.width,
+        args.height,
+        &args.title,
+        args.samples
+    ));
+    if let Some(address) = &args.listen {
+        #[cfg(feature = "server")]
+        panic_call!(app.listen(address));
+        #[cfg(not(feature = "server"))]
+        eprintln!(
+            "Ignoring --listen {}: built without the server feature",
+            address
+        );
+    }
     panic_call!(app.run());
 }
@@ -1,8 +1,10 @@
+use crate::model::error::ModelError;
 use crate::model::model::*;
 use exmex::prelude::*;
 use rayon::prelude::*;
 
 type T = f64;
+#[derive(Clone)]
 pub struct AnalyticModel {
     func: exmex::FlatEx<T>,
 
@@ -12,17 +14,25 @@ pub struct AnalyticModel {
     nodes: Vec<T>,
     cur_time_step: u32,
     node_count: u32,
+    max_step_delta: T,
+    units: Units,
 }
 
 impl AnalyticModel {
-    pub fn new(func: exmex::FlatEx<T>, length: T, node_count: u32, time_step: T) -> Self {
+    pub fn new(
+        func: exmex::FlatEx<T>,
+        length: T,
+        node_count: u32,
+        time_step: T,
+    ) -> Result<Self, ModelError> {
+        check_node_count(node_count)?;
         let node_step = length / (node_count - 1) as T;
         let nodes = (0..node_count)
             .into_par_iter()
-            .map(|i| func.eval(&[0., node_step * i as T]).unwrap())
-            .collect();
+            .map(|i| func.eval(&[0., node_step * i as T]))
+            .collect::<Result<_, _>>()?;
 
-        Self {
+        let res = Self {
             node_count,
             length,
             node_step,
@@ -30,7 +40,19 @@ impl AnalyticModel {
             time_step,
             nodes,
             func,
-        }
+            max_step_delta: T::INFINITY,
+            units: Units::default(),
+        };
+        check_initial_nodes(&res.get_node_positions(), &res.nodes)?;
+        Ok(res)
+    }
+
+    fn eval_nodes(&self) -> Result<Vec<T>, ModelError> {
+        let time = self.cur_time_step as T * self.time_step;
+        Ok((0..self.node_count)
+            .into_par_iter()
+            .map(|i| self.func.eval(&[time, self.node_step * i as T]))
+            .collect::<Result<_, _>>()?)
     }
 }
 
@@ -47,31 +69,76 @@ impl Model for AnalyticModel {
         &self.node_step
     }
 
-    fn reset(&mut self) {
-        let func = &self.func;
-        self.nodes = (0..self.node_count)
-            .into_par_iter()
-            .map(|i| func.eval(&[0., self.node_step * i as T]).unwrap())
-            .collect();
+    fn get_time_step(&self) -> T {
+        self.time_step
+    }
+
+    fn reset(&mut self) -> Result<(), ModelError> {
         self.cur_time_step = 0;
+        self.nodes = self.eval_nodes()?;
+        self.max_step_delta = T::INFINITY;
+        Ok(())
     }
 
-    fn run_step(&mut self) {
+    fn run_step(&mut self) -> Result<(), ModelError> {
         self.cur_time_step += 1;
-        self.nodes = (0..self.node_count)
-            .into_par_iter()
-            .map(|i| {
-                self.func
-                    .eval(&[
-                        self.cur_time_step as T * self.time_step,
-                        self.node_step * i as T,
-                    ])
-                    .unwrap()
-            })
-            .collect();
+        match self.eval_nodes() {
+            Ok(nodes) => {
+                self.max_step_delta = max_abs_difference(&nodes, &self.nodes);
+                self.nodes = nodes;
+                Ok(())
+            }
+            Err(e) => {
+                self.cur_time_step -= 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// The solution is evaluated directly, so any time is reached instantly
+    fn set_time(&mut self, time: T) -> Result<(), ModelError> {
+        check_seek_time(time)?;
+        self.cur_time_step = (time / self.time_step).ceil().clamp(0., u32::MAX as T) as u32;
+        self.nodes = self.eval_nodes()?;
+        Ok(())
+    }
+
+    fn is_closed_form(&self) -> bool {
+        true
     }
 
     fn get_elapsed_time(&self) -> T {
         self.cur_time_step as T * self.time_step
     }
+
+    fn max_step_delta(&self) -> T {
+        self.max_step_delta
+    }
+
+    fn get_units(&self) -> &Units {
+        &self.units
+    }
+
+    fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    fn clone_box(&self) -> Box<dyn Model> {
+        Box::new(self.clone())
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        vec![("u(t, x)".to_owned(), expression_text(&self.func))]
+    }
+
+    fn temperature_at(&self, x: T) -> Option<T> {
+        self.exact_at(x, self.get_elapsed_time())
+    }
+
+    fn exact_at(&self, x: T, t: T) -> Option<T> {
+        if !(0. ..=self.length).contains(&x) {
+            return None;
+        }
+        self.func.eval(&[t, x]).ok()
+    }
 }
@@ -0,0 +1,156 @@
+use crate::model::model::Model;
+use rayon::prelude::*;
+
+/// Differences of two models, all taken in the same pass over their nodes
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Metrics {
+    pub l2: f64,
+    /// Largest pointwise difference
+    pub max: f64,
+    /// Difference of the total heat by the trapezoidal rule
+    pub heat: f64,
+}
+
+impl Metrics {
+    /// Sums up `(d², |d|, weight·d)` of every compared node
+    fn of(terms: (f64, f64, f64)) -> Self {
+        let (squares, max, heat) = terms;
+        Self {
+            l2: squares.sqrt(),
+            max,
+            heat: heat.abs(),
+        }
+    }
+}
+
+/// Adds up the terms of two halves of a comparison
+fn add_terms(a: (f64, f64, f64), b: (f64, f64, f64)) -> (f64, f64, f64) {
+    (a.0 + b.0, a.1.max(b.1), a.2 + b.2)
+}
+
+/// Trapezoidal-rule weight of node `i`, half of the intervals on either side of it
+fn trapezoid_weight(positions: &[f64], i: usize) -> f64 {
+    let left = positions[i.saturating_sub(1)];
+    let right = positions[(i + 1).min(positions.len() - 1)];
+    (right - left) / 2.
+}
+
+pub fn compare_models(model_1: &dyn Model, model_2: &dyn Model) -> Metrics {
+    if !same_grid(model_1, model_2) {
+        return compare_resampled(model_1, model_2);
+    }
+    let positions = model_1.get_node_positions();
+    let (nodes_1, nodes_2) = (model_1.get_cur_nodes(), model_2.get_cur_nodes());
+    Metrics::of(
+        (0..nodes_1.len())
+            .into_par_iter()
+            .map(|i| {
+                let d = nodes_1[i] - nodes_2[i];
+                (d * d, d.abs(), trapezoid_weight(&positions, i) * d)
+            })
+            .reduce(|| (0., 0., 0.), add_terms),
+    )
+}
+
+/// Compares a model with a closed-form reference at the nodes and time of the model,
+/// so the reference never has to be stepped there
+pub fn compare_to_exact(model: &dyn Model, reference: &dyn Model) -> Metrics {
+    let time = model.get_elapsed_time();
+    compare_sampled(model, |x| reference.exact_at(x, time))
+}
+
+/// Compares models on different grids at the nodes of the coarser one
+fn compare_resampled(model_1: &dyn Model, model_2: &dyn Model) -> Metrics {
+    let (coarse, fine) = if model_1.get_node_count() <= model_2.get_node_count() {
+        (model_1, model_2)
+    } else {
+        (model_2, model_1)
+    };
+    compare_sampled(coarse, |x| fine.temperature_at(x))
+}
+
+/// Compares the nodes of `model` with `sample` at their positions, skipping where it is `None`
+fn compare_sampled(model: &dyn Model, sample: impl Fn(f64) -> Option<f64>) -> Metrics {
+    let positions = model.get_node_positions();
+    Metrics::of(
+        model
+            .get_cur_nodes()
+            .iter()
+            .zip(&positions)
+            .enumerate()
+            .filter_map(|(i, (a, x))| {
+                let d = a - sample(*x)?;
+                Some((d * d, d.abs(), trapezoid_weight(&positions, i) * d))
+            })
+            .fold((0., 0., 0.), add_terms),
+    )
+}
+
+/// Whether the nodes of both models sit at the same positions, so they can be zipped
+pub fn same_grid(model_1: &dyn Model, model_2: &dyn Model) -> bool {
+    model_1.get_node_count() == model_2.get_node_count()
+        && model_1
+            .get_node_positions()
+            .iter()
+            .zip(model_2.get_node_positions())
+            .all(|(a, b)| (a - b).abs() <= 1e-9 * a.abs().max(1.))
+}
+
+/// Models on different domains have no meaningful difference
+pub fn same_length(model_1: &dyn Model, model_2: &dyn Model) -> bool {
+    let (l1, l2) = (*model_1.get_length(), *model_2.get_length());
+    (l1 - l2).abs() <= 1e-9 * l1.abs().max(l2.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::analytic::AnalyticModel;
+
+    fn make_model(amplitude: f64, node_count: u32) -> AnalyticModel {
+        let func = exmex::parse::<f64>(&format!("{}*exp(-t)*sin(PI*x/200)", amplitude)).unwrap();
+        AnalyticModel::new(func, 200., node_count, 1.).unwrap()
+    }
+
+    #[test]
+    fn metrics_come_from_one_pass() {
+        let a = make_model(100., 10);
+        let b = make_model(90., 10);
+        let differences: Vec<f64> = a
+            .get_cur_nodes()
+            .iter()
+            .zip(b.get_cur_nodes())
+            .map(|(u1, u2)| u1 - u2)
+            .collect();
+
+        let metrics = compare_models(&a, &b);
+        let l2 = differences.iter().map(|d| d * d).sum::<f64>().sqrt();
+        assert!((metrics.l2 - l2).abs() < 1e-9);
+        let max = differences.iter().cloned().fold(0., f64::max);
+        assert_eq!(metrics.max, max);
+        let heat = (a.total_heat() - b.total_heat()).abs();
+        assert!((metrics.heat - heat).abs() < 1e-9 * heat);
+
+        // An analytic model is evaluated exactly at the nodes of the coarser one
+        let fine = make_model(90., 19);
+        let resampled = compare_models(&a, &fine);
+        assert!((resampled.max - max).abs() < 1e-9, "{:?}", resampled);
+        assert!((resampled.heat - heat).abs() < 1e-9 * heat);
+    }
+
+    #[test]
+    fn exact_reference_is_taken_at_the_model_time() {
+        let mut a = make_model(100., 10);
+        a.run_steps(3).unwrap();
+        let reference = make_model(90., 19);
+        let exact = compare_to_exact(&a, &reference);
+        assert_eq!(reference.get_elapsed_time(), 0.);
+
+        let mut stepped = make_model(90., 10);
+        stepped.run_steps(3).unwrap();
+        let metrics = compare_models(&a, &stepped);
+        assert!((exact.l2 - metrics.l2).abs() < 1e-9, "{:?}", exact);
+        assert!((exact.max - metrics.max).abs() < 1e-9);
+        assert!((exact.heat - metrics.heat).abs() < 1e-9);
+    }
+}
@@ -1,113 +1,514 @@
+use crate::model::error::ModelError;
 use crate::model::model::*;
 
 use exmex::prelude::*;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 type T = f64;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum TimeIntegrator {
+    #[default]
+    ForwardEuler,
+    RK4,
+}
+
+impl TimeIntegrator {
+    /// Largest a²·dt/h² the integrator is stable at on a uniform grid,
+    /// where the real stability interval of RK4 ends at about -2.785
+    pub fn stability_limit(&self) -> T {
+        match self {
+            Self::ForwardEuler => 0.5,
+            Self::RK4 => 2.785 / 4.,
+        }
+    }
+}
+
+/// Stencil of the second derivative in x
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum SpatialOrder {
+    /// `(u[i-1] - 2u[i] + u[i+1]) / h²`
+    #[default]
+    Second,
+    /// `(-u[i-2] + 16u[i-1] - 30u[i] + 16u[i+1] - u[i+2]) / 12h²` on uniform grids
+    /// with a coefficient of x, the nodes next to a non-periodic edge stay second order
+    Fourth,
+}
+
+impl SpatialOrder {
+    /// How much larger the largest eigenvalue of the stencil is than the 3-point one's,
+    /// explicit integrators need a time step that much smaller
+    pub fn stiffness(&self) -> T {
+        match self {
+            Self::Second => 1.,
+            Self::Fourth => 4. / 3.,
+        }
+    }
+}
+
+/// Where the three-point stencil takes an expression coefficient,
+/// piecewise coefficients are always taken between the nodes
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum CoefficientStencil {
+    /// `(a²[i+1/2](u[i+1] - u[i]) - a²[i-1/2](u[i] - u[i-1])) / h²` with `a` halfway between
+    /// the nodes, the divergence form `(a²·u_x)_x` of conduction through a varying material
+    /// that keeps the heat flowing out of one cell equal to what flows into the next
+    #[default]
+    Faces,
+    /// `a²[i]·(u[i-1] - 2u[i] + u[i+1]) / h²`, which drops the `(a²)'·u_x` term,
+    /// kept to compare against older runs. Both agree for a constant coefficient
+    Nodes,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum BoundaryKind {
+    #[default]
+    Dirichlet,
+    /// The first and last node are the same point of a ring
+    Periodic,
+    /// The edge conditions give the heat flowing in through each edge,
+    /// 0 for an insulated edge
+    Neumann,
+}
+
+/// Kind of the left and the right edge, a periodic edge has to be paired with another one
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct Boundaries {
+    pub left: BoundaryKind,
+    pub right: BoundaryKind,
+}
+
+impl Boundaries {
+    fn is_periodic(&self) -> bool {
+        self.left == BoundaryKind::Periodic
+    }
+}
+
+impl From<BoundaryKind> for Boundaries {
+    /// The same kind on both edges
+    fn from(kind: BoundaryKind) -> Self {
+        Self {
+            left: kind,
+            right: kind,
+        }
+    }
+}
+
+/// How a `DifferentialModel` discretizes the rod
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DifferentialOptions {
+    pub node_count: u32,
+    pub time_step: T,
+    /// Spacing ratio between neighbouring intervals, 1 is a uniform grid
+    pub grading: T,
+    pub integrator: TimeIntegrator,
+    pub boundaries: Boundaries,
+    pub spatial_order: SpatialOrder,
+    pub coefficient_stencil: CoefficientStencil,
+    /// Steps over which Dirichlet edges are blended in from the start conditions, 0 is off
+    pub ramp_steps: u32,
+}
+
+impl DifferentialOptions {
+    /// A uniform grid stepped by forward Euler between Dirichlet edges
+    pub fn new(node_count: u32, time_step: T) -> Self {
+        Self {
+            node_count,
+            time_step,
+            grading: 1.,
+            integrator: TimeIntegrator::default(),
+            boundaries: Boundaries::default(),
+            spatial_order: SpatialOrder::default(),
+            coefficient_stencil: CoefficientStencil::default(),
+            ramp_steps: 0,
+        }
+    }
+}
+
+/// a² at the nodes and between them, borrowed from the cache when it holds for every time
+type NodeAndIntervalA2<'a> = (Cow<'a, [T]>, Cow<'a, [T]>);
+
+#[derive(Clone)]
 pub struct DifferentialModel {
-    starting_conditions: exmex::FlatEx<T>,
+    starting_conditions: InitialCondition,
     left_edge_conditions: exmex::FlatEx<T>,
     right_edge_conditions: exmex::FlatEx<T>,
-    coefficient: exmex::FlatEx<T>,
+    coefficient: Coefficient,
 
     length: T,
     time_step: T,
+    /// Smallest spacing between two nodes
     node_step: T,
+    positions: Vec<T>,
+    /// a² at every node, evaluated once unless the coefficient depends on time
+    a2: Vec<T>,
+    /// a² between every pair of neighbouring nodes, only for piecewise coefficients
+    interval_a2: Vec<T>,
     nodes: Vec<T>,
     cur_time_step: u32,
+    max_step_delta: T,
+    units: Units,
+    integrator: TimeIntegrator,
+    boundaries: Boundaries,
+    spatial_order: SpatialOrder,
+    coefficient_stencil: CoefficientStencil,
+    /// Dirichlet edges go from the start conditions to the edge conditions over this many
+    /// steps, 0 applies the edge conditions from the start.
+    /// A start that doesn't match the edge values puts a jump of the whole difference
+    /// between the edge and its neighbour, whose second difference the first steps
+    /// multiply by dt/h². The explicit schemes overshoot on that even below their
+    /// stability limit, ramping spreads the jump over several steps
+    ramp_steps: u32,
 }
 
 impl DifferentialModel {
     pub fn new(
-        starting_conditions: exmex::FlatEx<T>,
+        starting_conditions: InitialCondition,
         left_edge_conditions: exmex::FlatEx<T>,
         right_edge_conditions: exmex::FlatEx<T>,
-        coefficient: exmex::FlatEx<T>,
+        coefficient: Coefficient,
         length: T,
-        node_count: u32,
-        time_step: T,
-    ) -> Self {
-        let node_step = length / (node_count as T - 1.);
-        let mut nodes = Vec::with_capacity(node_count as usize);
-        nodes.push(left_edge_conditions.eval(&[0.]).unwrap());
-        nodes.append(
-            &mut (1..node_count - 1)
-                .map(|i| starting_conditions.eval(&[node_step * i as T]).unwrap())
-                .collect(),
-        );
-        nodes.push(right_edge_conditions.eval(&[0.]).unwrap());
-        Self {
+        options: DifferentialOptions,
+    ) -> Result<Self, ModelError> {
+        let DifferentialOptions {
+            node_count,
+            time_step,
+            grading,
+            integrator,
+            boundaries,
+            spatial_order,
+            coefficient_stencil,
+            ramp_steps,
+        } = options;
+        check_node_count(node_count)?;
+        if (boundaries.left == BoundaryKind::Periodic)
+            != (boundaries.right == BoundaryKind::Periodic)
+        {
+            return Err(ModelError::Parameter(
+                "a periodic edge needs the other edge to be periodic too".to_owned(),
+            ));
+        }
+        // The five-point stencil assumes equal spacing
+        let spatial_order = if (grading - 1.).abs() < 1e-12 {
+            spatial_order
+        } else {
+            SpatialOrder::Second
+        };
+        let positions = graded_positions(length, node_count, grading);
+        let node_step = positions
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .fold(T::INFINITY, T::min);
+        let mut res = Self {
             node_step,
+            positions,
+            a2: vec![],
+            interval_a2: vec![],
             coefficient,
             left_edge_conditions,
             right_edge_conditions,
             starting_conditions,
             length,
             time_step,
-            nodes,
+            nodes: vec![0.; node_count as usize],
             cur_time_step: 0,
-        }
+            max_step_delta: T::INFINITY,
+            units: Units::default(),
+            integrator,
+            boundaries,
+            spatial_order,
+            coefficient_stencil,
+            ramp_steps,
+        };
+        res.reset()?;
+        check_initial_nodes(&res.positions, &res.nodes)?;
+        Ok(res)
     }
 
-    fn restore_node_value(&self, node_num: u32) -> T {
-        if node_num == 0 {
-            self.left_edge_conditions.eval(&[0.]).unwrap()
-        } else if node_num == self.nodes.len() as u32 - 1 {
-            self.right_edge_conditions.eval(&[0.]).unwrap()
+    fn restore_node_value(&self, node_num: u32) -> Result<T, ModelError> {
+        if self.boundaries.is_periodic() {
+            // Both ends are x = 0 on the ring
+            let node_num = node_num as usize % (self.nodes.len() - 1);
+            self.starting_conditions.eval(self.positions[node_num])
+        } else if node_num == 0 && self.boundaries.left == BoundaryKind::Dirichlet {
+            Ok(self.dirichlet_edges(0.)?.0)
+        } else if node_num == self.nodes.len() as u32 - 1
+            && self.boundaries.right == BoundaryKind::Dirichlet
+        {
+            Ok(self.dirichlet_edges(0.)?.1)
         } else {
             self.starting_conditions
-                .eval(&[self.node_step * node_num as T])
-                .unwrap()
+                .eval(self.positions[node_num as usize])
+        }
+    }
+
+    /// The (left, right) Dirichlet edge values at `time`, blended from the start conditions
+    /// at the edges into the edge conditions while the ramp lasts
+    fn dirichlet_edges(&self, time: T) -> Result<(T, T), ModelError> {
+        let left = self.left_edge_conditions.eval(&[time])?;
+        let right = self.right_edge_conditions.eval(&[time])?;
+        let progress = time / (self.ramp_steps as T * self.time_step);
+        if self.ramp_steps == 0 || progress >= 1. {
+            return Ok((left, right));
+        }
+        let start_left = self.starting_conditions.eval(self.positions[0])?;
+        let start_right = self
+            .starting_conditions
+            .eval(self.positions[self.positions.len() - 1])?;
+        Ok((
+            start_left + progress * (left - start_left),
+            start_right + progress * (right - start_right),
+        ))
+    }
+
+    fn eval_a2(&self, time: T) -> Result<Vec<T>, ModelError> {
+        self.positions
+            .par_iter()
+            .map(|x| {
+                let a = self.coefficient.eval(*x, time)?;
+                Ok(a * a)
+            })
+            .collect()
+    }
+
+    /// Whether an expression coefficient is taken between the nodes,
+    /// the five-point stencil only has a form with a² at the node
+    fn faces_of_expr(&self) -> bool {
+        matches!(self.coefficient, Coefficient::Expr(_))
+            && self.coefficient_stencil == CoefficientStencil::Faces
+            && self.spatial_order == SpatialOrder::Second
+    }
+
+    fn eval_interval_a2(&self, time: T) -> Result<Vec<T>, ModelError> {
+        if self.faces_of_expr() {
+            return self
+                .positions
+                .windows(2)
+                .map(|w| {
+                    let a = self.coefficient.eval((w[0] + w[1]) / 2., time)?;
+                    Ok(a * a)
+                })
+                .collect();
         }
+        self.positions
+            .windows(2)
+            .filter_map(|w| self.coefficient.interval_a2(w[0], w[1], time).transpose())
+            .collect()
     }
 
-    fn get_node_value(&self, node_num: u32) -> T {
-        let time = self.cur_time_step as T * self.time_step;
-        if node_num == 0 {
-            self.left_edge_conditions.eval(&[time]).unwrap()
-        } else if node_num == self.nodes.len() as u32 - 1 {
-            self.right_edge_conditions.eval(&[time]).unwrap()
+    /// a² at every node and between neighbours at `time`, the cached ones unless `a` depends on t
+    fn a2_at(&self, time: T) -> Result<NodeAndIntervalA2<'_>, ModelError> {
+        if self.coefficient.depends_on_time() {
+            Ok((
+                Cow::Owned(self.eval_a2(time)?),
+                Cow::Owned(self.eval_interval_a2(time)?),
+            ))
         } else {
-            let ai = self
-                .coefficient
-                .eval(&[self.node_step * node_num as T])
-                .unwrap();
+            Ok((Cow::Borrowed(&self.a2), Cow::Borrowed(&self.interval_a2)))
+        }
+    }
 
-            let a2 = ai * ai;
-            let h2 = self.node_step * self.node_step;
+    /// `(-u[i-2] + 16u[i-1] - 30u[i] + 16u[i+1] - u[i+2]) / 12h²`,
+    /// `None` when the stencil would reach past a non-periodic edge
+    fn fourth_order_difference(&self, nodes: &[T], i: usize) -> Option<T> {
+        let last = nodes.len() as isize - 1;
+        let at = |j: isize| {
+            if self.boundaries.is_periodic() {
+                Some(nodes[j.rem_euclid(last) as usize])
+            } else {
+                nodes.get(usize::try_from(j).ok()?).copied()
+            }
+        };
+        let i = i as isize;
+        let h = self.node_step;
+        Some(
+            (-at(i - 2)? + 16. * at(i - 1)? - 30. * at(i)? + 16. * at(i + 1)? - at(i + 2)?)
+                / (12. * h * h),
+        )
+    }
 
-            let res = a2 * self.time_step / h2
-                * (self.nodes[(node_num - 1) as usize] - 2. * self.nodes[node_num as usize]
-                    + self.nodes[(node_num + 1) as usize])
-                + self.nodes[node_num as usize];
-            res
+    /// du/dt of every node at `time`, Dirichlet edges are held fixed
+    fn derivative(&self, nodes: &[T], time: T) -> Result<Vec<T>, ModelError> {
+        let last = nodes.len() - 1;
+        let (a2, interval_a2) = self.a2_at(time)?;
+        let (a2, interval_a2) = (&a2[..], &interval_a2[..]);
+        let periodic = self.boundaries.is_periodic();
+        // The heat flowing in through each Neumann edge
+        let flux_in = |kind: BoundaryKind, conditions: &exmex::FlatEx<T>| match kind {
+            BoundaryKind::Neumann => conditions.eval(&[time]).map(Some),
+            _ => Ok(None),
+        };
+        let left_flux = flux_in(self.boundaries.left, &self.left_edge_conditions)?;
+        let right_flux = flux_in(self.boundaries.right, &self.right_edge_conditions)?;
+        (0..nodes.len())
+            .into_par_iter()
+            .map(|i| {
+                if (i == 0 || i == last) && !periodic {
+                    let (flux_in, inner, h, a2) = match (i, left_flux, right_flux) {
+                        (0, Some(left), _) => (
+                            left,
+                            nodes[1],
+                            self.positions[1] - self.positions[0],
+                            interval_a2.first().copied().unwrap_or(a2[0]),
+                        ),
+                        (0, None, _) => return Ok(0.),
+                        (_, _, Some(right)) => (
+                            right,
+                            nodes[last - 1],
+                            self.positions[last] - self.positions[last - 1],
+                            interval_a2.last().copied().unwrap_or(a2[last]),
+                        ),
+                        (_, _, None) => return Ok(0.),
+                    };
+                    // The usual stencil with a ghost node mirrored past the edge,
+                    // u[-1] = u[1] + 2h·flux_in/a², which keeps the edge second order
+                    return Ok(2. * a2 * (inner - nodes[i]) / (h * h) + 2. * flux_in / h);
+                }
+                // On a ring the neighbours of the seam are found on the other side
+                let (left, h_left) = if i == 0 {
+                    (
+                        nodes[last - 1],
+                        self.positions[last] - self.positions[last - 1],
+                    )
+                } else {
+                    (nodes[i - 1], self.positions[i] - self.positions[i - 1])
+                };
+                let (right, h_right) = if i == last {
+                    (nodes[1], self.positions[1] - self.positions[0])
+                } else {
+                    (nodes[i + 1], self.positions[i + 1] - self.positions[i])
+                };
+
+                if !interval_a2.is_empty() {
+                    // Flux through each side with the a² in between,
+                    // so the flux stays continuous where two materials meet
+                    let a2_left = interval_a2[if i == 0 { last - 1 } else { i - 1 }];
+                    let a2_right = interval_a2[if i == last { 0 } else { i }];
+                    return Ok(2. / (h_left + h_right)
+                        * (a2_right * (right - nodes[i]) / h_right
+                            - a2_left * (nodes[i] - left) / h_left));
+                }
+
+                if self.spatial_order == SpatialOrder::Fourth {
+                    if let Some(difference) = self.fourth_order_difference(nodes, i) {
+                        return Ok(a2[i] * difference);
+                    }
+                }
+
+                // Three-point second difference on a non-uniform grid,
+                // equal to (u[i-1] - 2u[i] + u[i+1]) / h² when both spacings are h
+                let second_difference = 2. / (h_left + h_right)
+                    * ((right - nodes[i]) / h_right - (nodes[i] - left) / h_left);
+                Ok(a2[i] * second_difference)
+            })
+            .collect()
+    }
+
+    /// `nodes + scale * derivative` with Dirichlet edges set to the boundary conditions at `time`
+    fn advance(&self, derivative: &[T], scale: T, time: T) -> Result<Vec<T>, ModelError> {
+        let mut nodes: Vec<T> = self
+            .nodes
+            .par_iter()
+            .zip(derivative.par_iter())
+            .map(|(u, du)| u + scale * du)
+            .collect();
+        self.set_dirichlet_edges(&mut nodes, time)?;
+        Ok(nodes)
+    }
+
+    /// Sets the edges of `nodes` that are Dirichlet to their conditions at `time`
+    fn set_dirichlet_edges(&self, nodes: &mut [T], time: T) -> Result<(), ModelError> {
+        let Boundaries { left, right } = self.boundaries;
+        if left != BoundaryKind::Dirichlet && right != BoundaryKind::Dirichlet {
+            return Ok(());
+        }
+        let last = nodes.len() - 1;
+        let (left_value, right_value) = self.dirichlet_edges(time)?;
+        if left == BoundaryKind::Dirichlet {
+            nodes[0] = left_value;
+        }
+        if right == BoundaryKind::Dirichlet {
+            nodes[last] = right_value;
+        }
+        Ok(())
+    }
+
+    fn next_nodes(&self) -> Result<Vec<T>, ModelError> {
+        let dt = self.time_step;
+        let time = self.cur_time_step as T * dt;
+        let k1 = self.derivative(&self.nodes, time - dt)?;
+        match self.integrator {
+            TimeIntegrator::ForwardEuler => self.advance(&k1, dt, time),
+            TimeIntegrator::RK4 => {
+                let half = time - dt / 2.;
+                let k2 = self.derivative(&self.advance(&k1, dt / 2., half)?, half)?;
+                let k3 = self.derivative(&self.advance(&k2, dt / 2., half)?, half)?;
+                let k4 = self.derivative(&self.advance(&k3, dt, time)?, time)?;
+                let k: Vec<T> = (0..k1.len())
+                    .into_par_iter()
+                    .map(|i| (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]) / 6.)
+                    .collect();
+                self.advance(&k, dt, time)
+            }
         }
     }
 }
 
+/// Node positions whose spacing grows by `grading` from left to right,
+/// so a grading above 1 clusters the nodes toward the left edge
+pub fn graded_positions(length: T, node_count: u32, grading: T) -> Vec<T> {
+    let intervals = node_count as i32 - 1;
+    if (grading - 1.).abs() < 1e-12 {
+        let node_step = length / intervals as T;
+        return (0..=intervals).map(|i| node_step * i as T).collect();
+    }
+
+    let first_step = length * (grading - 1.) / (grading.powi(intervals) - 1.);
+    let mut positions: Vec<T> = (0..=intervals)
+        .map(|i| first_step * (grading.powi(i) - 1.) / (grading - 1.))
+        .collect();
+    positions[intervals as usize] = length;
+    positions
+}
+
 impl Model for DifferentialModel {
     fn get_length(&self) -> &T {
         &self.length
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self) -> Result<(), ModelError> {
         let nodes = (0..self.nodes.len())
             .into_par_iter()
             .map(|i| self.restore_node_value(i as u32))
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         self.cur_time_step = 0;
+        self.max_step_delta = T::INFINITY;
 
         self.nodes = nodes;
+        Ok(())
     }
 
-    fn run_step(&mut self) {
+    fn run_step(&mut self) -> Result<(), ModelError> {
+        if self.a2.is_empty() && !self.coefficient.depends_on_time() {
+            self.a2 = self.eval_a2(0.)?;
+            self.interval_a2 = self.eval_interval_a2(0.)?;
+        }
         self.cur_time_step += 1;
 
-        self.nodes = (0..self.nodes.len())
-            .into_par_iter()
-            .map(|i| self.get_node_value(i as u32))
-            .collect();
+        match self.next_nodes() {
+            Ok(nodes) => {
+                self.max_step_delta = max_abs_difference(&nodes, &self.nodes);
+                self.nodes = nodes;
+                Ok(())
+            }
+            Err(e) => {
+                self.cur_time_step -= 1;
+                Err(e)
+            }
+        }
     }
 
     fn get_cur_nodes(&self) -> &[T] {
@@ -118,7 +519,702 @@ impl Model for DifferentialModel {
         &self.node_step
     }
 
+    fn get_time_step(&self) -> T {
+        self.time_step
+    }
+
+    fn get_node_positions(&self) -> Vec<T> {
+        self.positions.clone()
+    }
+
+    fn temperature_at(&self, x: T) -> Option<T> {
+        interpolate_positions(&self.positions, &self.nodes, x)
+    }
+
+    fn reseed_edges(&mut self, left: FlatEx<T>, right: FlatEx<T>) -> Result<(), ModelError> {
+        let time = self.get_elapsed_time();
+        eval_edges(&left, &right, time)?;
+        self.left_edge_conditions = left;
+        self.right_edge_conditions = right;
+        let mut nodes = self.nodes.clone();
+        self.set_dirichlet_edges(&mut nodes, time)?;
+        self.nodes = nodes;
+        Ok(())
+    }
+
+    fn set_nodes(&mut self, nodes: &[T]) -> Result<(), ModelError> {
+        check_set_nodes(nodes, self.nodes.len())?;
+        self.nodes = nodes.to_vec();
+        self.max_step_delta = T::INFINITY;
+        Ok(())
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = vec![
+            ("Start".to_owned(), self.starting_conditions.describe()),
+            (
+                "Left edge".to_owned(),
+                expression_text(&self.left_edge_conditions),
+            ),
+            (
+                "Right edge".to_owned(),
+                expression_text(&self.right_edge_conditions),
+            ),
+            ("a".to_owned(), self.coefficient.describe()),
+            ("Integrator".to_owned(), format!("{:?}", self.integrator)),
+            (
+                "Boundary".to_owned(),
+                match self.boundaries {
+                    Boundaries { left, right } if left == right => format!("{:?}", left),
+                    Boundaries { left, right } => format!("{:?} / {:?}", left, right),
+                },
+            ),
+            (
+                "Spatial order".to_owned(),
+                format!("{:?}", self.spatial_order),
+            ),
+        ];
+        if matches!(self.coefficient, Coefficient::Expr(_)) {
+            details.push((
+                "Coefficient stencil".to_owned(),
+                format!("{:?}", self.coefficient_stencil),
+            ));
+        }
+        if self.ramp_steps > 0 {
+            details.push(("Edge ramp steps".to_owned(), self.ramp_steps.to_string()));
+        }
+        details
+    }
+
+    fn boundary_flux(&self) -> Option<(T, T)> {
+        let n = self.positions.len();
+        if n < 2 {
+            return None;
+        }
+        let time = self.get_elapsed_time();
+        let a2 = |x: T| self.coefficient.eval(x, time).map_or(T::NAN, |a| a * a);
+        let (left, right) = edge_flux(
+            &self.nodes,
+            (
+                self.positions[1] - self.positions[0],
+                self.positions[n - 1] - self.positions[n - 2],
+            ),
+            (a2(0.), a2(self.length)),
+        );
+        // A Neumann edge lets in what its condition says
+        let given = |kind: BoundaryKind, conditions: &exmex::FlatEx<T>, fallback: T| match kind {
+            BoundaryKind::Neumann => conditions.eval(&[time]).ok(),
+            _ => Some(fallback),
+        };
+        given(self.boundaries.left, &self.left_edge_conditions, left).zip(given(
+            self.boundaries.right,
+            &self.right_edge_conditions,
+            right,
+        ))
+    }
+
     fn get_elapsed_time(&self) -> T {
         self.cur_time_step as T * self.time_step
     }
+
+    fn max_step_delta(&self) -> T {
+        self.max_step_delta
+    }
+
+    fn get_units(&self) -> &Units {
+        &self.units
+    }
+
+    fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    fn clone_box(&self) -> Box<dyn Model> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+
+    fn decay(x: f64, t: f64) -> f64 {
+        100. * (-(PI / 200.) * (PI / 200.) * t).exp() * (PI * x / 200.).sin()
+    }
+
+    fn make_model(integrator: TimeIntegrator) -> DifferentialModel {
+        DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("100*sin(PI*x/200)").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            200.,
+            DifferentialOptions {
+                integrator,
+                ..DifferentialOptions::new(100, 1.)
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn value_range_flags_blow_up() {
+        let mut model = make_model(TimeIntegrator::ForwardEuler);
+        let (min, max) = model.value_range();
+        assert!(min.abs() < 1e-9 && (max - 100.).abs() < 0.1);
+
+        // Way past the CFL limit, the scheme has to blow up
+        model.time_step = 100.;
+        model.run_steps(1000).unwrap();
+        let (min, max) = model.value_range();
+        assert!(!min.is_finite() || !max.is_finite());
+        assert!(min.is_nan() || min <= max);
+    }
+
+    #[test]
+    fn follows_analytic_decay() {
+        for integrator in [TimeIntegrator::ForwardEuler, TimeIntegrator::RK4] {
+            let mut model = make_model(integrator);
+            model.run_steps(500).unwrap();
+
+            let t = model.get_elapsed_time();
+            for (x, u) in model.get_node_positions().iter().zip(model.get_cur_nodes()) {
+                assert!(
+                    (u - decay(*x, t)).abs() < 1e-2,
+                    "{:?} at x = {}",
+                    integrator,
+                    x
+                );
+            }
+        }
+    }
+
+    fn make_unit_model(node_count: u32, time_step: f64) -> DifferentialModel {
+        try_unit_model(node_count, time_step).unwrap()
+    }
+
+    fn try_unit_model(node_count: u32, time_step: f64) -> Result<DifferentialModel, ModelError> {
+        DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("sin(PI*x)").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            1.,
+            DifferentialOptions::new(node_count, time_step),
+        )
+    }
+
+    #[test]
+    fn time_dependent_coefficient_follows_analytic_decay() {
+        // With a² = 1 + t the sine decays as exp(-π²(t + t²/2))
+        let mut model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("sin(PI*x)").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("sqrt(1+t)").unwrap())),
+            1.,
+            DifferentialOptions {
+                integrator: TimeIntegrator::RK4,
+                ..DifferentialOptions::new(41, 1e-4)
+            },
+        )
+        .unwrap();
+        model.run_steps(1000).unwrap();
+
+        let t = model.get_elapsed_time();
+        let decay = (-PI * PI * (t + t * t / 2.)).exp();
+        for (x, u) in model.get_node_positions().iter().zip(model.get_cur_nodes()) {
+            assert!((u - decay * (PI * x).sin()).abs() < 1e-3, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn too_few_nodes_is_an_error() {
+        for node_count in 0..3 {
+            assert!(matches!(
+                try_unit_model(node_count, 1e-3),
+                Err(ModelError::Parameter(_))
+            ));
+        }
+        let mut model = try_unit_model(3, 1e-3).unwrap();
+        model.run_steps(10).unwrap();
+        assert_eq!(model.get_cur_nodes().len(), 3);
+    }
+
+    #[test]
+    fn max_error_within_cfl_tolerance() {
+        let h = 1. / 20.;
+        // Forward Euler is stable for dt <= h² / 2a², stay well inside that bound
+        let dt = 0.4 * h * h;
+        let mut model = make_unit_model(21, dt);
+        model.run_steps(200).unwrap();
+
+        let t = model.get_elapsed_time();
+        let max_error = model
+            .get_node_positions()
+            .iter()
+            .zip(model.get_cur_nodes())
+            .map(|(x, u)| (u - (-PI * PI * t).exp() * (PI * x).sin()).abs())
+            .fold(0., f64::max);
+        assert!(max_error < dt + h * h, "max error {}", max_error);
+    }
+
+    #[test]
+    fn seeking_fails_instead_of_stopping_short() {
+        let mut model = make_unit_model(11, 1e-3);
+        model.set_time(0.01).unwrap();
+        assert!((model.get_elapsed_time() - 0.01).abs() < 1e-9);
+
+        for time in [-1., f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                model.set_time(time),
+                Err(ModelError::Parameter(_))
+            ));
+        }
+        // Too many steps to replay, refused before anything is stepped
+        assert!(matches!(
+            model.set_time(1e-3 * (MAX_SEEK_STEPS + 1) as f64),
+            Err(ModelError::Parameter(_))
+        ));
+        assert!((model.get_elapsed_time() - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reset_restores_initial_nodes() {
+        let mut model = make_unit_model(21, 1e-3);
+        let initial = model.get_cur_nodes().to_vec();
+        model.run_steps(10).unwrap();
+        assert_ne!(model.get_cur_nodes(), &initial[..]);
+
+        model.reset().unwrap();
+        assert_eq!(model.get_cur_nodes(), &initial[..]);
+        assert_eq!(model.get_elapsed_time(), 0.);
+    }
+
+    #[test]
+    fn sampled_start_matches_expression() {
+        let samples = (0..=100)
+            .map(|i| {
+                let x = i as f64 / 100.;
+                (x, (PI * x).sin())
+            })
+            .collect();
+        let mut model = make_unit_model(21, 1e-3);
+        model.starting_conditions = InitialCondition::Samples(samples);
+        model.reset().unwrap();
+
+        let expected = make_unit_model(21, 1e-3);
+        for (u, v) in model.get_cur_nodes().iter().zip(expected.get_cur_nodes()) {
+            assert!((u - v).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn steady_state_flux_goes_through() {
+        let mut model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+            exmex::parse::<f64>("100+0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            1.,
+            DifferentialOptions::new(21, 1e-3),
+        )
+        .unwrap();
+        model.run_steps(5000).unwrap();
+
+        // The profile is linear, 100 per unit length flows in on the left and out on the right
+        let (left, right) = model.boundary_flux().unwrap();
+        assert!((left - 100.).abs() < 0.5, "left {}", left);
+        assert!((right + 100.).abs() < 0.5, "right {}", right);
+    }
+
+    #[test]
+    fn step_delta_settles_at_steady_state() {
+        let mut model = make_unit_model(21, 1e-3);
+        assert_eq!(model.max_step_delta(), f64::INFINITY);
+        model.run_step().unwrap();
+        let first = model.max_step_delta();
+        assert!(first > 0.);
+
+        model.run_steps(5000).unwrap();
+        assert!(model.max_step_delta() < first * 1e-3);
+        model.reset().unwrap();
+        assert_eq!(model.max_step_delta(), f64::INFINITY);
+    }
+
+    #[test]
+    fn two_materials_steady_state() {
+        // Conductivity 1 up to the interface and 4 past it, which falls between two nodes
+        let interface = 0.53;
+        let mut model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+            exmex::parse::<f64>("100+0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Piecewise(vec![
+                (interface, exmex::parse::<f64>("1+0*x").unwrap()),
+                (1., exmex::parse::<f64>("2+0*x").unwrap()),
+            ]),
+            1.,
+            DifferentialOptions::new(21, 2.5e-4),
+        )
+        .unwrap();
+        model.run_steps(20000).unwrap();
+
+        // Linear in each material with a kink at the interface, the same flux through both
+        let flux = 100. / (interface + (1. - interface) / 4.);
+        let steady = |x: f64| {
+            if x <= interface {
+                100. - flux * x
+            } else {
+                flux * (1. - x) / 4.
+            }
+        };
+        for (x, u) in model.get_node_positions().iter().zip(model.get_cur_nodes()) {
+            assert!((u - steady(*x)).abs() < 1e-3, "x = {}: {}", x, u);
+        }
+        let (left, right) = model.boundary_flux().unwrap();
+        assert!((left - flux).abs() / flux < 0.05);
+        assert!((right + flux).abs() / flux < 0.05);
+    }
+
+    #[test]
+    fn graded_material_steady_flux() {
+        // a² = 1 up to the middle, then 1 + x
+        let mut model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+            exmex::parse::<f64>("100+0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Piecewise(vec![
+                (0.5, exmex::parse::<f64>("1+0*x").unwrap()),
+                (1., exmex::parse::<f64>("sqrt(1+x)").unwrap()),
+            ]),
+            1.,
+            DifferentialOptions::new(21, 2.5e-4),
+        )
+        .unwrap();
+        model.run_steps(20000).unwrap();
+
+        // The flux is the temperature drop over the total resistance, the integral of 1/a²
+        let flux = 100. / (0.5 + (2f64 / 1.5).ln());
+        let (left, right) = model.boundary_flux().unwrap();
+        assert!((left - flux).abs() / flux < 0.05, "left {}", left);
+        assert!((right + flux).abs() / flux < 0.05, "right {}", right);
+    }
+
+    #[test]
+    fn periodic_pulse_crosses_seam() {
+        let mut model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(
+                exmex::parse::<f64>("exp(0-(x-0.9)^2/0.005)").unwrap(),
+            )),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+            1.,
+            DifferentialOptions {
+                boundaries: BoundaryKind::Periodic.into(),
+                ..DifferentialOptions::new(101, 2e-5)
+            },
+        )
+        .unwrap();
+        // The last node is the first one again, leave it out of the total heat
+        let heat = |nodes: &[f64]| nodes[..nodes.len() - 1].iter().sum::<f64>();
+        let initial_heat = heat(model.get_cur_nodes());
+        model.run_steps(2000).unwrap();
+
+        let nodes = model.get_cur_nodes();
+        assert_eq!(nodes[0], nodes[nodes.len() - 1]);
+        assert!((heat(nodes) - initial_heat).abs() < 1e-9 * initial_heat);
+        // Heat reached the other side of the seam and the field stays smooth across it
+        assert!(nodes[5] > 1e-3);
+        let jump = (nodes[1] - nodes[0])
+            .abs()
+            .max((nodes[0] - nodes[nodes.len() - 2]).abs());
+        assert!(jump < 0.05, "jump {}", jump);
+    }
+
+    #[test]
+    fn insulated_rod_converges_second_order() {
+        // u = exp(-PI²·t)·cos(PI·x) has no flux through either edge of the unit rod
+        let error = |node_count: u32| {
+            let h = 1. / (node_count - 1) as f64;
+            let time_step = 0.1 * h * h;
+            let mut model = DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("cos(PI*x)").unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                1.,
+                DifferentialOptions {
+                    boundaries: BoundaryKind::Neumann.into(),
+                    ..DifferentialOptions::new(node_count, time_step)
+                },
+            )
+            .unwrap();
+            model.set_time(0.1).unwrap();
+            let t = model.get_elapsed_time();
+            model
+                .get_node_positions()
+                .iter()
+                .zip(model.get_cur_nodes())
+                .map(|(x, u)| (u - (-PI * PI * t).exp() * (PI * x).cos()).abs())
+                .fold(0., f64::max)
+        };
+
+        let (coarse, fine) = (error(11), error(21));
+        let order = (coarse / fine).log2();
+        assert!(
+            order > 1.8,
+            "errors {} and {}, order {}",
+            coarse,
+            fine,
+            order
+        );
+    }
+
+    #[test]
+    fn fourth_order_stencil_converges_fourth_order() {
+        // RK4 with a small step, so the error is the spatial one
+        let error = |node_count: u32| {
+            let mut model = DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("sin(PI*x)").unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                1.,
+                DifferentialOptions {
+                    integrator: TimeIntegrator::RK4,
+                    spatial_order: SpatialOrder::Fourth,
+                    ..DifferentialOptions::new(node_count, 1e-4)
+                },
+            )
+            .unwrap();
+            model.set_time(0.1).unwrap();
+            let t = model.get_elapsed_time();
+            model
+                .get_node_positions()
+                .iter()
+                .zip(model.get_cur_nodes())
+                .map(|(x, u)| (u - (-PI * PI * t).exp() * (PI * x).sin()).abs())
+                .fold(0., f64::max)
+        };
+
+        let errors = [error(11), error(21), error(41)];
+        for pair in errors.windows(2) {
+            let order = (pair[0] / pair[1]).log2();
+            assert!(order > 3.5, "errors {:?}, order {}", errors, order);
+        }
+    }
+
+    #[test]
+    fn mixed_edges_heat_up_to_the_dirichlet_value() {
+        let make = |left, right| {
+            DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+                exmex::parse::<f64>("100+0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                1.,
+                DifferentialOptions {
+                    boundaries: Boundaries { left, right },
+                    ..DifferentialOptions::new(11, 4e-3)
+                },
+            )
+        };
+
+        // Held at 100 on the left and insulated on the right, the whole rod ends up at 100
+        let mut model = make(BoundaryKind::Dirichlet, BoundaryKind::Neumann).unwrap();
+        model.run_steps(2500).unwrap();
+        let nodes = model.get_cur_nodes();
+        assert_eq!(nodes[0], 100.);
+        assert!(nodes.iter().all(|u| (u - 100.).abs() < 1e-3), "{:?}", nodes);
+        let (_, right) = model.boundary_flux().unwrap();
+        assert_eq!(right, 0.);
+
+        assert!(matches!(
+            make(BoundaryKind::Periodic, BoundaryKind::Dirichlet),
+            Err(ModelError::Parameter(_))
+        ));
+    }
+
+    #[test]
+    fn non_finite_start_is_an_error() {
+        let make = |start: &str| {
+            DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>(start).unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                1.,
+                DifferentialOptions {
+                    boundaries: BoundaryKind::Neumann.into(),
+                    ..DifferentialOptions::new(5, 1e-3)
+                },
+            )
+        };
+        match make("sqrt(x-0.5)") {
+            Err(ModelError::Eval(e)) => assert_eq!(e, "initial condition produced NaN at x = 0"),
+            _ => panic!("sqrt of a negative should not start"),
+        }
+        assert!(matches!(make("1/x"), Err(ModelError::Eval(_))));
+        assert!(make("sqrt(x)").is_ok());
+    }
+
+    #[test]
+    fn reseeding_edges_keeps_the_interior() {
+        let mut model = make_unit_model(11, 1e-3);
+        model.run_steps(10).unwrap();
+        let before = model.get_cur_nodes().to_vec();
+
+        let (left, right) = (
+            exmex::parse::<f64>("50+t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+        );
+        model.reseed_edges(left, right).unwrap();
+        let nodes = model.get_cur_nodes();
+        assert!((nodes[0] - 50.01).abs() < 1e-12);
+        assert_eq!(nodes[1..], before[1..]);
+        assert!((model.get_elapsed_time() - 0.01).abs() < 1e-12);
+
+        // The new edge keeps being applied as the model goes on
+        model.run_step().unwrap();
+        assert!((model.get_cur_nodes()[0] - 50.011).abs() < 1e-12);
+
+        // Edges that can't be evaluated leave the model as it was
+        let (left, right) = (
+            exmex::parse::<f64>("sqrt(0-1-t)").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+        );
+        assert!(model.reseed_edges(left, right).is_err());
+        model.run_step().unwrap();
+        assert!((model.get_cur_nodes()[0] - 50.012).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ramped_edges_start_from_the_start_conditions() {
+        let make = |ramp_steps| {
+            DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+                exmex::parse::<f64>("100+0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("1+0*x").unwrap())),
+                1.,
+                DifferentialOptions {
+                    integrator: TimeIntegrator::RK4,
+                    ramp_steps,
+                    ..DifferentialOptions::new(11, 0.0068)
+                },
+            )
+            .unwrap()
+        };
+        // Close to the RK4 limit the cold start against a hot edge rings below zero
+        let mut model = make(0);
+        assert_eq!(model.get_cur_nodes()[0], 100.);
+        let mut lowest = 0f64;
+        for _ in 0..10 {
+            model.run_step().unwrap();
+            lowest = lowest.min(model.get_cur_nodes().iter().cloned().fold(0., f64::min));
+        }
+        assert!(lowest < -0.05, "lowest {}", lowest);
+
+        let mut model = make(10);
+        assert_eq!(model.get_cur_nodes()[0], 0.);
+        let mut lowest = 0f64;
+        for step in 1..=12 {
+            model.run_step().unwrap();
+            let expected = 100. * (step as f64 / 10.).min(1.);
+            assert!(
+                (model.get_cur_nodes()[0] - expected).abs() < 1e-9,
+                "step {}",
+                step
+            );
+            lowest = lowest.min(model.get_cur_nodes().iter().cloned().fold(0., f64::min));
+        }
+        assert!(lowest > -1e-3, "lowest {}", lowest);
+        model.reset().unwrap();
+        assert_eq!(model.get_cur_nodes()[0], 0.);
+    }
+
+    #[test]
+    fn set_nodes_keeps_the_time() {
+        let mut model = make_unit_model(11, 1e-3);
+        model.run_steps(10).unwrap();
+        let flat = vec![1.; 11];
+        model.set_nodes(&flat).unwrap();
+        assert_eq!(model.get_cur_nodes(), &flat[..]);
+        assert!((model.get_elapsed_time() - 0.01).abs() < 1e-12);
+        // The edges are held at 0 again by the next step
+        model.run_step().unwrap();
+        assert_eq!(model.get_cur_nodes()[0], 0.);
+        assert!(model.get_cur_nodes()[5] > 0.99);
+
+        let before = model.get_cur_nodes().to_vec();
+        assert!(matches!(
+            model.set_nodes(&[1.; 10]),
+            Err(ModelError::Parameter(_))
+        ));
+        let mut nan = flat.clone();
+        nan[3] = f64::NAN;
+        assert!(model.set_nodes(&nan).is_err());
+        assert_eq!(model.get_cur_nodes(), &before[..]);
+    }
+
+    #[test]
+    fn details_show_the_expressions_as_written() {
+        let model = DifferentialModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("100*sin(PI*x/200)").unwrap())),
+            // Padded by the model creator so exmex takes t
+            exmex::parse::<f64>("(50)+t-t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            Coefficient::Piecewise(vec![
+                (100., exmex::parse::<f64>("1+0*x").unwrap()),
+                (200., exmex::parse::<f64>("(2)+x-x").unwrap()),
+            ]),
+            200.,
+            DifferentialOptions {
+                integrator: TimeIntegrator::RK4,
+                ..DifferentialOptions::new(100, 1.)
+            },
+        )
+        .unwrap();
+        let details: HashMap<String, String> = model.details().into_iter().collect();
+        assert_eq!(details["Start"], "100*sin(PI*x/200)");
+        assert_eq!(details["Left edge"], "50");
+        assert_eq!(details["Right edge"], "0*t");
+        assert_eq!(details["a"], "1+0*x up to x = 100; 2 beyond");
+        assert_eq!(details["Integrator"], "RK4");
+        assert!(!details.contains_key("Edge ramp steps"));
+    }
+
+    #[test]
+    fn faces_reach_the_divergence_form_steady_state() {
+        // (a²·u_x)_x = 0 with a² = 1 + x, u(0) = 0 and u(1) = 1 is u = ln(1 + x) / ln 2,
+        // a²·u_xx = 0 is the straight line instead
+        let steady = |stencil| {
+            let mut model = DifferentialModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("x").unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("1+0*t").unwrap(),
+                Coefficient::Expr(Box::new(exmex::parse::<f64>("sqrt(1+x)").unwrap())),
+                1.,
+                DifferentialOptions {
+                    coefficient_stencil: stencil,
+                    ..DifferentialOptions::new(21, 5e-4)
+                },
+            )
+            .unwrap();
+            model.run_steps(8000).unwrap();
+            model
+        };
+        let exact = |x: f64| (1. + x).ln() / 2f64.ln();
+
+        let faces = steady(CoefficientStencil::Faces);
+        for (x, u) in faces.get_node_positions().iter().zip(faces.get_cur_nodes()) {
+            assert!((u - exact(*x)).abs() < 1e-4, "x = {}: {}", x, u);
+        }
+        let nodes = steady(CoefficientStencil::Nodes);
+        assert!((nodes.temperature_at(0.5).unwrap() - 0.5).abs() < 1e-3);
+    }
 }
@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum ModelError {
+    Eval(String),
+    Solver(String),
+    /// A constructor argument the model can't work with
+    Parameter(String),
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Eval(e) => format!("EVAL - {}", e),
+                Self::Solver(e) => format!("SOLVER - {}", e),
+                Self::Parameter(e) => format!("PARAMETER - {}", e),
+            }
+        )
+    }
+}
+
+impl From<exmex::ExError> for ModelError {
+    fn from(e: exmex::ExError) -> Self {
+        Self::Eval(e.to_string())
+    }
+}
@@ -1,4 +1,8 @@
 pub mod analytic;
+pub mod compare;
 pub mod differential;
+pub mod error;
+#[allow(clippy::module_inception)]
 pub mod model;
+pub mod series;
 pub mod system;
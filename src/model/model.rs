@@ -1,9 +1,471 @@
+/// Upper bound on the steps `set_time` is allowed to take when replaying a model
+pub const MAX_SEEK_STEPS: usize = 10_000_000;
+/// Fewest nodes a model can have: both edges and at least one node between them
+pub const MIN_NODE_COUNT: u32 = 3;
+
+use crate::model::error::ModelError;
+use exmex::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Names of the units a model is given in, only used for display. Empty means dimensionless
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Units {
+    /// e.g. `m` or `cm`
+    pub length: String,
+    /// e.g. `K` or `°C`
+    pub temperature: String,
+    /// e.g. `s` or `h`
+    pub time: String,
+    /// Unit of a², e.g. `m²/s`
+    pub diffusivity: String,
+}
+
+impl Units {
+    /// ` (unit)` to put after a column or axis name, nothing when dimensionless
+    pub fn bracketed(unit: &str) -> String {
+        if unit.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", unit)
+        }
+    }
+
+    /// ` unit` to put after a value, nothing when dimensionless
+    pub fn suffix(unit: &str) -> String {
+        if unit.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", unit)
+        }
+    }
+}
+
 pub trait Model: Send {
-    fn reset(&mut self);
-    fn run_step(&mut self);
+    fn reset(&mut self) -> Result<(), ModelError>;
+    fn run_step(&mut self) -> Result<(), ModelError>;
+
+    /// Moves the model to the first step at or after `time`.
+    /// By default this resets the model and replays it step by step,
+    /// so numeric models re-run everything from t = 0 and fail past `MAX_SEEK_STEPS` steps.
+    fn set_time(&mut self, time: f64) -> Result<(), ModelError> {
+        check_replay(time, self.get_time_step())?;
+        self.reset()?;
+        let mut steps = 0;
+        while self.get_elapsed_time() < time && steps <= MAX_SEEK_STEPS {
+            self.run_step()?;
+            steps += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether the model is a closed form of t, so `set_time` and `exact_at`
+    /// reach any time without stepping through the ones before it
+    fn is_closed_form(&self) -> bool {
+        false
+    }
+
+    /// Swaps in new edge conditions and writes them at the current time into the edge nodes,
+    /// leaving the interior and the time alone. Only numeric models have edge conditions,
+    /// the others return an error
+    fn reseed_edges(&mut self, _left: FlatEx<f64>, _right: FlatEx<f64>) -> Result<(), ModelError> {
+        Err(ModelError::Parameter(
+            "only numeric models have edge conditions".to_owned(),
+        ))
+    }
+
+    /// Replaces the nodes with `nodes`, keeping the elapsed time, as if the model had got there
+    /// itself. Only models that step from their nodes can take them, the others return an error
+    fn set_nodes(&mut self, _nodes: &[f64]) -> Result<(), ModelError> {
+        Err(ModelError::Parameter(
+            "only numeric models can take nodes from another model".to_owned(),
+        ))
+    }
+
+    /// Runs `n` steps and returns the nodes after each one
+    fn run_steps(&mut self, n: usize) -> Result<Vec<Vec<f64>>, ModelError> {
+        (0..n)
+            .map(|_| {
+                self.run_step()?;
+                Ok(self.get_cur_nodes().to_vec())
+            })
+            .collect()
+    }
 
     fn get_elapsed_time(&self) -> f64;
+    /// Largest change of a node during the last step, infinite before the first one
+    fn max_step_delta(&self) -> f64;
+    fn get_units(&self) -> &Units;
+    fn set_units(&mut self, units: Units);
+    /// A copy with the same expressions and parameters, `reset` it to start over from t = 0
+    fn clone_box(&self) -> Box<dyn Model>;
     fn get_length(&self) -> &f64;
     fn get_cur_nodes(&self) -> &[f64];
     fn get_node_step(&self) -> &f64;
+    fn get_time_step(&self) -> f64;
+
+    fn get_node_count(&self) -> usize {
+        self.get_cur_nodes().len()
+    }
+
+    /// Position of every node in `[0, length]`, evenly spaced by default
+    fn get_node_positions(&self) -> Vec<f64> {
+        (0..self.get_node_count())
+            .map(|i| i as f64 * self.get_node_step())
+            .collect()
+    }
+
+    /// (min, max, mean) of the current nodes
+    fn temperature_stats(&self) -> (f64, f64, f64) {
+        let nodes = self.get_cur_nodes();
+        if nodes.is_empty() {
+            return (0., 0., 0.);
+        }
+        let (min, max, sum) = nodes.par_iter().map(|&t| (t, t, t)).reduce(
+            || (f64::INFINITY, f64::NEG_INFINITY, 0.),
+            |a, b| (a.0.min(b.0), a.1.max(b.1), a.2 + b.2),
+        );
+        (min, max, sum / nodes.len() as f64)
+    }
+
+    /// (min, max) of the current nodes, NaN on both ends if any node is NaN
+    /// and `(0, 0)` without nodes, so the range is never inverted
+    fn value_range(&self) -> (f64, f64) {
+        // The min and max of the stats skip NaN, but it makes the sum and so the mean NaN
+        let (min, max, mean) = self.temperature_stats();
+        if mean.is_nan() {
+            (f64::NAN, f64::NAN)
+        } else {
+            (min, max)
+        }
+    }
+
+    /// Integral of the temperature over the rod by the trapezoidal rule,
+    /// the heat it holds per unit of heat capacity
+    fn total_heat(&self) -> f64 {
+        let positions = self.get_node_positions();
+        positions
+            .windows(2)
+            .zip(self.get_cur_nodes().windows(2))
+            .map(|(x, u)| (x[1] - x[0]) * (u[0] + u[1]) / 2.)
+            .sum()
+    }
+
+    /// The expressions and parameters the model was built from as `(label, value)`, for display
+    fn details(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Heat flowing into the rod through the (left, right) edge,
+    /// `None` for models that do not know the coefficient
+    fn boundary_flux(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Linearly interpolated temperature at position `x`, `None` outside of `[0, length]`
+    fn temperature_at(&self, x: f64) -> Option<f64> {
+        interpolate_nodes(self.get_cur_nodes(), *self.get_length(), x)
+    }
+
+    /// Temperature at position `x` and any time `t` without stepping there,
+    /// `None` outside of `[0, length]` and for models that aren't a closed form of t
+    fn exact_at(&self, _x: f64, _t: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Linearly interpolates evenly spaced `nodes` covering `[0, length]` at position `x`
+pub fn interpolate_nodes(nodes: &[f64], length: f64, x: f64) -> Option<f64> {
+    if nodes.is_empty() || !(0. ..=length).contains(&x) {
+        return None;
+    }
+    if nodes.len() == 1 {
+        return Some(nodes[0]);
+    }
+
+    let pos = x / length * (nodes.len() - 1) as f64;
+    let left = (pos.floor() as usize).min(nodes.len() - 1);
+    let right = (left + 1).min(nodes.len() - 1);
+    let t = pos - left as f64;
+    Some(nodes[left] + (nodes[right] - nodes[left]) * t)
+}
+
+/// Linearly interpolates `nodes` at arbitrary increasing `positions` at position `x`
+pub fn interpolate_positions(positions: &[f64], nodes: &[f64], x: f64) -> Option<f64> {
+    let (first, last) = (*positions.first()?, *positions.last()?);
+    if !(first..=last).contains(&x) {
+        return None;
+    }
+    if nodes.len() == 1 {
+        return Some(nodes[0]);
+    }
+
+    let right = positions
+        .partition_point(|&p| p < x)
+        .max(1)
+        .min(nodes.len() - 1);
+    let left = right - 1;
+    let span = positions[right] - positions[left];
+    if span <= 0. {
+        return Some(nodes[left]);
+    }
+    let t = (x - positions[left]) / span;
+    Some(nodes[left] + (nodes[right] - nodes[left]) * t)
+}
+
+/// Rejects nodes handed to `Model::set_nodes` that don't fit a grid of `node_count` nodes
+pub fn check_set_nodes(nodes: &[f64], node_count: usize) -> Result<(), ModelError> {
+    if nodes.len() != node_count {
+        return Err(ModelError::Parameter(format!(
+            "expected {} nodes, got {}",
+            node_count,
+            nodes.len()
+        )));
+    }
+    match nodes.iter().position(|u| !u.is_finite()) {
+        Some(i) => Err(ModelError::Parameter(format!("node {} is {}", i, nodes[i]))),
+        None => Ok(()),
+    }
+}
+
+/// Rejects times a model can't be moved to
+pub fn check_seek_time(time: f64) -> Result<(), ModelError> {
+    if time.is_finite() && time >= 0. {
+        Ok(())
+    } else {
+        Err(ModelError::Parameter(format!(
+            "cannot seek to t = {}",
+            time
+        )))
+    }
+}
+
+/// Rejects times that take more than `MAX_SEEK_STEPS` steps of `time_step` to replay to
+pub fn check_replay(time: f64, time_step: f64) -> Result<(), ModelError> {
+    check_seek_time(time)?;
+    if time / time_step > MAX_SEEK_STEPS as f64 {
+        return Err(ModelError::Parameter(format!(
+            "t = {} takes more than {} steps of {} to replay",
+            time, MAX_SEEK_STEPS, time_step
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects node counts the constructors can't build a grid from
+pub fn check_node_count(node_count: u32) -> Result<(), ModelError> {
+    if node_count < MIN_NODE_COUNT {
+        Err(ModelError::Parameter(format!(
+            "node count {} is below the minimum of {}",
+            node_count, MIN_NODE_COUNT
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Fails on the first node of a freshly built model that isn't finite, naming its position,
+/// instead of letting a NaN spread through every later step
+pub fn check_initial_nodes(positions: &[f64], nodes: &[f64]) -> Result<(), ModelError> {
+    match positions.iter().zip(nodes).find(|(_, u)| !u.is_finite()) {
+        Some((x, u)) => Err(ModelError::Eval(format!(
+            "initial condition produced {} at x = {}",
+            u, x
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Evaluates edge conditions at `time`, failing on one that isn't finite
+/// so swapping them into a running model can't poison it
+pub fn eval_edges(
+    left: &FlatEx<f64>,
+    right: &FlatEx<f64>,
+    time: f64,
+) -> Result<(f64, f64), ModelError> {
+    let (l, r) = (left.eval(&[time])?, right.eval(&[time])?);
+    for (side, u) in [("left", l), ("right", r)] {
+        if !u.is_finite() {
+            return Err(ModelError::Eval(format!(
+                "{} edge condition produced {} at t = {}",
+                side, u, time
+            )));
+        }
+    }
+    Ok((l, r))
+}
+
+/// Infinity norm of `new - old`
+pub fn max_abs_difference(new: &[f64], old: &[f64]) -> f64 {
+    new.par_iter()
+        .zip(old.par_iter())
+        .map(|(a, b)| (a - b).abs())
+        .reduce(|| 0., f64::max)
+}
+
+/// `-a²·du/dx` at the left edge and `a²·du/dx` at the right one, so both are positive
+/// when heat flows in. `h` and `a2` are the spacing and a² at the (left, right) edge
+pub fn edge_flux(nodes: &[f64], h: (f64, f64), a2: (f64, f64)) -> (f64, f64) {
+    let n = nodes.len();
+    if n < 2 {
+        return (0., 0.);
+    }
+    (
+        -a2.0 * (nodes[1] - nodes[0]) / h.0,
+        a2.1 * (nodes[n - 1] - nodes[n - 2]) / h.1,
+    )
+}
+
+/// Whether an expression uses the elapsed time `t`
+pub fn uses_time(expr: &exmex::FlatEx<f64>) -> bool {
+    expr.var_names().iter().any(|v| v == "t")
+}
+
+/// The text an expression was written as, without the `+v-v` the model creator pads it with
+/// so exmex takes a variable the expression doesn't use
+pub fn expression_text(expr: &exmex::FlatEx<f64>) -> String {
+    let mut text = expr.unparse();
+    let mut padded = false;
+    while let Some(rest) = ["n", "t", "x"]
+        .iter()
+        .find_map(|v| text.strip_suffix(&format!("+{}-{}", v, v)))
+    {
+        text = rest;
+        padded = true;
+    }
+    match text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) if padded => inner.to_owned(),
+        _ => text.to_owned(),
+    }
+}
+
+/// Evaluates a coefficient of x that may also use t.
+/// exmex takes variables in alphabetical order, so one of both is evaluated at (t, x)
+pub fn eval_coefficient(expr: &exmex::FlatEx<f64>, x: f64, t: f64) -> Result<f64, ModelError> {
+    let res = match (expr.var_names().len(), uses_time(expr)) {
+        (2, _) => expr.eval(&[t, x]),
+        (1, true) => expr.eval(&[t]),
+        _ => expr.eval(&[x]),
+    };
+    Ok(res?)
+}
+
+/// The `a` of `u_t = a²·u_xx`
+#[derive(Clone)]
+pub enum Coefficient {
+    /// An expression of x, or of x and t, boxed as it is far larger than the segments
+    Expr(Box<exmex::FlatEx<f64>>),
+    /// `(x_boundary, a(x))` segments sorted by x, each `a` holds up to its boundary
+    /// and the last one also past it. Models a rod made of several materials
+    Piecewise(Vec<(f64, exmex::FlatEx<f64>)>),
+}
+
+impl Coefficient {
+    /// Whether `a` changes over time, so a² can't be evaluated once up front
+    pub fn depends_on_time(&self) -> bool {
+        match self {
+            Self::Expr(expr) => uses_time(expr),
+            Self::Piecewise(segments) => segments.iter().any(|(_, a)| uses_time(a)),
+        }
+    }
+
+    /// The expression, or every segment with the boundary it holds up to
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Expr(expr) => expression_text(expr),
+            Self::Piecewise(segments) => segments
+                .iter()
+                .enumerate()
+                .map(|(i, (boundary, a))| {
+                    if i + 1 == segments.len() {
+                        format!("{} beyond", expression_text(a))
+                    } else {
+                        format!("{} up to x = {}", expression_text(a), boundary)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+
+    pub fn eval(&self, x: f64, t: f64) -> Result<f64, ModelError> {
+        match self {
+            Self::Expr(expr) => eval_coefficient(expr, x, t),
+            Self::Piecewise(segments) => match segments
+                .iter()
+                .find(|(boundary, _)| x <= *boundary)
+                .or_else(|| segments.last())
+            {
+                Some((_, a)) => eval_coefficient(a, x, t),
+                None => Ok(0.),
+            },
+        }
+    }
+
+    /// a² of the material between `x0` and `x1` as resistances in series,
+    /// the harmonic mean across an interface, so the flux through it stays continuous.
+    /// Each segment's `a` is taken at the middle of its part of the interval.
+    /// `None` unless the coefficient is piecewise
+    pub fn interval_a2(&self, x0: f64, x1: f64, t: f64) -> Result<Option<f64>, ModelError> {
+        let segments = match self {
+            Self::Piecewise(segments) if !segments.is_empty() => segments,
+            _ => return Ok(None),
+        };
+        let mut resistance = 0.;
+        let mut start = x0;
+        for (i, (boundary, a)) in segments.iter().enumerate() {
+            let end = if i == segments.len() - 1 {
+                x1
+            } else {
+                boundary.min(x1)
+            };
+            if end > start {
+                let a = eval_coefficient(a, (start + end) / 2., t)?;
+                resistance += (end - start) / (a * a);
+                start = end;
+            }
+        }
+        Ok(Some((x1 - x0) / resistance))
+    }
+}
+
+/// Temperature profile a numeric model starts from
+#[derive(Clone)]
+pub enum InitialCondition {
+    /// An expression of x, boxed as it is far larger than the samples
+    Expr(Box<exmex::FlatEx<f64>>),
+    /// Measured `(x, temperature)` pairs sorted by x, linearly interpolated
+    /// and held constant past either end
+    Samples(Vec<(f64, f64)>),
+}
+
+impl InitialCondition {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Expr(expr) => expression_text(expr),
+            Self::Samples(samples) => format!("{} measured samples", samples.len()),
+        }
+    }
+
+    pub fn eval(&self, x: f64) -> Result<f64, ModelError> {
+        match self {
+            Self::Expr(expr) => Ok(expr.eval(&[x])?),
+            Self::Samples(samples) => {
+                let (first, last) = match (samples.first(), samples.last()) {
+                    (Some(first), Some(last)) => (*first, *last),
+                    _ => return Ok(0.),
+                };
+                if x <= first.0 {
+                    return Ok(first.1);
+                }
+                if x >= last.0 {
+                    return Ok(last.1);
+                }
+                let right = samples.partition_point(|&(p, _)| p < x);
+                let ((x0, t0), (x1, t1)) = (samples[right - 1], samples[right]);
+                Ok(t0 + (t1 - t0) * (x - x0) / (x1 - x0))
+            }
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use crate::model::error::ModelError;
+use crate::model::model::*;
+use exmex::prelude::*;
+use rayon::prelude::*;
+
+type T = f64;
+
+/// An exact solution given as `sum_{n=1}^{N} term(n, t, x)`, e.g. the Fourier series
+/// of an arbitrary initial condition
+#[derive(Clone)]
+pub struct AnalyticSeriesModel {
+    /// An expression of any of n, t and x
+    term: exmex::FlatEx<T>,
+    term_count: u32,
+
+    length: T,
+    time_step: T,
+    node_step: T,
+    nodes: Vec<T>,
+    cur_time_step: u32,
+    node_count: u32,
+    max_step_delta: T,
+    units: Units,
+}
+
+impl AnalyticSeriesModel {
+    pub fn new(
+        term: exmex::FlatEx<T>,
+        term_count: u32,
+        length: T,
+        node_count: u32,
+        time_step: T,
+    ) -> Result<Self, ModelError> {
+        check_node_count(node_count)?;
+        if term_count == 0 {
+            return Err(ModelError::Parameter(
+                "a series needs at least one term".to_owned(),
+            ));
+        }
+        if let Some(v) = term
+            .var_names()
+            .iter()
+            .find(|v| !["n", "t", "x"].contains(&v.as_str()))
+        {
+            return Err(ModelError::Parameter(format!(
+                "unknown variable {} in the series term, expected n, t and x",
+                v
+            )));
+        }
+
+        let mut res = Self {
+            term,
+            term_count,
+            length,
+            time_step,
+            node_step: length / (node_count - 1) as T,
+            nodes: vec![],
+            cur_time_step: 0,
+            node_count,
+            max_step_delta: T::INFINITY,
+            units: Units::default(),
+        };
+        res.nodes = res.eval_nodes()?;
+        check_initial_nodes(&res.get_node_positions(), &res.nodes)?;
+        Ok(res)
+    }
+
+    fn sum_at(&self, t: T, x: T) -> Result<T, ModelError> {
+        let mut sum = 0.;
+        for n in 1..=self.term_count {
+            // exmex takes the variables in alphabetical order
+            let args: Vec<T> = self
+                .term
+                .var_names()
+                .iter()
+                .map(|v| match v.as_str() {
+                    "n" => n as T,
+                    "t" => t,
+                    _ => x,
+                })
+                .collect();
+            sum += self.term.eval(&args)?;
+        }
+        Ok(sum)
+    }
+
+    fn eval_nodes(&self) -> Result<Vec<T>, ModelError> {
+        let time = self.cur_time_step as T * self.time_step;
+        (0..self.node_count)
+            .into_par_iter()
+            .map(|i| self.sum_at(time, self.node_step * i as T))
+            .collect()
+    }
+}
+
+impl Model for AnalyticSeriesModel {
+    fn get_cur_nodes(&self) -> &[T] {
+        &self.nodes[..]
+    }
+
+    fn get_length(&self) -> &T {
+        &self.length
+    }
+
+    fn get_node_step(&self) -> &T {
+        &self.node_step
+    }
+
+    fn get_time_step(&self) -> T {
+        self.time_step
+    }
+
+    fn reset(&mut self) -> Result<(), ModelError> {
+        self.cur_time_step = 0;
+        self.nodes = self.eval_nodes()?;
+        self.max_step_delta = T::INFINITY;
+        Ok(())
+    }
+
+    fn run_step(&mut self) -> Result<(), ModelError> {
+        self.cur_time_step += 1;
+        match self.eval_nodes() {
+            Ok(nodes) => {
+                self.max_step_delta = max_abs_difference(&nodes, &self.nodes);
+                self.nodes = nodes;
+                Ok(())
+            }
+            Err(e) => {
+                self.cur_time_step -= 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// The series is summed directly, so any time is reached instantly
+    fn set_time(&mut self, time: T) -> Result<(), ModelError> {
+        check_seek_time(time)?;
+        self.cur_time_step = (time / self.time_step).ceil().clamp(0., u32::MAX as T) as u32;
+        self.nodes = self.eval_nodes()?;
+        Ok(())
+    }
+
+    fn is_closed_form(&self) -> bool {
+        true
+    }
+
+    fn get_elapsed_time(&self) -> T {
+        self.cur_time_step as T * self.time_step
+    }
+
+    fn max_step_delta(&self) -> T {
+        self.max_step_delta
+    }
+
+    fn get_units(&self) -> &Units {
+        &self.units
+    }
+
+    fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    fn clone_box(&self) -> Box<dyn Model> {
+        Box::new(self.clone())
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        vec![
+            ("Term".to_owned(), expression_text(&self.term)),
+            ("Terms".to_owned(), self.term_count.to_string()),
+        ]
+    }
+
+    fn temperature_at(&self, x: T) -> Option<T> {
+        self.exact_at(x, self.get_elapsed_time())
+    }
+
+    fn exact_at(&self, x: T, t: T) -> Option<T> {
+        if !(0. ..=self.length).contains(&x) {
+            return None;
+        }
+        self.sum_at(t, x).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn fourier_series_of_a_constant_start() {
+        // u = 1 inside, 0 at both edges: only odd n contribute 4/(nπ)
+        let term =
+            exmex::parse::<f64>("(1-cos(n*PI))*2/(n*PI)*exp(0-(n*PI)^2*t)*sin(n*PI*x)").unwrap();
+        let mut model = AnalyticSeriesModel::new(term, 99, 1., 11, 0.01).unwrap();
+        model.run_steps(5).unwrap();
+
+        let t = model.get_elapsed_time();
+        // By then every term past the first few has decayed away
+        let expected = |x: f64| {
+            (0..5)
+                .map(|k| {
+                    let n = (2 * k + 1) as f64;
+                    4. / (n * PI) * (-(n * PI).powi(2) * t).exp() * (n * PI * x).sin()
+                })
+                .sum::<f64>()
+        };
+        for (i, u) in model.get_cur_nodes().iter().enumerate() {
+            let x = i as f64 / 10.;
+            assert!((u - expected(x)).abs() < 1e-9, "x = {}", x);
+        }
+        assert!(model.temperature_at(0.5).unwrap() > 0.5);
+    }
+
+    #[test]
+    fn terms_without_time() {
+        let term = exmex::parse::<f64>("x^n").unwrap();
+        let model = AnalyticSeriesModel::new(term, 3, 1., 3, 1.).unwrap();
+        assert_eq!(model.get_cur_nodes(), &[0., 0.5 + 0.25 + 0.125, 3.]);
+
+        let term = exmex::parse::<f64>("y*n").unwrap();
+        assert!(matches!(
+            AnalyticSeriesModel::new(term, 3, 1., 3, 1.),
+            Err(ModelError::Parameter(_))
+        ));
+    }
+}
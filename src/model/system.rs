@@ -1,45 +1,120 @@
+use crate::model::differential::CoefficientStencil;
+use crate::model::error::ModelError;
 use crate::model::model::*;
 
 use exmex::prelude::*;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 extern crate lapack;
 extern crate netlib_src;
 
+/// How a `SystemModel` steps in time
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ImplicitScheme {
+    /// `u[n+1] - u[n] = dt·a²·D·(sigma·u[n+1] + (1 - sigma)·u[n])`, 0.5 is Crank–Nicolson
+    /// and 1 is implicit Euler. Unconditionally stable for sigma of at least 0.5
+    #[default]
+    Theta,
+    /// `(3u[n+1] - 4u[n] + u[n-1]) / 2dt = a²·u_xx[n+1]`, L-stable so large steps don't ring.
+    /// The first step is implicit Euler
+    BDF2,
+}
+
+/// How a `SystemModel` discretizes the rod
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SystemOptions {
+    pub node_count: u32,
+    pub time_step: f64,
+    /// Weight of the new step in the theta scheme's second difference, 1 is implicit Euler
+    pub sigma: f64,
+    pub scheme: ImplicitScheme,
+    pub coefficient_stencil: CoefficientStencil,
+}
+
+impl SystemOptions {
+    /// Crank–Nicolson on `node_count` nodes
+    pub fn new(node_count: u32, time_step: f64) -> Self {
+        Self {
+            node_count,
+            time_step,
+            sigma: 0.5,
+            scheme: ImplicitScheme::default(),
+            coefficient_stencil: CoefficientStencil::default(),
+        }
+    }
+}
+
+/// a² between every inner node and its (west, east) neighbour, either the one at the node
+/// on both sides or the ones at the faces halfway to the neighbours
+#[derive(Clone)]
+struct SideA2 {
+    west: Vec<f64>,
+    east: Vec<f64>,
+}
+
+/// LU factors of the implicit step matrix as returned by `dgttrf`
+#[derive(Clone)]
+struct Factorization {
+    /// Time step the matrix was built for
+    dt: f64,
+    a2: SideA2,
+    dl: Vec<f64>,
+    d: Vec<f64>,
+    du: Vec<f64>,
+    du2: Vec<f64>,
+    ipiv: Vec<i32>,
+}
+
+#[derive(Clone)]
 pub struct SystemModel {
-    starting_conditions: exmex::FlatEx<f64>,
+    starting_conditions: InitialCondition,
     left_edge_conditions: exmex::FlatEx<f64>,
     right_edge_conditions: exmex::FlatEx<f64>,
     coefficient: exmex::FlatEx<f64>,
     sigma: f64,
+    scheme: ImplicitScheme,
+    coefficient_stencil: CoefficientStencil,
 
     length: f64,
     time_step: f64,
     node_step: f64,
     nodes: Vec<f64>,
     cur_time_step: u32,
+    max_step_delta: f64,
+    units: Units,
+    /// Unless the coefficient depends on time the matrix is factored once on the first step
+    factorization: Option<Factorization>,
+    /// Nodes one step back, kept by BDF2
+    previous: Option<Vec<f64>>,
 }
 
 impl SystemModel {
     pub fn new(
-        starting_conditions: exmex::FlatEx<f64>,
+        starting_conditions: InitialCondition,
         left_edge_conditions: exmex::FlatEx<f64>,
         right_edge_conditions: exmex::FlatEx<f64>,
         coefficient: exmex::FlatEx<f64>,
-        sigma: f64,
         length: f64,
-        node_count: u32,
-        time_step: f64,
-    ) -> Self {
+        options: SystemOptions,
+    ) -> Result<Self, ModelError> {
+        let SystemOptions {
+            node_count,
+            time_step,
+            sigma,
+            scheme,
+            coefficient_stencil,
+        } = options;
+        check_node_count(node_count)?;
         let node_step = length / (node_count as f64 - 1.);
         let mut nodes = Vec::with_capacity(node_count as usize);
-        nodes.push(left_edge_conditions.eval(&[0.]).unwrap());
-        nodes.append(
-            &mut (1..node_count - 1)
-                .map(|i| starting_conditions.eval(&[node_step * i as f64]).unwrap())
-                .collect(),
-        );
-        nodes.push(right_edge_conditions.eval(&[0.]).unwrap());
-        Self {
+        nodes.push(left_edge_conditions.eval(&[0.])?);
+        for i in 1..node_count - 1 {
+            nodes.push(starting_conditions.eval(node_step * i as f64)?);
+        }
+        nodes.push(right_edge_conditions.eval(&[0.])?);
+        let positions: Vec<f64> = (0..node_count).map(|i| node_step * i as f64).collect();
+        check_initial_nodes(&positions, &nodes)?;
+        Ok(Self {
             node_step,
             coefficient,
             left_edge_conditions,
@@ -49,117 +124,241 @@ impl SystemModel {
             time_step,
             nodes,
             sigma,
+            scheme,
+            coefficient_stencil,
             cur_time_step: 0,
-        }
+            max_step_delta: f64::INFINITY,
+            units: Units::default(),
+            factorization: None,
+            previous: None,
+        })
     }
 
-    fn restore_node_value(&self, node_num: u32) -> f64 {
+    fn restore_node_value(&self, node_num: u32) -> Result<f64, ModelError> {
         if node_num == 0 {
-            self.left_edge_conditions.eval(&[0.]).unwrap()
+            Ok(self.left_edge_conditions.eval(&[0.])?)
         } else if node_num == self.nodes.len() as u32 - 1 {
-            self.right_edge_conditions.eval(&[0.]).unwrap()
+            Ok(self.right_edge_conditions.eval(&[0.])?)
         } else {
             self.starting_conditions
-                .eval(&[self.node_step * node_num as f64])
-                .unwrap()
+                .eval(self.node_step * node_num as f64)
         }
     }
 
-    fn get_node_value(&self, node_num: u32) -> f64 {
+    fn get_node_value(&self, node_num: u32, a2: &SideA2) -> Result<f64, ModelError> {
         let time = self.cur_time_step as f64 * self.time_step;
         if node_num == 0 {
-            self.left_edge_conditions.eval(&[time]).unwrap()
+            Ok(self.left_edge_conditions.eval(&[time])?)
         } else if node_num == self.nodes.len() as u32 - 1 {
-            self.right_edge_conditions.eval(&[time]).unwrap()
+            Ok(self.right_edge_conditions.eval(&[time])?)
         } else {
-            let ai = self
-                .coefficient
-                .eval(&[self.node_step * node_num as f64])
-                .unwrap();
-
-            let a2 = ai * ai;
+            let i = node_num as usize;
+            let (west, east) = (a2.west[i - 1], a2.east[i - 1]);
             let h2 = self.node_step * self.node_step;
 
-            let res = a2 * self.time_step / h2
-                * (self.nodes[(node_num - 1) as usize] - 2. * self.nodes[node_num as usize]
-                    + self.nodes[(node_num + 1) as usize])
-                + self.nodes[node_num as usize];
-            res
+            let res = self.time_step / h2
+                * (west * (self.nodes[i - 1] - self.nodes[i])
+                    + east * (self.nodes[i + 1] - self.nodes[i]))
+                + self.nodes[i];
+            Ok(res)
         }
     }
-}
 
-impl Model for SystemModel {
-    fn get_length(&self) -> &f64 {
-        &self.length
+    /// a² on either side of every inner node at `time`
+    fn eval_a2(&self, time: f64) -> Result<SideA2, ModelError> {
+        let a2 = |x: f64| eval_coefficient(&self.coefficient, x, time).map(|a| a * a);
+        let inner = 1..self.nodes.len() - 1;
+        match self.coefficient_stencil {
+            CoefficientStencil::Faces => {
+                // Face j lies between nodes j and j + 1
+                let faces = (0..self.nodes.len() - 1)
+                    .map(|j| a2(self.node_step * (j as f64 + 0.5)))
+                    .collect::<Result<Vec<f64>, ModelError>>()?;
+                Ok(SideA2 {
+                    west: faces[..faces.len() - 1].to_vec(),
+                    east: faces[1..].to_vec(),
+                })
+            }
+            CoefficientStencil::Nodes => {
+                let nodes = inner
+                    .map(|i| a2(self.node_step * i as f64))
+                    .collect::<Result<Vec<f64>, ModelError>>()?;
+                Ok(SideA2 {
+                    west: nodes.clone(),
+                    east: nodes,
+                })
+            }
+        }
     }
 
-    fn reset(&mut self) {
-        let nodes = (0..self.nodes.len())
-            .into_par_iter()
-            .map(|i| self.restore_node_value(i as u32))
+    /// LU factors of `I - dt·D`, D being the second difference with `a2` over the inner nodes
+    fn factorize(&self, a2: SideA2, dt: f64) -> Result<Factorization, ModelError> {
+        let th = dt / (self.node_step * self.node_step);
+        let n = a2.west.len();
+        // Row k couples inner node k to k - 1 through its west a² and to k + 1 through its east one
+        let mut dl: Vec<f64> = a2.west[1..].iter().map(|w| -th * w).collect();
+        let mut d: Vec<f64> = a2
+            .west
+            .iter()
+            .zip(&a2.east)
+            .map(|(w, e)| th * (w + e) + 1.)
             .collect();
+        let mut du: Vec<f64> = a2.east[..n - 1].iter().map(|e| -th * e).collect();
+        let mut du2 = vec![0.; n.saturating_sub(2)];
+        let mut ipiv = vec![0; n];
 
-        self.cur_time_step = 0;
+        let mut info = 0;
+        unsafe {
+            lapack::dgttrf(
+                n as i32, &mut dl, &mut d, &mut du, &mut du2, &mut ipiv, &mut info,
+            );
+        }
+        if info != 0 {
+            return Err(ModelError::Solver(format!(
+                "dgttrf failed, info = {}",
+                info
+            )));
+        }
 
-        self.nodes = nodes;
+        Ok(Factorization {
+            dt,
+            a2,
+            dl,
+            d,
+            du,
+            du2,
+            ipiv,
+        })
     }
 
-    fn run_step(&mut self) {
-        self.cur_time_step += 1;
-
-        let th = self.time_step / (self.node_step * self.node_step);
-        let mut dl: Vec<f64> = (1..self.nodes.len() - 1)
-            .map(|i| {
-                let a = self.coefficient.eval(&[self.node_step * i as f64]).unwrap();
-                -th * a * a
-            })
-            .collect();
-        // dl.insert(0, 0.);
-
-        let mut d: Vec<f64> = (1..self.nodes.len() - 1)
-            .map(|i| {
-                let a = self.coefficient.eval(&[self.node_step * i as f64]).unwrap();
-                2. * th * a * a + 1.
-            })
-            .collect();
+    fn solve_step(&mut self) -> Result<(), ModelError> {
+        if self.scheme == ImplicitScheme::BDF2 {
+            return self.bdf2_step();
+        }
+        if uses_time(&self.coefficient) {
+            // The implicit part is taken at the new time and the explicit part at the old one
+            let time = self.cur_time_step as f64 * self.time_step;
+            let f = self.factorize(self.eval_a2(time)?, self.sigma * self.time_step)?;
+            let explicit_a2 = self.eval_a2(time - self.time_step)?;
+            return self.solve_with(&f, &explicit_a2);
+        }
+        let f = match self.factorization.take() {
+            Some(f) => f,
+            None => self.factorize(self.eval_a2(0.)?, self.sigma * self.time_step)?,
+        };
+        let res = self.solve_with(&f, &f.a2);
+        self.factorization = Some(f);
+        res
+    }
 
-        let mut du: Vec<f64> = (1..self.nodes.len() - 1)
-            .map(|i| {
-                let a = self.coefficient.eval(&[self.node_step * i as f64]).unwrap();
-                -th * a * a
-            })
-            .collect();
+    /// The explicit `(1 - sigma)` part of the second difference goes into the right-hand side,
+    /// `f` is factored for `sigma·dt`
+    fn solve_with(&mut self, f: &Factorization, explicit_a2: &SideA2) -> Result<(), ModelError> {
+        let rhs = (0..self.nodes.len())
+            .into_par_iter()
+            .map(|i| self.get_node_value(i as u32, explicit_a2))
+            .zip(self.nodes.par_iter())
+            .map(|(a, u)| Ok(u + (1. - self.sigma) * (a? - u)))
+            .collect::<Result<_, ModelError>>()?;
+        let nodes = self.solve_implicit(f, rhs)?;
+        self.max_step_delta = max_abs_difference(&nodes, &self.nodes);
+        self.nodes = nodes;
+        Ok(())
+    }
 
+    /// `(I - dt·a²·D)·u[n+1] = rhs` with the edges held at their conditions at the new time
+    fn solve_implicit(&self, f: &Factorization, mut b: Vec<f64>) -> Result<Vec<f64>, ModelError> {
         let time = self.cur_time_step as f64 * self.time_step;
-        let mut b = self.nodes.clone();
-        b[0] -= self.left_edge_conditions.eval(&[time]).unwrap();
-        b[self.nodes.len() - 1] -= self.right_edge_conditions.eval(&[time]).unwrap();
+        let last = b.len() - 1;
+        let th = f.dt / (self.node_step * self.node_step);
+        b[0] = self.left_edge_conditions.eval(&[time])?;
+        b[last] = self.right_edge_conditions.eval(&[time])?;
+        b[1] += th * f.a2.west[0] * b[0];
+        b[last - 1] += th * f.a2.east[last - 2] * b[last];
 
+        let mut info = 0;
         unsafe {
-            let mut info = 0;
-            lapack::dgtsv(
-                self.nodes.len() as i32 - 2,
+            lapack::dgttrs(
+                b'N',
+                last as i32 - 1,
                 1,
-                &mut dl,
-                &mut d,
-                &mut du,
-                &mut b[1..self.nodes.len() - 1],
-                self.nodes.len() as i32 - 2,
+                &f.dl,
+                &f.d,
+                &f.du,
+                &f.du2,
+                &f.ipiv,
+                &mut b[1..last],
+                last as i32 - 1,
                 &mut info,
             );
+        }
+        if info != 0 {
+            return Err(ModelError::Solver(format!(
+                "dgttrs failed, info = {}",
+                info
+            )));
+        }
+        Ok(b)
+    }
 
-            if info != 0 {
-                panic!("Info != 0");
+    fn bdf2_step(&mut self) -> Result<(), ModelError> {
+        // (3u[n+1] - 4u[n] + u[n-1]) / 2dt = a²·D·u[n+1] is an implicit Euler step
+        // of 2dt/3 from (4u[n] - u[n-1]) / 3
+        let (rhs, dt) = match &self.previous {
+            Some(previous) => (
+                self.nodes
+                    .iter()
+                    .zip(previous)
+                    .map(|(u, v)| (4. * u - v) / 3.)
+                    .collect(),
+                2. / 3. * self.time_step,
+            ),
+            None => (self.nodes.clone(), self.time_step),
+        };
+
+        let f = match self.factorization.take() {
+            Some(f) if f.dt == dt && !uses_time(&self.coefficient) => f,
+            _ => {
+                let time = self.cur_time_step as f64 * self.time_step;
+                self.factorize(self.eval_a2(time)?, dt)?
             }
-        }
+        };
+        let res = self.solve_implicit(&f, rhs);
+        self.factorization = Some(f);
 
-        self.nodes = (0..self.nodes.len())
+        let nodes = res?;
+        self.max_step_delta = max_abs_difference(&nodes, &self.nodes);
+        self.previous = Some(std::mem::replace(&mut self.nodes, nodes));
+        Ok(())
+    }
+}
+
+impl Model for SystemModel {
+    fn get_length(&self) -> &f64 {
+        &self.length
+    }
+
+    fn reset(&mut self) -> Result<(), ModelError> {
+        let nodes = (0..self.nodes.len())
             .into_par_iter()
-            .map(|i| self.get_node_value(i as u32))
-            .zip(b.par_iter())
-            .map(|(a, b)| self.sigma * b + (1. - self.sigma) * a)
-            .collect();
+            .map(|i| self.restore_node_value(i as u32))
+            .collect::<Result<_, _>>()?;
+
+        self.cur_time_step = 0;
+        self.max_step_delta = f64::INFINITY;
+        self.previous = None;
+
+        self.nodes = nodes;
+        Ok(())
+    }
+
+    fn run_step(&mut self) -> Result<(), ModelError> {
+        self.cur_time_step += 1;
+        let res = self.solve_step();
+        if res.is_err() {
+            self.cur_time_step -= 1;
+        }
+        res
     }
 
     fn get_cur_nodes(&self) -> &[f64] {
@@ -170,7 +369,259 @@ impl Model for SystemModel {
         &self.node_step
     }
 
+    fn get_time_step(&self) -> f64 {
+        self.time_step
+    }
+
     fn get_elapsed_time(&self) -> f64 {
         self.cur_time_step as f64 * self.time_step
     }
+
+    fn max_step_delta(&self) -> f64 {
+        self.max_step_delta
+    }
+
+    fn get_units(&self) -> &Units {
+        &self.units
+    }
+
+    fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    fn clone_box(&self) -> Box<dyn Model> {
+        Box::new(self.clone())
+    }
+
+    fn reseed_edges(&mut self, left: FlatEx<f64>, right: FlatEx<f64>) -> Result<(), ModelError> {
+        let time = self.get_elapsed_time();
+        let (left_value, right_value) = eval_edges(&left, &right, time)?;
+        let last = self.nodes.len() - 1;
+        self.nodes[0] = left_value;
+        self.nodes[last] = right_value;
+        self.left_edge_conditions = left;
+        self.right_edge_conditions = right;
+        Ok(())
+    }
+
+    fn set_nodes(&mut self, nodes: &[f64]) -> Result<(), ModelError> {
+        check_set_nodes(nodes, self.nodes.len())?;
+        self.nodes = nodes.to_vec();
+        // BDF2 would otherwise extrapolate from a step that never led here
+        self.previous = None;
+        self.max_step_delta = f64::INFINITY;
+        Ok(())
+    }
+
+    fn details(&self) -> Vec<(String, String)> {
+        vec![
+            ("Start".to_owned(), self.starting_conditions.describe()),
+            (
+                "Left edge".to_owned(),
+                expression_text(&self.left_edge_conditions),
+            ),
+            (
+                "Right edge".to_owned(),
+                expression_text(&self.right_edge_conditions),
+            ),
+            ("a".to_owned(), expression_text(&self.coefficient)),
+            ("Scheme".to_owned(), format!("{:?}", self.scheme)),
+            ("Sigma".to_owned(), self.sigma.to_string()),
+            (
+                "Coefficient stencil".to_owned(),
+                format!("{:?}", self.coefficient_stencil),
+            ),
+        ]
+    }
+
+    fn boundary_flux(&self) -> Option<(f64, f64)> {
+        let time = self.get_elapsed_time();
+        let a2 = |x: f64| eval_coefficient(&self.coefficient, x, time).map_or(f64::NAN, |a| a * a);
+        Some(edge_flux(
+            &self.nodes,
+            (self.node_step, self.node_step),
+            (a2(0.), a2(self.length)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn implicit_follows_analytic_decay() {
+        let mut model = SystemModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>("100*sin(PI*x/200)").unwrap())),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("1+0*x").unwrap(),
+            200.,
+            SystemOptions {
+                sigma: 1.,
+                ..SystemOptions::new(100, 1.)
+            },
+        )
+        .unwrap();
+        model.run_steps(500).unwrap();
+
+        let t = model.get_elapsed_time();
+        let node_step = *model.get_node_step();
+        for (i, u) in model.get_cur_nodes().iter().enumerate() {
+            let x = i as f64 * node_step;
+            let expected = 100. * (-(PI / 200.) * (PI / 200.) * t).exp() * (PI * x / 200.).sin();
+            assert!((u - expected).abs() < 1e-2, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn too_few_nodes_is_an_error() {
+        let make = |node_count| {
+            SystemModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("sin(PI*x)").unwrap())),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("1+0*x").unwrap(),
+                1.,
+                SystemOptions {
+                    sigma: 1.,
+                    ..SystemOptions::new(node_count, 1e-3)
+                },
+            )
+        };
+        for node_count in 0..3 {
+            assert!(matches!(make(node_count), Err(ModelError::Parameter(_))));
+        }
+        let mut model = make(3).unwrap();
+        model.run_steps(10).unwrap();
+        assert_eq!(model.get_cur_nodes().len(), 3);
+    }
+
+    #[test]
+    fn hot_edge_steady_flux() {
+        // u(0) = 100 and u(1) = 0 settle into a straight line, heat flows in on the left
+        // and out on the right. a²·dt/h² = 1 is past the explicit limit, Crank–Nicolson
+        // has to stay stable there as well
+        for sigma in [1., 0.5] {
+            let mut model = SystemModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+                exmex::parse::<f64>("100+0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("1+0*x").unwrap(),
+                1.,
+                SystemOptions {
+                    sigma,
+                    ..SystemOptions::new(11, 0.01)
+                },
+            )
+            .unwrap();
+            model.run_steps(2000).unwrap();
+
+            for (x, u) in model.get_node_positions().iter().zip(model.get_cur_nodes()) {
+                assert!(
+                    (u - 100. * (1. - x)).abs() < 1e-6,
+                    "sigma {}, x = {}: {}",
+                    sigma,
+                    x,
+                    u
+                );
+            }
+            let (left, right) = model.boundary_flux().unwrap();
+            assert!((left - 100.).abs() < 1e-6, "sigma {}, left {}", sigma, left);
+            assert!(
+                (right + 100.).abs() < 1e-6,
+                "sigma {}, right {}",
+                sigma,
+                right
+            );
+        }
+    }
+
+    fn make_bdf2(start: &str, left: &str, node_count: u32, time_step: f64) -> SystemModel {
+        SystemModel::new(
+            InitialCondition::Expr(Box::new(exmex::parse::<f64>(start).unwrap())),
+            exmex::parse::<f64>(left).unwrap(),
+            exmex::parse::<f64>("0*t").unwrap(),
+            exmex::parse::<f64>("1+0*x").unwrap(),
+            1.,
+            SystemOptions {
+                sigma: 1.,
+                scheme: ImplicitScheme::BDF2,
+                ..SystemOptions::new(node_count, time_step)
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bdf2_is_second_order_in_time() {
+        // Against a run with a much smaller step on the same grid, so only the time error is left
+        let at = |time_step: f64| {
+            let mut model = make_bdf2("sin(PI*x)", "0*t", 21, time_step);
+            model.set_time(0.1).unwrap();
+            model.get_cur_nodes().to_vec()
+        };
+        let reference = at(1e-5);
+        let error = |time_step| max_abs_difference(&at(time_step), &reference);
+
+        let (coarse, fine) = (error(0.01), error(0.005));
+        let order = (coarse / fine).log2();
+        assert!(
+            order > 1.8,
+            "errors {} and {}, order {}",
+            coarse,
+            fine,
+            order
+        );
+    }
+
+    #[test]
+    fn bdf2_large_steps_reach_steady_state_without_ringing() {
+        // dt·a²/h² = 1000, the edges jump from 0 to 100 on the left
+        let mut model = make_bdf2("0*x", "100+0*t", 11, 10.);
+        for _ in 0..20 {
+            model.run_step().unwrap();
+            let (min, max) = model.value_range();
+            assert!(min > -1e-9 && max < 100. + 1e-9, "range {} to {}", min, max);
+        }
+        for (i, u) in model.get_cur_nodes().iter().enumerate() {
+            let expected = 100. * (1. - i as f64 / 10.);
+            assert!((u - expected).abs() < 1e-6, "node {}: {}", i, u);
+        }
+
+        model.reset().unwrap();
+        assert!(model.previous.is_none());
+    }
+
+    #[test]
+    fn faces_reach_the_divergence_form_steady_state() {
+        // (a²·u_x)_x = 0 with a² = 1 + x, u(0) = 1 and u(1) = 0 is u = 1 - ln(1 + x) / ln 2,
+        // a²·u_xx = 0 is the straight line instead
+        let steady = |stencil| {
+            let mut model = SystemModel::new(
+                InitialCondition::Expr(Box::new(exmex::parse::<f64>("0*x").unwrap())),
+                exmex::parse::<f64>("1+0*t").unwrap(),
+                exmex::parse::<f64>("0*t").unwrap(),
+                exmex::parse::<f64>("sqrt(1+x)").unwrap(),
+                1.,
+                SystemOptions {
+                    scheme: ImplicitScheme::BDF2,
+                    coefficient_stencil: stencil,
+                    ..SystemOptions::new(21, 1.)
+                },
+            )
+            .unwrap();
+            model.run_steps(50).unwrap();
+            model
+        };
+        let exact = |x: f64| 1. - (1. + x).ln() / 2f64.ln();
+
+        let faces = steady(CoefficientStencil::Faces);
+        for (x, u) in faces.get_node_positions().iter().zip(faces.get_cur_nodes()) {
+            assert!((u - exact(*x)).abs() < 1e-4, "x = {}: {}", x, u);
+        }
+        let nodes = steady(CoefficientStencil::Nodes);
+        assert!((nodes.temperature_at(0.5).unwrap() - 0.5).abs() < 1e-6);
+    }
 }
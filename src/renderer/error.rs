@@ -2,13 +2,36 @@ use std::fmt::Display;
 
 #[derive(Debug)]
 pub enum Error {
-    GlError(String),
+    Gl(String),
     ShaderCompilation(String),
     ShaderLinking(String),
     InvalidBuffer(String),
     BatchFull,
+    /// A plain message, also what string errors such as SDL's turn into
     At(String),
     UnknownUniform(String),
+    PhysicsThread(String),
+    Io(std::io::Error),
+    Nul(std::ffi::NulError),
+    Json(serde_json::Error),
+    Image(image::ImageError),
+    WindowBuild(sdl2::video::WindowBuildError),
+    /// `cause` with where it happened, added by `call!`
+    Context(String, Box<Error>),
+}
+
+impl Error {
+    /// The error under every `Context` layer
+    pub fn cause(&self) -> &Error {
+        match self {
+            Self::Context(_, cause) => cause.cause(),
+            e => e,
+        }
+    }
+
+    pub fn context(self, context: String) -> Self {
+        Self::Context(context, Box::new(self))
+    }
 }
 
 impl Display for Error {
@@ -17,35 +40,79 @@ impl Display for Error {
             f,
             "{}",
             match self {
-                Self::GlError(e) => format!("GL_ERROR - {}", e),
+                Self::Gl(e) => format!("GL_ERROR - {}", e),
                 Self::ShaderCompilation(e) => format!("SHADER_COMPILE - {}", e),
                 Self::ShaderLinking(e) => format!("SHADER_LINKING - {}", e),
                 Self::InvalidBuffer(e) => format!("INVALID_BUFFER - {}", e),
-                Self::BatchFull => format!("BATCH_FULL"),
-                Self::At(e) => format!("{}", e),
+                Self::BatchFull => "BATCH_FULL".to_owned(),
+                Self::At(e) => e.clone(),
                 Self::UnknownUniform(e) => format!("UNKNOWN_UNIFORM {}", e),
+                Self::PhysicsThread(e) => format!("PHYSICS_THREAD - {}", e),
+                Self::Io(e) => format!("IO - {}", e),
+                Self::Nul(e) => format!("NUL - {}", e),
+                Self::Json(e) => format!("JSON - {}", e),
+                Self::Image(e) => format!("IMAGE - {}", e),
+                Self::WindowBuild(e) => format!("WINDOW_BUILD - {}", e),
+                Self::Context(context, cause) => format!("{}\n{}", cause, context),
             }
         )
     }
 }
 
+impl std::error::Error for Error {}
+
+impl From<String> for Error {
+    fn from(e: String) -> Self {
+        Self::At(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<std::ffi::NulError> for Error {
+    fn from(e: std::ffi::NulError) -> Self {
+        Self::Nul(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+impl From<sdl2::video::WindowBuildError> for Error {
+    fn from(e: sdl2::video::WindowBuildError) -> Self {
+        Self::WindowBuild(e)
+    }
+}
+
 #[macro_export]
 macro_rules! gl_call {
     ($func:expr) => {{
-        unsafe {
-            let res = $func;
-            let err = gl::GetError();
-            if err != 0 {
-                Err(Error::GlError(format!(
-                    "[{}] at {}, {}, line {}",
-                    err,
-                    stringify!($func),
-                    file!(),
-                    line!()
-                )))
-            } else {
-                Ok(res)
-            }
+        // Every GL function is unsafe, wrapping the call in `unsafe` is what the macro is for
+        #[allow(clippy::macro_metavars_in_unsafe)]
+        let (res, err) = unsafe { ($func, gl::GetError()) };
+        if err != 0 {
+            Err(Error::Gl(format!(
+                "[{}] at {}, {}, line {}",
+                err,
+                stringify!($func),
+                file!(),
+                line!()
+            )))
+        } else {
+            Ok(res)
         }
     }};
 }
@@ -55,9 +122,8 @@ macro_rules! call {
     ($func:expr) => {{
         match $func {
             Ok(res) => Ok(res),
-            Err(e) => Err(Error::At(format!(
-                "{}\nAt {}, {}, line {}",
-                e,
+            Err(e) => Err(Error::from(e).context(format!(
+                "At {}, {}, line {}",
                 stringify!($func),
                 file!(),
                 line!()
@@ -1,4 +1,6 @@
 pub mod error;
+#[allow(clippy::module_inception)]
 pub mod renderer;
 pub mod shader;
+pub mod texture;
 pub mod vertex;
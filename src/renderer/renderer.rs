@@ -1,16 +1,17 @@
 use super::{
     error::Error,
     shader::Shader,
-    vertex::{get_type_size, Buffer, IndexBuffer, VertexBuffer, VertexLayout},
+    vertex::{get_type_size, Buffer, IndexBuffer, VertexArray, VertexBuffer, VertexLayout},
 };
 use crate::{call, gl_call};
 use egui_sdl2_gl::gl;
 
 use core::ffi::c_void;
+use num_traits::PrimInt;
 
+/// `vao` has to have been set up with `indices`
 pub fn draw(
-    layout: &VertexLayout,
-    vertices: &VertexBuffer,
+    vao: &VertexArray,
     indices: &IndexBuffer,
     shader: &Shader,
     primitive: u32,
@@ -18,9 +19,7 @@ pub fn draw(
     from_index: i32,
 ) -> Result<(), Error> {
     call!(shader.bind())?;
-    call!(vertices.bind())?;
-    call!(indices.bind())?;
-    call!(layout.bind())?;
+    call!(vao.bind())?;
     let index_type = *indices.get_index_type();
 
     gl_call!(gl::DrawElements(
@@ -29,49 +28,123 @@ pub fn draw(
         index_type,
         (from_index * get_type_size(index_type)) as *const c_void
     ))?;
-    vertices.unbind()
+    vao.unbind()
 }
 
 struct Batch<V: Clone, I: Clone> {
+    /// Declared first so it is deleted before the buffers it refers to
+    vao: VertexArray,
     vbo: VertexBuffer,
     ibo: IndexBuffer,
+    contents: BatchContents<V, I>,
+}
+
+impl<V: Clone + std::fmt::Debug, I: PrimInt + std::fmt::Debug> Batch<V, I> {
+    pub fn new(
+        layout: &VertexLayout,
+        vertices: Option<&[V]>,
+        indices: Option<&[I]>,
+        max_vertex_count: i32,
+        max_index_count: i32,
+        usage: u32,
+        index_type: u32,
+    ) -> Result<Self, Error> {
+        // The buffers get bound while they are created, so the array has to be bound first
+        let vao = call!(VertexArray::new())?;
+        call!(vao.bind())?;
+        let vbo = call!(VertexBuffer::new::<V>(
+            vertices,
+            Some(max_vertex_count),
+            usage
+        ))?;
+        let ibo = call!(IndexBuffer::new::<I>(
+            indices,
+            Some(max_index_count),
+            usage,
+            index_type
+        ))?;
+        call!(layout.bind())?;
+        call!(vao.unbind())?;
+        call!(vbo.unbind())?;
+
+        Ok(Self {
+            vao,
+            vbo,
+            ibo,
+            contents: BatchContents::new(
+                vertices.unwrap_or(&[]).len() as i32,
+                indices.unwrap_or(&[]).len() as i32,
+                max_vertex_count,
+                max_index_count,
+                layout.get_vertex_size() / std::mem::size_of::<V>() as i32,
+            ),
+        })
+    }
+
+    pub fn draw(&mut self, shader: &Shader, primitive: u32) -> Result<(), Error> {
+        let c = &mut self.contents;
+        // Binding the index buffer for the upload must not touch another vertex array
+        call!(self.vao.bind())?;
+        if !c.new_indices.is_empty() || !c.new_vertices.is_empty() {
+            call!(self
+                .vbo
+                .set_buffer_data(&c.new_vertices[..], c.current_vertex_count))?;
+            call!(self
+                .ibo
+                .set_buffer_data(&c.new_indices[..], c.current_index_count))?;
 
+            c.current_index_count += c.new_indices.len() as i32;
+            c.current_vertex_count += c.new_vertices.len() as i32;
+            c.new_vertices.clear();
+            c.new_indices.clear();
+        }
+        debug_assert!(c.new_vertices.is_empty() && c.new_indices.is_empty());
+        debug_assert!(
+            c.current_vertex_count <= c.max_vertex_count
+                && c.current_index_count <= c.max_index_count
+        );
+
+        draw(
+            &self.vao,
+            &self.ibo,
+            shader,
+            primitive,
+            c.current_index_count,
+            0,
+        )
+    }
+}
+
+/// What a batch holds and what was pushed to it since it was last drawn,
+/// kept apart from the buffers so it can be filled without a GL context
+struct BatchContents<V: Clone, I: Clone> {
     new_vertices: Vec<V>,
     new_indices: Vec<I>,
     current_index_count: i32,
     current_vertex_count: i32,
     max_index_count: i32,
     max_vertex_count: i32,
+    /// How many `V`s make up one vertex of the layout
+    elements_per_vertex: i32,
 }
 
-impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> Batch<V, I> {
+impl<V: Clone + std::fmt::Debug, I: PrimInt + std::fmt::Debug> BatchContents<V, I> {
     pub fn new(
-        vertices: Option<&[V]>,
-        indices: Option<&[I]>,
+        current_vertex_count: i32,
+        current_index_count: i32,
         max_vertex_count: i32,
         max_index_count: i32,
-        usage: u32,
-        index_type: u32,
-    ) -> Result<Self, Error> {
-        Ok(Self {
-            vbo: call!(VertexBuffer::new::<V>(
-                vertices,
-                Some(max_vertex_count),
-                usage
-            ))?,
-            ibo: call!(IndexBuffer::new::<I>(
-                indices,
-                Some(max_index_count),
-                usage,
-                index_type
-            ))?,
+        elements_per_vertex: i32,
+    ) -> Self {
+        Self {
             new_indices: vec![],
             new_vertices: vec![],
-            current_index_count: indices.unwrap_or(&[]).len() as i32,
-            current_vertex_count: vertices.unwrap_or(&[]).len() as i32,
+            current_index_count,
+            current_vertex_count,
             max_index_count,
             max_vertex_count,
-        })
+            elements_per_vertex,
+        }
     }
 
     pub fn get_empty_space(&self) -> (i32, i32) {
@@ -81,58 +154,34 @@ impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> Batch<V, I> {
         )
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.current_vertex_count == 0 && self.new_vertices.is_empty()
+    }
+
+    pub fn fits(&self, vertex_count: usize, index_count: usize) -> bool {
+        let (v, i) = self.get_empty_space();
+        has_room(v, vertex_count) && has_room(i, index_count)
+    }
+
     pub fn push(&mut self, new_vertices: &[V], new_indices: &[I]) -> Result<(), Error> {
-        if self.current_index_count + self.new_indices.len() as i32 + new_indices.len() as i32
-            >= self.max_index_count
-            || self.current_vertex_count
-                + self.new_vertices.len() as i32
-                + new_vertices.len() as i32
-                >= self.max_vertex_count
-        {
+        if !self.fits(new_vertices.len(), new_indices.len()) {
             Err(Error::BatchFull)
         } else {
+            // Indices are relative to the pushed vertices, move them past the ones already here
+            let base = (self.current_vertex_count + self.new_vertices.len() as i32)
+                / self.elements_per_vertex;
+            let base = I::from(base).ok_or(Error::BatchFull)?;
             for v in new_vertices {
                 self.new_vertices.push(v.clone());
             }
             for i in new_indices {
-                self.new_indices.push(i.clone());
+                self.new_indices.push(*i + base);
             }
 
             Ok(())
         }
     }
 
-    pub fn draw(
-        &mut self,
-        layout: &VertexLayout,
-        shader: &Shader,
-        primitive: u32,
-    ) -> Result<(), Error> {
-        if !self.new_indices.is_empty() && !self.new_vertices.is_empty() {
-            call!(self
-                .vbo
-                .set_buffer_data(&self.new_vertices[..], self.current_vertex_count))?;
-            call!(self
-                .ibo
-                .set_buffer_data(&self.new_indices[..], self.current_index_count))?;
-
-            self.current_index_count += self.new_indices.len() as i32;
-            self.current_vertex_count += self.new_vertices.len() as i32;
-            self.new_indices.clear();
-            self.new_indices.clear();
-        }
-
-        draw(
-            layout,
-            &self.vbo,
-            &self.ibo,
-            shader,
-            primitive,
-            self.current_index_count,
-            0,
-        )
-    }
-
     pub fn clear(&mut self) -> Result<(), Error> {
         self.new_vertices.clear();
         self.new_indices.clear();
@@ -144,6 +193,14 @@ impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> Batch<V, I> {
     }
 }
 
+/// Whether `count` more elements fit into `space` free ones, a batch may be filled up exactly
+fn has_room(space: i32, count: usize) -> bool {
+    count as i64 <= space as i64
+}
+
+/// Spreads pushed geometry over as many batches as needed,
+/// every push is indexed from 0 and has to fit into a single batch.
+/// Cleared batches are filled again front to back before new ones are made
 pub struct BatchRenderer<V: Clone, I: Clone> {
     layout: VertexLayout,
     batches: Vec<Batch<V, I>>,
@@ -153,7 +210,7 @@ pub struct BatchRenderer<V: Clone, I: Clone> {
     index_type: u32,
 }
 
-impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> BatchRenderer<V, I> {
+impl<V: Clone + std::fmt::Debug, I: PrimInt + std::fmt::Debug> BatchRenderer<V, I> {
     pub fn new(
         layout: VertexLayout,
         vertices: Option<&[V]>,
@@ -178,15 +235,22 @@ impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> BatchRenderer<V, I>
     }
 
     pub fn push(&mut self, vertices: &[V], indices: &[I]) -> Result<(), Error> {
-        for b in &mut self.batches {
-            let (v, i) = b.get_empty_space();
-            if v > vertices.len() as i32 && i > indices.len() as i32 {
-                call!(b.push(vertices, indices))?;
-                return Ok(());
-            }
+        if !has_room(self.max_vertices_per_batch, vertices.len())
+            || !has_room(self.max_indices_per_batch, indices.len())
+        {
+            return Err(Error::BatchFull);
+        }
+
+        if let Some(b) = self
+            .batches
+            .iter_mut()
+            .find(|b| b.contents.fits(vertices.len(), indices.len()))
+        {
+            return call!(b.contents.push(vertices, indices));
         }
 
         self.batches.push(call!(Batch::new(
+            &self.layout,
             Some(vertices),
             Some(indices),
             self.max_vertices_per_batch,
@@ -199,7 +263,7 @@ impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> BatchRenderer<V, I>
 
     pub fn draw(&mut self, shader: &Shader, primitive: u32) -> Result<(), Error> {
         for b in &mut self.batches {
-            call!(b.draw(&self.layout, shader, primitive))?;
+            call!(b.draw(shader, primitive))?;
         }
 
         Ok(())
@@ -207,9 +271,89 @@ impl<V: Clone + std::fmt::Debug, I: Clone + std::fmt::Debug> BatchRenderer<V, I>
 
     pub fn clear(&mut self) -> Result<(), Error> {
         for b in &mut self.batches {
-            call!(b.clear())?;
+            call!(b.contents.clear())?;
         }
 
         Ok(())
     }
+
+    /// Frees the buffers of empty batches past the first `high_water` ones,
+    /// call it before `clear` to keep only what the current frame needed
+    pub fn drop_empty_batches(&mut self, high_water: usize) {
+        let mut index = 0;
+        self.batches.retain(|b| {
+            index += 1;
+            index <= high_water || !b.contents.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_batch_rolls_over_without_losing_samples() {
+        // Room for exactly 10 quads of 4 vertices with 2 floats each
+        let quad = |k: usize| (vec![k as f32; 8], vec![0u16, 1, 2, 2, 3, 0]);
+        let (max_vertices, max_indices) = (10 * 8, 10 * 6);
+
+        // Pushed the way BatchRenderer::push does, a new batch starts out with the push
+        let mut batches: Vec<BatchContents<f32, u16>> = vec![];
+        for k in 0..25 {
+            let (vertices, indices) = quad(k);
+            match batches
+                .iter_mut()
+                .find(|b| b.fits(vertices.len(), indices.len()))
+            {
+                Some(b) => b.push(&vertices, &indices).unwrap(),
+                None => batches.push(BatchContents::new(
+                    vertices.len() as i32,
+                    indices.len() as i32,
+                    max_vertices,
+                    max_indices,
+                    2,
+                )),
+            }
+        }
+
+        assert_eq!(batches.len(), 3);
+        let first = &batches[0];
+        assert_eq!(first.get_empty_space(), (0, 0));
+        assert!(!first.fits(1, 0));
+        assert_eq!(first.new_vertices.first(), Some(&1.));
+        assert_eq!(first.new_vertices.last(), Some(&9.));
+        assert_eq!(first.new_indices.last(), Some(&36));
+
+        // Quad 10 went into the new batch with its buffers, the next one follows it
+        let second = &batches[1];
+        assert_eq!(second.current_vertex_count, 8);
+        assert_eq!(second.new_vertices.first(), Some(&11.));
+        assert_eq!(second.new_indices[..6], [4, 5, 6, 6, 7, 4]);
+
+        let total: i32 = batches
+            .iter()
+            .map(|b| b.current_vertex_count + b.new_vertices.len() as i32)
+            .sum();
+        assert_eq!(total, 25 * 8);
+    }
+
+    #[test]
+    fn batch_fills_up_exactly() {
+        let max = u16::MAX as i32;
+        let mut space = max;
+
+        // Pushes that exactly fill the batch go in...
+        for push in [max as usize - 600, 594, 6] {
+            assert!(has_room(space, push));
+            space -= push as i32;
+        }
+        assert_eq!(space, 0);
+        // ...and the next vertex has to roll over into a new one
+        assert!(!has_room(space, 1));
+        assert!(has_room(space, 0));
+
+        assert!(has_room(max, max as usize));
+        assert!(!has_room(max, max as usize + 1));
+    }
 }
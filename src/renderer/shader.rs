@@ -55,10 +55,9 @@ impl Shader {
                         buffer.as_mut_ptr() as *mut gl::types::GLchar
                     ))?;
 
-                    return Err(Error::ShaderCompilation(format!(
-                        "{}",
-                        str::from_utf8(&buffer[..]).unwrap_or("Unknown")
-                    )));
+                    return Err(Error::ShaderCompilation(
+                        str::from_utf8(&buffer[..]).unwrap_or("Unknown").to_owned(),
+                    ));
                 } else {
                     return Err(Error::ShaderCompilation("Unknown".to_owned()));
                 }
@@ -100,19 +99,14 @@ impl Shader {
                     buffer.as_mut_ptr() as *mut gl::types::GLchar
                 ))?;
 
-                return Err(Error::ShaderLinking(format!(
-                    "{}",
-                    str::from_utf8(&buffer[..]).unwrap_or("Unknown")
-                )));
+                Err(Error::ShaderLinking(
+                    str::from_utf8(&buffer[..]).unwrap_or("Unknown").to_owned(),
+                ))
             } else {
-                return Err(Error::ShaderLinking("Unknown".to_owned()));
+                Err(Error::ShaderLinking("Unknown".to_owned()))
             }
         }
     }
-
-    pub fn get_program(&self) -> Option<&gl::types::GLenum> {
-        Some(&self.program)
-    }
 }
 
 use super::error::Error;
@@ -122,36 +116,77 @@ impl Shader {
         gl_call!(gl::UseProgram(self.program))
     }
 
-    pub fn unbind(&self) -> Result<(), Error> {
-        gl_call!(gl::UseProgram(0))
+    fn get_uniform_location(&mut self, uniform_name: &str) -> Result<gl::types::GLint, Error> {
+        if let Some(uniform_location) = self.uniforms.get(uniform_name) {
+            return Ok(*uniform_location);
+        }
+
+        let c_str = call!(CString::new(uniform_name.as_bytes()))?;
+        let uniform_location = gl_call!(gl::GetUniformLocation(self.program, c_str.as_ptr()))?;
+        if uniform_location == -1 {
+            Err(Error::UnknownUniform(uniform_name.to_owned()))
+        } else {
+            self.uniforms
+                .insert(uniform_name.to_owned(), uniform_location);
+            Ok(uniform_location)
+        }
     }
 
     pub fn set_uniform4x4(&mut self, uniform_name: &str, mat: &Matrix4<f32>) -> Result<(), Error> {
         call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::UniformMatrix4fv(
+            uniform_location,
+            1,
+            gl::FALSE,
+            mat.as_slice().as_ptr(),
+        ))
+    }
 
-        if let Some(uniform_location) = self.uniforms.get(uniform_name) {
-            gl_call!(gl::UniformMatrix4fv(
-                *uniform_location,
-                1,
-                gl::FALSE,
-                mat.as_slice().as_ptr(),
-            ))
-        } else {
-            let c_str = call!(CString::new(uniform_name.as_bytes()))?;
-            let uniform_location = gl_call!(gl::GetUniformLocation(self.program, c_str.as_ptr()))?;
-            if uniform_location == -1 {
-                Err(Error::UnknownUniform(format!("{}", uniform_name)))
-            } else {
-                self.uniforms
-                    .insert(format!("{}", uniform_name), uniform_location);
-                gl_call!(gl::UniformMatrix4fv(
-                    uniform_location,
-                    1,
-                    gl::FALSE,
-                    mat.as_slice().as_ptr(),
-                ))
-            }
-        }
+    pub fn set_uniform1f(&mut self, uniform_name: &str, x: f32) -> Result<(), Error> {
+        call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::Uniform1f(uniform_location, x))
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform2f(&mut self, uniform_name: &str, x: f32, y: f32) -> Result<(), Error> {
+        call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::Uniform2f(uniform_location, x, y))
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform3f(
+        &mut self,
+        uniform_name: &str,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> Result<(), Error> {
+        call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::Uniform3f(uniform_location, x, y, z))
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform4f(
+        &mut self,
+        uniform_name: &str,
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    ) -> Result<(), Error> {
+        call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::Uniform4f(uniform_location, x, y, z, w))
+    }
+
+    pub fn set_uniform1i(&mut self, uniform_name: &str, x: i32) -> Result<(), Error> {
+        call!(self.bind())?;
+        let uniform_location = call!(self.get_uniform_location(uniform_name))?;
+        gl_call!(gl::Uniform1i(uniform_location, x))
     }
 }
 
@@ -0,0 +1,97 @@
+use super::error::Error;
+use crate::gl_call;
+use egui_sdl2_gl::gl;
+
+use core::ffi::c_void;
+
+/// A 2D texture of single floats, sampled as the red channel
+pub struct Texture {
+    texture: gl::types::GLuint,
+    /// (width, height) of the storage allocated by the last `TexImage2D`
+    size: (usize, usize),
+}
+
+impl Texture {
+    pub fn new() -> Result<Self, Error> {
+        let mut texture: gl::types::GLuint = 0;
+        gl_call!(gl::GenTextures(1, &mut texture))?;
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, texture))?;
+        for (parameter, value) in [
+            (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+            (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+            (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+            (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+        ] {
+            gl_call!(gl::TexParameteri(
+                gl::TEXTURE_2D,
+                parameter,
+                value as gl::types::GLint
+            ))?;
+        }
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, 0))?;
+        Ok(Self {
+            texture,
+            size: (0, 0),
+        })
+    }
+
+    /// Replaces the whole texture with `data`, `width` values per row, first row at v = 0.
+    /// The storage is only reallocated when the size changes
+    pub fn set_data(&mut self, width: usize, height: usize, data: &[f32]) -> Result<(), Error> {
+        if data.len() != width * height {
+            return Err(Error::At(format!(
+                "Texture data has {} values, expected {}x{}",
+                data.len(),
+                width,
+                height
+            )));
+        }
+        let pixels = data.as_ptr() as *const c_void;
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, self.texture))?;
+        if self.size == (width, height) {
+            gl_call!(gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RED,
+                gl::FLOAT,
+                pixels,
+            ))?;
+        } else {
+            gl_call!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R32F as gl::types::GLint,
+                width as i32,
+                height as i32,
+                0,
+                gl::RED,
+                gl::FLOAT,
+                pixels,
+            ))?;
+            self.size = (width, height);
+        }
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, 0))
+    }
+
+    pub fn bind(&self, unit: u32) -> Result<(), Error> {
+        gl_call!(gl::ActiveTexture(gl::TEXTURE0 + unit))?;
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, self.texture))
+    }
+
+    pub fn unbind(&self, unit: u32) -> Result<(), Error> {
+        gl_call!(gl::ActiveTexture(gl::TEXTURE0 + unit))?;
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, 0))
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -58,6 +58,12 @@ impl VertexLayout {
         Ok(())
     }
 
+    pub fn get_vertex_size(&self) -> i32 {
+        self.vertex_size
+    }
+
+    /// Sets up the attributes for the bound vertex buffer,
+    /// they are recorded into the bound `VertexArray`
     pub fn bind(&self) -> Result<(), Error> {
         for a in &self.attributes {
             gl_call!(gl::EnableVertexAttribArray(a.index))?;
@@ -73,13 +79,34 @@ impl VertexLayout {
 
         Ok(())
     }
+}
+
+/// Remembers a vertex layout together with the vertex and index buffers it was set up with
+pub struct VertexArray {
+    array: gl::types::GLuint,
+}
+
+impl VertexArray {
+    pub fn new() -> Result<Self, Error> {
+        let mut array: gl::types::GLuint = 0;
+        gl_call!(gl::GenVertexArrays(1, &mut array))?;
+        Ok(Self { array })
+    }
+
+    pub fn bind(&self) -> Result<(), Error> {
+        gl_call!(gl::BindVertexArray(self.array))
+    }
 
     pub fn unbind(&self) -> Result<(), Error> {
-        for a in &self.attributes {
-            gl_call!(gl::DisableVertexAttribArray(a.index))?
-        }
+        gl_call!(gl::BindVertexArray(0))
+    }
+}
 
-        Ok(())
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.array);
+        }
     }
 }
 
@@ -115,11 +142,11 @@ pub trait Buffer {
             if count.is_some() {
                 gl_call!(gl::BufferData(buffer_type, size, ptr::null(), usage))?;
 
-                if data.is_some() {
+                if let Some(data) = data {
                     gl_call!(gl::BufferSubData(
                         Self::get_buffer_type(),
                         0,
-                        (data.unwrap().len() * std::mem::size_of::<T>()) as isize,
+                        std::mem::size_of_val(data) as isize,
                         raw_data,
                     ))?;
                 }
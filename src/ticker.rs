@@ -1,12 +1,24 @@
 use std::thread;
 use std::time::{Duration, Instant};
 
+#[derive(Clone, Copy, Default)]
+pub struct TickStats {
+    pub tps: usize,
+    pub avg_tick_us: f64,
+    pub max_tick_us: f64,
+}
+
 pub struct Ticker {
     tick_start: Instant,
     min_tick_time: Duration,
+    speed_multiplier: f64,
     last_tps_measurement: Instant,
     tick_count: usize,
     tps: usize,
+    busy_time: Duration,
+    max_busy_time: Duration,
+    avg_tick_us: f64,
+    max_tick_us: f64,
 }
 
 impl Ticker {
@@ -14,9 +26,14 @@ impl Ticker {
         Self {
             tick_start: Instant::now(),
             min_tick_time,
+            speed_multiplier: 1.,
             last_tps_measurement: Instant::now(),
             tick_count: 0,
             tps: 0,
+            busy_time: Duration::ZERO,
+            max_busy_time: Duration::ZERO,
+            avg_tick_us: 0.,
+            max_tick_us: 0.,
         }
     }
 
@@ -26,22 +43,72 @@ impl Ticker {
     pub fn end_tick(&mut self) {
         let tick_end = Instant::now();
         let tick_duration = tick_end.duration_since(self.tick_start);
-        if tick_duration < self.min_tick_time {
-            thread::sleep(self.min_tick_time - tick_duration);
+        let min_tick_time = self.effective_min_tick_time();
+        if tick_duration < min_tick_time {
+            thread::sleep(min_tick_time - tick_duration);
         }
 
         self.tick_count += 1;
+        self.busy_time += tick_duration;
+        self.max_busy_time = self.max_busy_time.max(tick_duration);
         let since_last_tps_measurement = Instant::now().duration_since(self.last_tps_measurement);
         if since_last_tps_measurement.as_millis() > 1000 {
             self.tps = self.tick_count;
+            self.avg_tick_us = self.busy_time.as_secs_f64() * 1e6 / self.tick_count as f64;
+            self.max_tick_us = self.max_busy_time.as_secs_f64() * 1e6;
             self.tick_count = 0;
+            self.busy_time = Duration::ZERO;
+            self.max_busy_time = Duration::ZERO;
             self.last_tps_measurement = Instant::now();
         }
     }
     pub fn get_tps(&self) -> usize {
         self.tps
     }
+    /// Average time spent in a tick before sleeping, over the last second
+    pub fn get_avg_tick_us(&self) -> f64 {
+        self.avg_tick_us
+    }
+    /// Longest time spent in a tick before sleeping, over the last second
+    pub fn get_max_tick_us(&self) -> f64 {
+        self.max_tick_us
+    }
+    pub fn get_stats(&self) -> TickStats {
+        TickStats {
+            tps: self.get_tps(),
+            avg_tick_us: self.get_avg_tick_us(),
+            max_tick_us: self.get_max_tick_us(),
+        }
+    }
     pub fn set_min_tick_time(&mut self, t: Duration) {
         self.min_tick_time = t;
     }
+    /// 2.0 lets ticks run twice as often, 0.5 half as often.
+    /// Ticks can't run faster than the work in them, so high multipliers are capped by that
+    pub fn set_speed_multiplier(&mut self, multiplier: f64) {
+        self.speed_multiplier = multiplier;
+    }
+    /// `min_tick_time` scaled by the speed multiplier
+    pub fn effective_min_tick_time(&self) -> Duration {
+        if self.speed_multiplier > 0. {
+            self.min_tick_time.div_f64(self.speed_multiplier)
+        } else {
+            self.min_tick_time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_multiplier_scales_tick_time() {
+        let mut ticker = Ticker::new(Duration::from_millis(10));
+        assert_eq!(ticker.effective_min_tick_time(), Duration::from_millis(10));
+        ticker.set_speed_multiplier(0.5);
+        assert_eq!(ticker.effective_min_tick_time(), Duration::from_millis(20));
+        ticker.set_speed_multiplier(2.);
+        assert_eq!(ticker.effective_min_tick_time(), Duration::from_millis(5));
+    }
 }
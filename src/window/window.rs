@@ -4,7 +4,7 @@ use egui_sdl2_gl::{self, EguiStateHandler};
 
 extern crate gl;
 extern crate sdl2;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 
 use crate::renderer::error::Error;
 use crate::{call, gl_call};
@@ -19,17 +19,26 @@ pub struct Window {
     egui_state: EguiStateHandler,
     pub egui_context: CtxRef,
     pub is_running: bool,
+    mouse_position: Option<(i32, i32)>,
+    /// Samples per pixel the driver actually granted
+    multisample_samples: u8,
+    /// RGB the frame is cleared to before anything is drawn
+    clear_color: [f32; 3],
 }
 
 impl Window {
-    pub fn new(width: u32, height: u32, title: &str) -> Result<Self, Error> {
+    /// `samples` is the requested MSAA sample count. It is fixed when the context is created,
+    /// so changing it means creating the window again
+    pub fn new(width: u32, height: u32, title: &str, samples: u8) -> Result<Self, Error> {
         let sdl_context = call!(sdl2::init())?;
         let video_subsystem = call!(sdl_context.video())?;
 
         let gl_attributes = video_subsystem.gl_attr();
         gl_attributes.set_context_profile(sdl2::video::GLProfile::Core);
         gl_attributes.set_double_buffer(true);
-        gl_attributes.set_multisample_samples(4);
+        // Without a multisample buffer the sample count is ignored
+        gl_attributes.set_multisample_buffers((samples > 0) as u8);
+        gl_attributes.set_multisample_samples(samples);
         gl_attributes.set_framebuffer_srgb_compatible(true);
         // gl_attributes.set_context_version(4, 5);
 
@@ -40,6 +49,7 @@ impl Window {
             .build())?;
 
         let gl_context = call!(window.gl_create_context())?;
+        let multisample_samples = gl_attributes.multisample_samples();
         // gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
         call!(window
             .subsystem()
@@ -65,13 +75,17 @@ impl Window {
             window,
             event_pump,
             is_running: true,
+            mouse_position: None,
+            multisample_samples,
+            clear_color: [0.5, 0.5, 0.5],
         })
     }
 
     pub fn start_frame(&mut self) -> Result<(), Error> {
         self.egui_context.begin_frame(self.egui_state.input.take());
 
-        gl_call!(gl::ClearColor(0.5, 0.5, 0.5, 1.))?;
+        let [r, g, b] = self.clear_color;
+        gl_call!(gl::ClearColor(r, g, b, 1.))?;
         gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT))?;
 
         Ok(())
@@ -90,12 +104,85 @@ impl Window {
         Ok(())
     }
 
+    /// Cursor position in window coordinates, `None` if the cursor is outside the window
+    pub fn get_mouse_position(&self) -> Option<(i32, i32)> {
+        self.mouse_position
+    }
+
+    pub fn set_clear_color(&mut self, color: [f32; 3]) {
+        self.clear_color = color;
+    }
+
+    pub fn get_multisample_samples(&self) -> u8 {
+        self.multisample_samples
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        self.window.size()
+    }
+
+    pub fn save_screenshot(&self, path: &str) -> Result<(), Error> {
+        let (width, height) = self.window.drawable_size();
+        let row_size = width as usize * 4;
+        let mut pixels = vec![0u8; row_size * height as usize];
+
+        // The egui painter toggles sRGB writes around its own draws, so the bytes in the
+        // framebuffer are already display-encoded. Read them back raw, otherwise the
+        // driver would linearize them and the png would come out darker than the window.
+        let srgb_enabled = gl_call!(gl::IsEnabled(gl::FRAMEBUFFER_SRGB))? == gl::TRUE;
+        if srgb_enabled {
+            gl_call!(gl::Disable(gl::FRAMEBUFFER_SRGB))?;
+        }
+
+        // Rows of odd-sized windows must not be padded to 4 bytes
+        gl_call!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1))?;
+        gl_call!(gl::ReadBuffer(gl::BACK))?;
+        gl_call!(gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut std::os::raw::c_void,
+        ))?;
+
+        if srgb_enabled {
+            gl_call!(gl::Enable(gl::FRAMEBUFFER_SRGB))?;
+        }
+
+        // GL starts rows at the bottom, images start at the top
+        let flipped: Vec<u8> = pixels
+            .chunks_exact(row_size)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+
+        let image = image::RgbaImage::from_raw(width, height, flipped).ok_or_else(|| {
+            Error::InvalidBuffer(format!(
+                "Screenshot buffer does not fit {}x{}",
+                width, height
+            ))
+        })?;
+        call!(image.save(path))
+    }
+
     pub fn process_events(&mut self) -> Result<bool, Error> {
         if !self.is_running {
             return Ok(false);
         }
 
         for event in self.event_pump.poll_iter() {
+            match &event {
+                Event::MouseMotion { x, y, .. } => self.mouse_position = Some((*x, *y)),
+                Event::Window {
+                    win_event: WindowEvent::Leave,
+                    ..
+                } => self.mouse_position = None,
+                _ => (),
+            }
+
             match event {
                 Event::Quit { .. } => return Ok(false),
                 _ => self